@@ -13,7 +13,7 @@ pub fn compiled_n1(c: &mut Criterion) {
 
     let mut group = c.benchmark_group("continuous compiled ^a?^1a^1");
 
-    for text in (1..N).into_iter().map(|n| "a".repeat(n)) {
+    for text in (1..N).map(|n| "a".repeat(n)) {
         let text_length = text.chars().count();
         group.throughput(Throughput::Elements(text_length as u64));
         group.bench_with_input(BenchmarkId::from_parameter(text_length), &&text, |b, txt| {
@@ -27,7 +27,7 @@ pub fn interpreted_n1(c: &mut Criterion) {
 
     let mut group = c.benchmark_group("continuous interpreted ^a?^1a^1");
 
-    for text in (1..N).into_iter().map(|n| "a".repeat(n)) {
+    for text in (1..N).map(|n| "a".repeat(n)) {
         let text_length = text.chars().count();
         group.throughput(Throughput::Elements(text_length as u64));
         group.bench_with_input(BenchmarkId::from_parameter(text_length), &&text, |b, txt| {
@@ -42,7 +42,7 @@ pub fn compiled_n4(c: &mut Criterion) {
 
     let mut group = c.benchmark_group("continuous compiled ^a?^4a^4");
 
-    for text in (1..N).into_iter().map(|n| "a".repeat(n)) {
+    for text in (1..N).map(|n| "a".repeat(n)) {
         let text_length = text.chars().count();
         group.throughput(Throughput::Elements(text_length as u64));
         group.bench_with_input(BenchmarkId::from_parameter(text_length), &&text, |b, txt| {
@@ -56,7 +56,7 @@ pub fn interpreted_n4(c: &mut Criterion) {
 
     let mut group = c.benchmark_group("continuous interpreted ^a?^4a^4");
 
-    for text in (1..N).into_iter().map(|n| "a".repeat(n)) {
+    for text in (1..N).map(|n| "a".repeat(n)) {
         let text_length = text.chars().count();
         group.throughput(Throughput::Elements(text_length as u64));
         group.bench_with_input(BenchmarkId::from_parameter(text_length), &&text, |b, txt| {
@@ -71,7 +71,7 @@ pub fn compiled_n16(c: &mut Criterion) {
 
     let mut group = c.benchmark_group("continuous compiled ^a?^16a^16");
 
-    for text in (1..N).into_iter().map(|n| "a".repeat(n)) {
+    for text in (1..N).map(|n| "a".repeat(n)) {
         let text_length = text.chars().count();
         group.throughput(Throughput::Elements(text_length as u64));
         group.bench_with_input(BenchmarkId::from_parameter(text_length), &&text, |b, txt| {
@@ -85,7 +85,7 @@ pub fn interpreted_n16(c: &mut Criterion) {
 
     let mut group = c.benchmark_group("continuous interpreted ^a?^16a^16");
 
-    for text in (1..N).into_iter().map(|n| "a".repeat(n)) {
+    for text in (1..N).map(|n| "a".repeat(n)) {
         let text_length = text.chars().count();
         group.throughput(Throughput::Elements(text_length as u64));
         group.bench_with_input(BenchmarkId::from_parameter(text_length), &&text, |b, txt| {
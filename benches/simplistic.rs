@@ -1,6 +1,6 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
-const BENCH_STRING: &'static str = include_str!("../data/alice29.txt");
+const BENCH_STRING: &str = include_str!("../data/alice29.txt");
 
 use regex::Regex;
 use compiled_regex::parse_regex;
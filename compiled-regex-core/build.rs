@@ -0,0 +1,39 @@
+//! Hashes the sources of the crate, so cached generated code is not
+//! reused after the code generation changed
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::{fs, io};
+
+fn hash_dir(dir: &Path, hasher: &mut DefaultHasher) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    // Directory order differs between platforms and runs
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            hash_dir(&path, hasher)?;
+        } else {
+            path.file_name().hash(hasher);
+            fs::read(&path)?.hash(hasher);
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let mut hasher = DefaultHasher::new();
+
+    hash_dir(Path::new("src"), &mut hasher).unwrap();
+
+    println!("cargo:rerun-if-changed=src");
+    println!(
+        "cargo:rustc-env=COMPILED_REGEX_CORE_SOURCE_HASH={:016x}",
+        hasher.finish()
+    );
+}
@@ -0,0 +1,474 @@
+use crate::options::{Engine, Options, DEFAULT_VISITED_CAPACITY};
+use crate::program::{Inst, Program};
+use crate::{Bounded, CompiledRegex};
+
+use crate::ir::classes::{class_condition, ClassTables};
+use crate::ir::trie::Trie;
+use crate::ir::functions::{
+    look_condition, repeat_body_closure, repeat_step_back,
+    time_name, ProgramImplementation, CHAR_GET_FUNC_NAME,
+    INDEX_PARAM_NAME, INDEX_PARAM_TYPE, INPUT_PARAM_NAME, INPUT_PARAM_TYPE,
+};
+
+/// Storage of the backtracking stack, which holds `(instruction,
+/// position, count)` frames to resume from when a path fails
+enum Stack {
+    /// Growable `Vec`, only limited by memory
+    Heap,
+    /// Array of a fixed number of frames, running out of frames is
+    /// reported as an error
+    Fixed(usize),
+}
+
+impl Stack {
+    fn init(&self) -> String {
+        match self {
+            Stack::Heap => String::from(
+                "let mut stack: ::compiled_regex::__alloc::Vec<(usize, usize, usize)> = ::compiled_regex::__alloc::Vec::new();",
+            ),
+            Stack::Fixed(limit) => format!(
+                "let mut stack = [(0usize, 0usize, 0usize); {limit}];\nlet mut depth: usize = 0;"
+            ),
+        }
+    }
+
+    fn push(&self, frame: &str) -> String {
+        match self {
+            Stack::Heap => format!("stack.push({frame});"),
+            Stack::Fixed(limit) => format!(
+                "if depth == {limit} {{
+    return Err(::compiled_regex::StackExhausted)
+}}
+stack[depth] = {frame};
+depth += 1;"
+            ),
+        }
+    }
+
+    /// Resume from the most recent frame, or fail the whole match when
+    /// there are none left
+    fn pop(&self, count: &str) -> String {
+        match self {
+            Stack::Heap => format!(
+                "match stack.pop() {{
+    Some(frame) => (pc, at, {count}) = frame,
+    None => return false,
+}}"
+            ),
+            Stack::Fixed(_) => format!(
+                "if depth == 0 {{
+    return Ok(false)
+}}
+depth -= 1;
+(pc, at, {count}) = stack[depth];"
+            ),
+        }
+    }
+
+    fn accept(&self) -> &'static str {
+        match self {
+            Stack::Heap => "return true",
+            Stack::Fixed(_) => "return Ok(true)",
+        }
+    }
+}
+
+/// Generate a single function matching `program` anchored at the index
+/// it is given, like the root function of the recursive `parse`.
+///
+/// Instead of a function per instruction the instructions are states of
+/// a loop, and every alternative that is not taken right away is pushed
+/// onto an explicit stack of frames. The call stack use is therefore
+/// the same for every input, while the frames live on the heap, or in a
+/// fixed size array when a `stack_limit` is given.
+///
+/// With a `stack_limit` the matching function returns
+/// `Result<bool, StackExhausted>`, while the root function panics when
+/// the limit is exceeded. With `Engine::Bounded` the matching function
+/// takes the set of visited pairs, so it can be shared between the
+/// start positions of a search.
+pub fn parse(program: &Program, options: &Options) -> CompiledRegex {
+    let stack = match options.stack_limit {
+        Some(limit) => Stack::Fixed(limit),
+        None => Stack::Heap,
+    };
+    let visited_capacity = (options.engine == Engine::Bounded).then(|| {
+        options.visited_capacity.unwrap_or(DEFAULT_VISITED_CAPACITY)
+    });
+    let mut tables = ClassTables::new();
+
+    let has_repeats = program
+        .insts
+        .iter()
+        .any(|inst| matches!(inst, Inst::Repeat(_)));
+    // Only read by the counted repetitions, frames of other
+    // instructions store 0
+    let count = if has_repeats { "count" } else { "_" };
+
+    // Repetition bodies are closures shared by the state entering the
+    // repetition and its give back state
+    let mut closures = vec![];
+    let mut states = vec![];
+
+    for (i, inst) in program.insts.iter().enumerate() {
+        let state = match inst {
+            Inst::Char(x) => format!(
+                "if Self::{CHAR_GET_FUNC_NAME}({INPUT_PARAM_NAME}, &mut at) == Some('{}') {{
+    at += {};
+    pc = {};
+    true
+}} else {{
+    false
+}}",
+                x.c.escape_unicode(),
+                x.c.len_utf8(),
+                x.goto
+            ),
+            Inst::Ranges(x) => format!(
+                "match Self::{CHAR_GET_FUNC_NAME}({INPUT_PARAM_NAME}, &mut at) {{
+    Some(c) => {{
+        let is_member = {};
+        if is_member {{
+            at += c.len_utf8();
+            pc = {};
+        }}
+        is_member
+    }}
+    None => false,
+}}",
+                class_condition(&x.ranges, "c", &mut tables)
+                    .replace('\n', "\n        "),
+                x.goto
+            ),
+            Inst::Bytes(x) => format!(
+                "match {INPUT_PARAM_NAME}.as_bytes().get(at) {{
+    Some({}..={}) => {{
+        at += 1;
+        pc = {};
+        true
+    }}
+    _ => false,
+}}",
+                x.start, x.end, x.goto
+            ),
+            Inst::Split(x) => format!(
+                "{}\npc = {};\ntrue",
+                stack.push(&format!("({}, at, 0)", x.goto2)),
+                x.goto1
+            ),
+            Inst::EmptyLook(x) => format!(
+                "let holds = {};\nif holds {{\n    pc = {};\n}}\nholds",
+                look_condition(x.look, "at", &mut tables),
+                x.goto
+            ),
+            // Every literal found after the one of the highest priority
+            // is an alternative, pushed so the lowest priority pops last
+            Inst::Literals(x) => {
+                let mut trie = Trie::new();
+
+                for (priority, literal) in x.literals.iter().enumerate() {
+                    trie.insert(literal.as_bytes(), priority);
+                }
+
+                let walk = trie.code("bytes", "at", &mut |found| {
+                    let Some(((_, first), rest)) = found.split_first() else {
+                        return String::from("false");
+                    };
+
+                    let mut code = rest
+                        .iter()
+                        .rev()
+                        .map(|(_, len)| {
+                            stack.push(&format!("({}, at + {len}, 0)", x.goto))
+                        })
+                        .collect::<Vec<_>>();
+                    code.push(format!("at += {first};\npc = {};\ntrue", x.goto));
+                    code.join("\n")
+                });
+
+                format!("let bytes = {INPUT_PARAM_NAME}.as_bytes();\n{walk}")
+            }
+            Inst::Save(x) => format!("pc = {};\ntrue", x.goto),
+            Inst::Match(_) => format!(
+                "*{INDEX_PARAM_NAME} = at;\n{}",
+                stack.accept()
+            ),
+            Inst::Repeat(x) => {
+                let body = format!("repeat_body_{i}");
+                let give_back = program.insts.len() + i;
+                let push =
+                    stack.push(&format!("({give_back}, at, count)"));
+                let min = x.min;
+
+                closures.push(repeat_body_closure(x, &body, &mut tables));
+
+                // Greedy repetitions start out taking as many
+                // repetitions as they can and give them back one at a
+                // time, lazy ones take the minimum and add one at a time
+                let (take, resume) = if x.greedy {
+                    let below_max = match x.max {
+                        Some(max) => format!("count < {max} && "),
+                        None => String::new(),
+                    };
+                    let resume = format!(
+                        "if count == {min} {{
+    false
+}} else {{
+    {}
+    count -= 1;
+    {}
+    pc = {};
+    true
+}}",
+                        repeat_step_back(x, "at").replace('\n', "\n    "),
+                        push.replace('\n', "\n    "),
+                        x.goto
+                    );
+
+                    (below_max, resume)
+                } else {
+                    let at_max = match x.max {
+                        Some(max) => format!("count == {max} || "),
+                        None => String::new(),
+                    };
+                    let resume = format!(
+                        "if {at_max}!{body}(&mut at) {{
+    false
+}} else {{
+    count += 1;
+    {}
+    pc = {};
+    true
+}}",
+                        push.replace('\n', "\n    "),
+                        x.goto
+                    );
+
+                    (format!("count < {min} && "), resume)
+                };
+
+                let enter = format!(
+                    "count = 0;
+while {take}{body}(&mut at) {{
+    count += 1;
+}}
+if count < {min} {{
+    false
+}} else {{
+    {}
+    pc = {};
+    true
+}}",
+                    push.replace('\n', "\n    "),
+                    x.goto
+                );
+
+                states.push((give_back, inst, resume));
+
+                enter
+            }
+        };
+
+        states.push((i, inst, state));
+    }
+
+    // A loop whose body matched the empty string would repeat forever,
+    // so a loop head reached again at the position it was last entered
+    // at on the current path fails, as it is skipped by a Pike VM. The
+    // position it was entered at before is restored by a frame popped
+    // when backtracking past the entry. The visited pairs of
+    // `Engine::Bounded` already fail such a path.
+    let heads = match visited_capacity {
+        Some(_) => vec![],
+        None => program.empty_loop_heads(),
+    };
+
+    for (k, &head) in heads.iter().enumerate() {
+        let restore = 2 * program.insts.len() + k;
+        let (_, inst, state) = states
+            .iter_mut()
+            .find(|(i, ..)| *i == head)
+            .unwrap();
+
+        *state = format!(
+            "if entered[{k}] == at {{
+    false
+}} else {{
+    {}
+    entered[{k}] = at;
+    {}
+}}",
+            stack
+                .push(&format!("({restore}, entered[{k}], 0)"))
+                .replace('\n', "\n    "),
+            state.replace('\n', "\n    ")
+        );
+
+        let inst = *inst;
+        states.push((restore, inst, format!("entered[{k}] = at;\nfalse")));
+    }
+
+    states.sort_by_key(|&(i, ..)| i);
+
+    let arms = states
+        .iter()
+        .map(|(i, inst, state)| {
+            format!(
+                "// From: {inst:?}\n{i} => {{\n    {}\n}}",
+                state.replace('\n', "\n    ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let counter = if has_repeats {
+        "\nlet mut count: usize = 0;"
+    } else {
+        ""
+    };
+    let entered = if heads.is_empty() {
+        String::new()
+    } else {
+        format!("\nlet mut entered = [usize::MAX; {}];", heads.len())
+    };
+
+    // A pair that was explored before is not explored again, since it
+    // either failed or is still pending on the stack with a higher
+    // priority than the current path
+    let step = if visited_capacity.is_some() {
+        "visited.insert(pc, at) && match pc"
+    } else {
+        "match pc"
+    };
+
+    let body = format!(
+        "{}
+{}
+let mut pc: usize = {};
+let mut at: usize = *{INDEX_PARAM_NAME};{counter}{entered}
+
+loop {{
+    let advanced = {step} {{
+        {}
+        _ => unreachable!(),
+    }};
+
+    if !advanced {{
+        {}
+    }}
+}}",
+        closures.join("\n").trim_start(),
+        stack.init(),
+        program.start,
+        arms.replace('\n', "\n        "),
+        stack.pop(count).replace('\n', "\n        "),
+    );
+
+    let name = time_name!('F');
+    let mut constants = tables.into_items();
+    let mut fallible = None;
+    let mut bounded = None;
+
+    let body = if let Some(capacity) = visited_capacity {
+        let matcher = format!("{name}_bounded");
+        let visited = format!(
+            "::compiled_regex::Visited::new({}, {INPUT_PARAM_NAME}.len(), {capacity})",
+            program.insts.len()
+        );
+
+        constants.push(format!(
+            "\nfn {matcher}({INPUT_PARAM_NAME}: {INPUT_PARAM_TYPE}, {INDEX_PARAM_NAME}: {INDEX_PARAM_TYPE}, visited: &mut ::compiled_regex::Visited) -> bool {{\n    {}\n}}\n",
+            body.replace('\n', "\n    ")
+        ));
+
+        let body = format!("let mut visited = {visited};\nSelf::{matcher}({INPUT_PARAM_NAME}, {INDEX_PARAM_NAME}, &mut visited)");
+
+        let reset = format!(
+            "visited.reset({}, {INPUT_PARAM_NAME}.len(), {capacity});",
+            program.insts.len()
+        );
+
+        bounded = Some(Bounded { visited, reset, matcher });
+
+        body
+    } else if let Stack::Fixed(_) = stack {
+        let name = format!("{name}_try");
+
+        constants.push(format!(
+            "\nfn {name}({INPUT_PARAM_NAME}: {INPUT_PARAM_TYPE}, {INDEX_PARAM_NAME}: {INDEX_PARAM_TYPE}) -> Result<bool, ::compiled_regex::StackExhausted> {{\n    {}\n}}\n",
+            body.replace('\n', "\n    ")
+        ));
+
+        let body = format!(
+            "match Self::{name}({INPUT_PARAM_NAME}, {INDEX_PARAM_NAME}) {{
+    Ok(is_match) => is_match,
+    Err(err) => ::core::panic!(\"{{}}\", err),
+}}"
+        );
+
+        fallible = Some(name);
+
+        body
+    } else {
+        body
+    };
+
+    CompiledRegex {
+        implementation: ProgramImplementation {
+            name,
+            body,
+            children: vec![],
+            parameters: "",
+            constants,
+        },
+        is_anchored_start: program.is_anchored_start,
+        is_anchored_end: program.is_anchored_end,
+        fallible,
+        bounded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex_syntax::Parser;
+
+    use super::parse;
+    use crate::compiler::Compiler;
+    use crate::options::Options;
+
+    #[test]
+    fn fixed_stack_is_fallible() {
+        let hir = Parser::new().parse("a*b").unwrap();
+        let program = Compiler::new().compile(&hir).unwrap();
+
+        let compiled = parse(&program, &Options::new());
+        assert!(compiled.fallible.is_none());
+        assert!(compiled.implementation.body.contains("Vec::new()"));
+
+        let mut options = Options::new();
+        options.set("stack_limit", "16").unwrap();
+
+        let compiled = parse(&program, &options);
+        let fallible = compiled.fallible.unwrap();
+        assert!(compiled.implementation.body.contains(&fallible));
+        assert!(compiled
+            .implementation
+            .constants
+            .iter()
+            .any(|x| x.contains("[(0usize, 0usize, 0usize); 16]")));
+    }
+
+    #[test]
+    fn bounded_shares_visited() {
+        let hir = Parser::new().parse("(a|a)*b").unwrap();
+        let program = Compiler::new().compile(&hir).unwrap();
+
+        let mut options = Options::new();
+        options.set("engine", "bounded").unwrap();
+
+        let compiled = parse(&program, &options);
+        let bounded = compiled.bounded.unwrap();
+        assert!(bounded.visited.contains(&program.insts.len().to_string()));
+        assert!(compiled.implementation.body.contains(&bounded.matcher));
+    }
+}
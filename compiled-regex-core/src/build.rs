@@ -0,0 +1,263 @@
+//! Generating matchers from a build script instead of a macro
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs, io};
+
+use crate::codegen::{pattern_file_types, regex_set_type, regex_type, Naming};
+use crate::options::Options;
+use crate::pattern_file::{location, parse_pattern_file};
+use crate::types::CompileError;
+
+#[derive(Debug)]
+pub enum BuildError {
+    /// The type of the given name failed to compile
+    Compile(String, CompileError),
+    /// Lines of a pattern file failed to compile, by their line
+    PatternFile(PathBuf, Vec<(usize, CompileError)>),
+    Io(io::Error),
+    /// `OUT_DIR` is not set, which cargo does for build scripts
+    NoOutDir,
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::Compile(name, err) => write!(f, "{name}: {err}"),
+            BuildError::PatternFile(path, errors) => {
+                errors.iter().try_for_each(|(line, err)| {
+                    writeln!(
+                        f,
+                        "{}: {err}",
+                        location(&path.display().to_string(), *line)
+                    )
+                })
+            }
+            BuildError::Io(err) => write!(f, "{err}"),
+            BuildError::NoOutDir => {
+                write!(f, "OUT_DIR is not set, is this a build script?")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl From<io::Error> for BuildError {
+    fn from(err: io::Error) -> Self {
+        BuildError::Io(err)
+    }
+}
+
+enum Item {
+    Regex(String, String, Options),
+    Set(String, Vec<String>, Options),
+    File(String, PathBuf, Options),
+}
+
+/// Writes the types `parse_regex!` and `parse_regex_set!` produce into
+/// a module in `OUT_DIR`, to be used with `include!`.
+///
+/// The types are declared as `pub struct Name;` with public methods,
+/// and the module is formatted with `rustfmt` when it is installed.
+///
+/// ```no_run
+/// // build.rs
+/// use compiled_regex_core::build::Builder;
+///
+/// Builder::new()
+///     .regex("Email", r"^[^@\s]+@[^@\s]+$")
+///     .set_file("Ads", "data/easylist_url_regex.txt")
+///     .write("regexes.rs")
+///     .unwrap();
+///
+/// // src/lib.rs
+/// // include!(concat!(env!("OUT_DIR"), "/regexes.rs"));
+/// ```
+#[derive(Default)]
+pub struct Builder {
+    items: Vec<Item>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the type `name` matching `pattern`
+    pub fn regex(self, name: &str, pattern: &str) -> Self {
+        self.regex_with(name, pattern, Options::default())
+    }
+
+    /// Add the type `name` matching `pattern`, with the options that
+    /// follow the pattern in `parse_regex!`
+    pub fn regex_with(
+        mut self,
+        name: &str,
+        pattern: &str,
+        options: Options,
+    ) -> Self {
+        self.items.push(Item::Regex(
+            name.to_string(),
+            pattern.to_string(),
+            options,
+        ));
+        self
+    }
+
+    /// Add the set `name` of `patterns`
+    pub fn set<I, S>(mut self, name: &str, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.items.push(Item::Set(
+            name.to_string(),
+            patterns.into_iter().map(Into::into).collect(),
+            Options::default(),
+        ));
+        self
+    }
+
+    /// Add the set `name` of the patterns in the file at `path`, which
+    /// is relative to `CARGO_MANIFEST_DIR`, and a type for every named
+    /// line, like `parse_regex_set!(Name = file "path")`
+    pub fn set_file(mut self, name: &str, path: impl AsRef<Path>) -> Self {
+        self.items.push(Item::File(
+            name.to_string(),
+            path.as_ref().to_path_buf(),
+            Options::default(),
+        ));
+        self
+    }
+
+    /// The source of the module
+    pub fn generate(&self) -> Result<String, BuildError> {
+        let mut code = String::from(
+            "// Generated by compiled_regex_core::build::Builder, do not edit\n",
+        );
+
+        for item in &self.items {
+            let generated = match item {
+                Item::Regex(name, pattern, options) => {
+                    regex_type(name, pattern, options, Naming::Public)
+                        .map_err(|err| BuildError::Compile(name.clone(), err))?
+                }
+                Item::Set(name, patterns, options) => {
+                    let patterns = patterns
+                        .iter()
+                        .map(String::as_str)
+                        .collect::<Vec<_>>();
+
+                    regex_set_type(name, &patterns, options, Naming::Public)
+                        .map_err(|err| BuildError::Compile(name.clone(), err))?
+                }
+                Item::File(name, path, options) => {
+                    let text = fs::read_to_string(manifest_path(path))?;
+                    let lines = parse_pattern_file(&text);
+
+                    pattern_file_types(name, &lines, options, Naming::Public)
+                        .map_err(|errors| {
+                            BuildError::PatternFile(path.clone(), errors)
+                        })?
+                }
+            };
+
+            code.push_str(&generated);
+        }
+
+        Ok(code)
+    }
+
+    /// Write the module to `file_name` in `OUT_DIR`, and tell cargo to
+    /// run the build script again when a pattern file changes
+    pub fn write(
+        &self,
+        file_name: impl AsRef<Path>,
+    ) -> Result<PathBuf, BuildError> {
+        let out_dir = env::var_os("OUT_DIR").ok_or(BuildError::NoOutDir)?;
+        let path = Path::new(&out_dir).join(file_name);
+
+        self.write_to(&path)?;
+
+        for item in &self.items {
+            if let Item::File(_, file, _) = item {
+                println!("cargo:rerun-if-changed={}", manifest_path(file).display());
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// Write the module to `path`
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<(), BuildError> {
+        let path = path.as_ref();
+
+        fs::write(path, self.generate()?)?;
+
+        // Formatting is only for reading the module, so it is fine if
+        // rustfmt is not around
+        let rustfmt = env::var_os("RUSTFMT").unwrap_or_else(|| "rustfmt".into());
+        let _ = Command::new(rustfmt)
+            .args(["--edition", "2021"])
+            .arg(path)
+            .status();
+
+        Ok(())
+    }
+}
+
+/// `path` relative to the crate of the running build script
+fn manifest_path(path: &Path) -> PathBuf {
+    match env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) => Path::new(&dir).join(path),
+        None => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BuildError, Builder};
+
+    #[test]
+    fn public_types() {
+        let code = Builder::new()
+            .regex("Digits", "[0-9]+")
+            .set("Words", ["foo", "bar"])
+            .generate()
+            .unwrap();
+
+        assert!(code.contains("/// `[0-9]+`\npub struct Digits;"));
+        assert!(code.contains("pub struct Words;"));
+        assert!(code.contains("pub fn is_match(input: &str) -> bool"));
+        assert!(code.contains("pub fn matches(input: &str)"));
+        assert!(!code.contains("__S"));
+    }
+
+    #[test]
+    fn pattern_file() {
+        let code = Builder::new()
+            .set_file("Sample", "../data/easylist_sample.txt")
+            .generate()
+            .unwrap();
+
+        assert!(code.contains("pub struct Sample;"));
+        assert!(code.contains("pub struct AdBanner;"));
+
+        let err = Builder::new().regex("Broken", "a(").generate();
+        assert!(matches!(err, Err(BuildError::Compile(name, _)) if name == "Broken"));
+    }
+
+    #[test]
+    fn formatted_file() {
+        let path = std::env::temp_dir().join("compiled_regex_build_test.rs");
+
+        Builder::new().regex("Digits", "[0-9]+").write_to(&path).unwrap();
+
+        let code = std::fs::read_to_string(&path).unwrap();
+        assert!(code.contains("pub struct Digits;"));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
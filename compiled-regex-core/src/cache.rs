@@ -0,0 +1,214 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+/// Changes whenever a source file of this crate changes, so code cached
+/// by an older code generation is never used
+const SOURCE_HASH: &str = env!("COMPILED_REGEX_CORE_SOURCE_HASH");
+
+/// On-disk cache of generated code, keyed by a hash of everything the
+/// code is generated from.
+///
+/// Entries are kept in a directory named after `SOURCE_HASH`, and the
+/// directories of older code generations are removed when the first
+/// entry of a new one is written.
+///
+/// Reading and writing the cache is best effort, any error just means
+/// the code is generated again.
+pub struct Cache {
+    dir: PathBuf,
+}
+
+/// The parsed `pattern`, as part of the key of code generated from it,
+/// since the same pattern parses differently with another version of
+/// `regex-syntax`, such as one with newer Unicode tables
+pub fn pattern_key(pattern: &str) -> String {
+    format!("{:?}", regex_syntax::Parser::new().parse(pattern))
+}
+
+impl Cache {
+    /// Cache in the directory `dir`, created when first written to
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Cache {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Cache in `COMPILED_REGEX_CACHE_DIR` when set, or otherwise in
+    /// `compiled-regex-cache` under the target directory of the crate
+    /// being compiled.
+    ///
+    /// The target directory is only known for sure when it is given as
+    /// `CARGO_TARGET_DIR`, or for the crates of the workspace being
+    /// built, whose `target` directory is found above their manifest.
+    /// Dependencies such as those from a registry are not cached, since
+    /// their sources are not below the target directory. `None` when
+    /// `COMPILED_REGEX_NO_CACHE` is set.
+    pub fn from_env() -> Option<Self> {
+        if env::var_os("COMPILED_REGEX_NO_CACHE").is_some() {
+            return None;
+        }
+
+        if let Some(dir) = env::var_os("COMPILED_REGEX_CACHE_DIR") {
+            return Some(Cache::new(dir));
+        }
+
+        Some(Cache::new(target_dir()?.join("compiled-regex-cache")))
+    }
+
+    /// The cached code for `key`, or the code of `generate` which is
+    /// then cached. Errors of `generate` are not cached.
+    pub fn get_or_insert_with<E>(
+        &self,
+        key: &[&str],
+        generate: impl FnOnce() -> Result<String, E>,
+    ) -> Result<String, E> {
+        let path = self.path(key);
+
+        if let Ok(code) = fs::read_to_string(&path) {
+            return Ok(code);
+        }
+
+        let code = generate()?;
+
+        let version = self.dir.join(SOURCE_HASH);
+
+        if !version.is_dir() {
+            self.prune();
+        }
+
+        // Written under another name first, so a concurrent expansion
+        // never reads half a file
+        let partial = path.with_extension(format!("{}.tmp", std::process::id()));
+        let written = fs::create_dir_all(&version)
+            .and_then(|_| fs::write(&partial, &code))
+            .and_then(|_| fs::rename(&partial, &path));
+
+        if written.is_err() {
+            let _ = fs::remove_file(&partial);
+        }
+
+        Ok(code)
+    }
+
+    fn path(&self, key: &[&str]) -> PathBuf {
+        let mut hasher = Sha256::new();
+
+        hasher.update(SOURCE_HASH);
+
+        // Lengths keep `["ab", "c"]` and `["a", "bc"]` apart
+        for part in key {
+            hasher.update(part.len().to_le_bytes());
+            hasher.update(part);
+        }
+
+        self.dir
+            .join(SOURCE_HASH)
+            .join(format!("{}.rs", hex::encode(hasher.finalize())))
+    }
+
+    /// Remove the entries of other code generations, which are never
+    /// read again. Only names the cache writes are removed, in case the
+    /// directory is shared.
+    fn prune(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+
+            let is_hex = |text: &str, len: usize| {
+                text.len() == len && text.bytes().all(|byte| byte.is_ascii_hexdigit())
+            };
+
+            // Entries of the same length as `SOURCE_HASH`, and entries
+            // kept directly in the directory by earlier versions
+            if is_hex(name, SOURCE_HASH.len()) && name != SOURCE_HASH {
+                let _ = fs::remove_dir_all(entry.path());
+            } else if name.strip_suffix(".rs").is_some_and(|stem| is_hex(stem, 64)) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+/// `CARGO_TARGET_DIR`, or for a crate of the workspace being built the
+/// closest existing `target` directory above it, which is the one of
+/// its workspace
+fn target_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("CARGO_TARGET_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+
+    env::var_os("CARGO_PRIMARY_PACKAGE")?;
+
+    let manifest_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR")?);
+
+    manifest_dir
+        .ancestors()
+        .map(|dir| dir.join("target"))
+        .find(|dir| dir.is_dir())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::Cache;
+
+    #[test]
+    fn generates_once() {
+        let dir = std::env::temp_dir()
+            .join(format!("compiled_regex_cache_test_{}", std::process::id()));
+        let cache = Cache::new(&dir);
+        let generated = Cell::new(0);
+        let generate = || -> Result<String, ()> {
+            generated.set(generated.get() + 1);
+            Ok(String::from("fn f() {}"))
+        };
+
+        assert_eq!(cache.get_or_insert_with(&["a", "b"], generate).unwrap(), "fn f() {}");
+        assert_eq!(cache.get_or_insert_with(&["a", "b"], generate).unwrap(), "fn f() {}");
+        assert_eq!(generated.get(), 1);
+
+        cache.get_or_insert_with(&["ab", ""], generate).unwrap();
+        assert_eq!(generated.get(), 2);
+
+        // Errors are not cached
+        assert!(cache.get_or_insert_with(&["c"], || Err::<String, _>(())).is_err());
+        cache.get_or_insert_with(&["c"], generate).unwrap();
+        assert_eq!(generated.get(), 3);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn prunes_older_generations() {
+        let dir = std::env::temp_dir()
+            .join(format!("compiled_regex_cache_prune_{}", std::process::id()));
+        let older = dir.join("0123456789abcdef");
+        let unrelated = dir.join("notes.txt");
+        let flat = dir.join(format!("{}.rs", "ab".repeat(32)));
+
+        std::fs::create_dir_all(&older).unwrap();
+        std::fs::write(older.join("x.rs"), "").unwrap();
+        std::fs::write(&unrelated, "").unwrap();
+        std::fs::write(&flat, "").unwrap();
+
+        let cache = Cache::new(&dir);
+        cache.get_or_insert_with(&["a"], || Ok::<_, ()>(String::new())).unwrap();
+
+        assert!(!older.exists());
+        assert!(!flat.exists());
+        assert!(unrelated.exists());
+        assert!(cache.path(&["a"]).exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
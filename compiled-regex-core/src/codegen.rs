@@ -0,0 +1,896 @@
+use regex_syntax::hir::{Hir, Look};
+use sha2::{Digest, Sha256};
+
+use crate::ir::functions::{CHAR_GET_FUNC, CONST_CHAR_GET_FUNC};
+use crate::options::Options;
+use crate::pattern_file::PatternLine;
+use crate::types::{CompileError, Result};
+use crate::vectors::TestVectors;
+use crate::{
+    parse_automaton, parse_hir, parse_lexer, parse_prefilter, parse_regex, parse_regex_set,
+    parse_reverse_automaton,
+};
+
+/// How a generated type is declared
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Naming<'a> {
+    /// A `__S<sha>` struct behind a `type Name = __S<sha>;` alias with
+    /// private methods, as `parse_regex!` expands to
+    Hashed,
+    /// A `pub struct Name;` with public methods, as written by the
+    /// `build::Builder`
+    Public,
+    /// Public methods in an `impl` block of a type declared by the user,
+    /// as `#[derive(Regex)]` adds them
+    Derived(Generics<'a>),
+}
+
+impl Naming<'_> {
+    fn visibility(self) -> &'static str {
+        match self {
+            Naming::Hashed => "",
+            Naming::Public | Naming::Derived(_) => "pub ",
+        }
+    }
+}
+
+/// The generics of a type, as written in the parts of `impl<params>
+/// Name<args> where_clause`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Generics<'a> {
+    /// Parameters with their bounds, but without defaults, such as
+    /// `'a, T: Display, const N: usize`
+    pub params: &'a str,
+    /// Names of the parameters, such as `'a, T, N`
+    pub args: &'a str,
+    /// `where` and its predicates, or nothing
+    pub where_clause: &'a str,
+}
+
+/// Declare the type `name` with the `items` of its `impl` block
+fn declare(name: &str, doc: &str, items: &str, naming: Naming<'_>) -> String {
+    match naming {
+        Naming::Hashed => {
+            let struct_name = hashed_name(name, items);
+
+            format!("
+struct {struct_name}();
+#[allow(unused_variables)]
+#[allow(nonstandard_style)]
+impl {struct_name} {{
+    {0}
+}}
+type {name} = {struct_name};",
+                items.replace('\n', "\n    "))
+        }
+        // Written to a file that is included as is, so lints of the
+        // including crate apply
+        Naming::Public => format!("
+{doc}
+pub struct {name};
+
+#[allow(unused_variables)]
+#[allow(nonstandard_style)]
+#[allow(unused_mut)]
+#[allow(clippy::all)]
+impl {name} {{
+    {0}
+}}
+",
+            items.replace('\n', "\n    ")),
+        // The type is declared by the user, along with its docs
+        Naming::Derived(generics) => format!("
+#[allow(unused_variables)]
+#[allow(nonstandard_style)]
+#[allow(unused_mut)]
+#[allow(clippy::all)]
+impl<{}> {name}<{}> {} {{
+    {}
+}}
+",
+            generics.params,
+            generics.args,
+            generics.where_clause,
+            items.replace('\n', "\n    ")),
+    }
+}
+
+/// Name of the struct behind the type `name` with the `items`
+fn hashed_name(name: &str, items: &str) -> String {
+    let mut hasher = Sha256::new();
+
+    hasher.update(name);
+    hasher.update(items);
+
+    format!("__S{}", hex::encode(hasher.finalize()))
+}
+
+/// Doc comment on a type naming its patterns
+fn type_doc(patterns: &[&str]) -> String {
+    patterns
+        .iter()
+        .map(|pattern| {
+            format!("/// `{}`", pattern.replace('\n', "\\n").replace('\r', "\\r"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n///\n")
+}
+
+/// The type `parse_regex!(Name = "pattern", options)` produces
+pub fn regex_type(
+    name: &str,
+    pattern: &str,
+    options: &Options,
+    naming: Naming<'_>,
+) -> Result<String> {
+    let compiled = parse_regex(pattern, options)?;
+    let implementation = &compiled.implementation;
+    let vis = naming.visibility();
+
+    // Anchored patterns only need to be tried at the start, others are
+    // tried at every character boundary until the first match
+    let next_start = |none: &str| if compiled.is_anchored_start {
+        format!("return {none};")
+    } else if options.const_fn {
+        format!("if start == input.len() {{
+            return {none};
+        }}
+        let mut next = start;
+        start += match Self::__get_char(input, &mut next) {{
+            Some(c) => c.len_utf8(),
+            None => 1,
+        }};")
+    } else {
+        format!("if start == input.len() {{
+            return {none};
+        }}
+        start += input[start..].chars().next().map_or(1, char::len_utf8);")
+    };
+
+    // Everything called by `is_match` and `find` has to be `const` too
+    let (functions, constness) = if options.const_fn {
+        (format!("{CONST_CHAR_GET_FUNC}{}", implementation.const_fns()), "const ")
+    } else {
+        (format!("{CHAR_GET_FUNC}{implementation}"), "")
+    };
+
+    // The bounded engine shares its visited set between all start
+    // positions, a pair that failed from one start fails from any
+    let (setup, matcher) = match &compiled.bounded {
+        Some(bounded) => (
+            format!("let mut visited = {};\n    ", bounded.visited),
+            format!("Self::{}(input, &mut end, &mut visited)", bounded.matcher),
+        ),
+        None => (
+            String::new(),
+            format!("Self::{}(input, &mut end)", implementation.name),
+        ),
+    };
+
+    // Patterns with a bounded backtracking stack can report running out
+    // of it instead of panicking
+    let fallible = match &compiled.fallible {
+        Some(fallible) => format!("
+
+#[allow(dead_code)]
+{vis}fn try_is_match(input: &str) -> Result<bool, ::compiled_regex::StackExhausted> {{
+    Ok(Self::try_find(input)?.is_some())
+}}
+
+/// Same as `find`, but returns an error when the match needs more
+/// backtracking frames than the `stack_limit` allows
+#[allow(dead_code)]
+{vis}fn try_find(input: &str) -> Result<Option<::compiled_regex::Match<'_>>, ::compiled_regex::StackExhausted> {{
+    let mut start = 0;
+
+    loop {{
+        let mut end = start;
+
+        if Self::{fallible}(input, &mut end)? {{
+            return Ok(Some(::compiled_regex::Match::new(input, start, end)));
+        }}
+
+        {}
+    }}
+}}", next_start("Ok(None)")),
+        None => String::new(),
+    };
+
+    // The threads of streams and chunked haystacks are states of
+    // another program, kept in a struct of their own so their class
+    // tables can not clash
+    let (automaton_type, automaton) = match options.stream || options.chunked {
+        true => {
+            let compiled = parse_automaton(pattern)?;
+            let struct_name = hashed_name(name, &compiled.items);
+
+            (
+                format!("
+struct {struct_name}();
+#[allow(unused_variables)]
+#[allow(nonstandard_style)]
+#[allow(clippy::all)]
+impl {struct_name} {{
+    {}
+}}
+",
+                    compiled.items.replace('\n', "\n    ")),
+                struct_name,
+            )
+        }
+        false => (String::new(), String::new()),
+    };
+
+    let stream = match options.stream {
+        true => format!("
+
+/// Every match in the text read from `reader`, with offsets from the
+/// start of the text. Only the text of a match still being searched is
+/// kept, so the text can be much larger than the memory.
+#[allow(dead_code)]
+{vis}fn stream_find<R: ::std::io::Read>(reader: R) -> ::compiled_regex::StreamFind<R> {{
+    ::compiled_regex::StreamFind::new(reader, {automaton}::__automaton())
+}}"),
+        false => String::new(),
+    };
+
+    let chunked = match options.chunked {
+        true => format!("
+
+#[allow(dead_code)]
+{vis}fn is_match_in<H: ::compiled_regex::Haystack>(haystack: H) -> bool {{
+    Self::find_in(haystack).is_some()
+}}
+
+/// Leftmost-first match in the text `haystack` reads, with offsets from
+/// the start of that text
+#[allow(dead_code)]
+{vis}fn find_in<H: ::compiled_regex::Haystack>(haystack: H) -> Option<::core::ops::Range<usize>> {{
+    {automaton}::__automaton().find(haystack)
+}}"),
+        false => String::new(),
+    };
+
+    let prefilter = parse_prefilter(pattern)?;
+
+    // Reverse searches read the input backwards with the threads of the
+    // reversed pattern, starting at the suffix every match ends with
+    let (reverse_type, reverse) = match options.reverse {
+        true => {
+            let compiled = parse_reverse_automaton(pattern)?;
+            let struct_name = hashed_name(&format!("{name}::reverse"), &compiled.items);
+
+            (
+                format!("
+struct {struct_name}();
+#[allow(unused_variables)]
+#[allow(nonstandard_style)]
+#[allow(clippy::all)]
+impl {struct_name} {{
+    {}
+}}
+",
+                    compiled.items.replace('\n', "\n    ")),
+                struct_name,
+            )
+        }
+        false => (String::new(), String::new()),
+    };
+    let suffix = match &prefilter.suffix {
+        Some(suffix) => format!("Some({suffix:?})"),
+        None => String::from("None"),
+    };
+
+    let rfind = match options.reverse {
+        true => format!("
+
+/// The match ending closest to the end of `input`, and of the matches
+/// ending there the one starting first, found by reading `input`
+/// backwards from the end
+#[allow(dead_code)]
+{vis}fn rfind(input: &str) -> Option<::compiled_regex::Match<'_>> {{
+    let found = {reverse}::__automaton().rfind(input, {suffix})?;
+
+    Some(::compiled_regex::Match::new(input, found.start, found.end))
+}}"),
+        false => String::new(),
+    };
+
+    // Patterns that can only match at the end of the input are searched
+    // from there, instead of trying every start before it
+    let find = if options.reverse
+        && compiled.is_anchored_end
+        && !compiled.is_anchored_start
+        && !options.const_fn
+    {
+        format!("let found = {reverse}::__automaton().find_at_end(input, {suffix})?;
+
+    Some(::compiled_regex::Match::new(input, found.start, found.end))")
+    } else {
+        format!("let mut start = 0;
+    {setup}
+    loop {{
+        let mut end = start;
+
+        if {matcher} {{
+            return Some(::compiled_regex::Match::new(input, start, end));
+        }}
+
+        {}
+    }}", next_start("None"))
+    };
+
+    // Matching many inputs checks what every input needs to contain
+    // first, and creates the visited set of the bounded engine once
+    let prefilter = prefilter.condition();
+    let (batch_param, batch_reset, batch_matcher, batch_setup, batch_arg) = match &compiled.bounded {
+        Some(bounded) => (
+            ", visited: &mut ::compiled_regex::Visited",
+            bounded.reset.as_str(),
+            format!("Self::{}(input, &mut end, visited)", bounded.matcher),
+            "// Grown to the size every input needs by `reset`
+    let mut visited = ::compiled_regex::Visited::new(0, 0, 0);
+    ",
+            ", &mut visited",
+        ),
+        None => ("", "", matcher.clone(), "", ""),
+    };
+    let batch = format!("
+
+/// `is_match` of one of many inputs
+fn __batch_match(input: &str{batch_param}) -> bool {{
+    if !({prefilter}) {{
+        return false;
+    }}
+    {batch_reset}
+    let mut start = 0;
+
+    loop {{
+        let mut end = start;
+
+        if {batch_matcher} {{
+            return true;
+        }}
+
+        {}
+    }}
+}}
+
+::compiled_regex::__with_alloc! {{
+    /// Whether each of `inputs` matches, by its index
+    #[allow(dead_code)]
+    {vis}fn is_match_many(inputs: &[&str]) -> ::compiled_regex::MatchBits {{
+        let mut matched = ::compiled_regex::MatchBits::new(inputs.len());
+        {}
+        for (i, input) in inputs.iter().enumerate() {{
+            if Self::__batch_match(input{batch_arg}) {{
+                matched.insert(i);
+            }}
+        }}
+
+        matched
+    }}
+}}
+
+/// The `inputs` that match
+#[allow(dead_code)]
+{vis}fn filter<'__input>(inputs: impl IntoIterator<Item = &'__input str>) -> impl Iterator<Item = &'__input str> {{
+    // Does not borrow the lifetimes of `Self`
+    let is_match: fn(&str{batch_param}) -> bool = Self::__batch_match;
+    {batch_setup}
+    inputs.into_iter().filter(move |input| is_match(input{batch_arg}))
+}}",
+        next_start("false"),
+        batch_setup.replace('\n', "\n    "),
+    );
+
+    let items = format!("{functions}
+#[allow(dead_code)]
+{vis}{constness}fn is_match(input: &str) -> bool {{
+    Self::find(input).is_some()
+}}
+
+/// Leftmost-first match, preferring the branches a backtracking
+/// implementation would try first, as `regex::Regex::find` does
+#[allow(dead_code)]
+{vis}{constness}fn find(input: &str) -> Option<::compiled_regex::Match<'_>> {{
+    {find}
+}}{fallible}{stream}{chunked}{batch}{rfind}");
+
+    Ok(format!("{automaton_type}{reverse_type}{}", declare(name, &type_doc(&[pattern]), &items, naming)))
+}
+
+/// A variant of the token enum of `lexer!` and the patterns matching it
+pub struct TokenPatterns<'a> {
+    pub variant: &'a str,
+    pub patterns: Vec<&'a str>,
+}
+
+/// The `lexer` function `lexer!` adds to the enum `name` of `tokens`,
+/// and the hidden type matching them.
+///
+/// Errors are the patterns that failed to compile, by their index in
+/// the order of `tokens`.
+pub fn lexer_type(
+    name: &str,
+    vis: &str,
+    tokens: &[TokenPatterns],
+    error: &str,
+) -> Result<String> {
+    let patterns = tokens
+        .iter()
+        .flat_map(|token| token.patterns.iter().copied())
+        .collect::<Vec<_>>();
+    let compiled = parse_lexer(&patterns)?;
+
+    let mut pattern = 0;
+    let mut arms = vec![];
+
+    for token in tokens {
+        let indices = (pattern..pattern + token.patterns.len())
+            .map(|i| format!("Some({i})"))
+            .collect::<Vec<_>>();
+
+        pattern += token.patterns.len();
+
+        if !indices.is_empty() {
+            arms.push(format!("{} => {name}::{},", indices.join(" | "), token.variant));
+        }
+    }
+
+    let items = format!("{CHAR_GET_FUNC}{}
+/// The variant of `pattern`, or the error variant without one
+fn __token(pattern: Option<usize>) -> {name} {{
+    match pattern {{
+        {}
+        _ => {name}::{error},
+    }}
+}}",
+        compiled.items,
+        arms.join("\n        "),
+    );
+    let struct_name = hashed_name(name, &items);
+
+    Ok(format!("
+struct {struct_name}();
+#[allow(unused_variables)]
+#[allow(nonstandard_style)]
+impl {struct_name} {{
+    {0}
+}}
+
+impl {name} {{
+    /// The tokens of `input` and their spans, matching the longest
+    /// token at every position, or the token declared first of the ones
+    /// that are equally long. A character no token starts with is an
+    /// error token of its own.
+    #[allow(dead_code)]
+    {vis}fn lexer(input: &str) -> ::compiled_regex::Lexer<'_, Self> {{
+        ::compiled_regex::Lexer::new(input, {1}, {struct_name}::__token, {struct_name}::__longest)
+    }}
+}}",
+        items.replace('\n', "\n    "),
+        compiled.states,
+    ))
+}
+
+/// A pattern the field of a struct deriving `ValidatePatterns` has to
+/// match, from a `#[pattern("...", options)]` attribute
+pub struct FieldPattern<'a> {
+    /// Name of the field, or its index in a tuple struct
+    pub field: &'a str,
+    pub pattern: &'a str,
+    pub options: &'a Options,
+}
+
+/// The `validate` method `#[derive(ValidatePatterns)]` adds to the
+/// struct `name`, checking every field against its patterns.
+///
+/// Errors are the patterns that failed to compile, by their index in
+/// `fields`.
+pub fn validate_type(
+    name: &str,
+    generics: Generics<'_>,
+    fields: &[FieldPattern<'_>],
+) -> Result<String> {
+    let mut matchers = vec![];
+    let mut checks = vec![];
+    let mut errors = vec![];
+
+    for (i, field) in fields.iter().enumerate() {
+        let matcher = format!("__Pattern{i}");
+
+        match regex_type(&matcher, field.pattern, field.options, Naming::Hashed) {
+            Ok(code) => matchers.push(code),
+            Err(err) => errors.push((i, err)),
+        }
+
+        // Fields without a value, such as `None`, are not checked
+        checks.push(format!("
+if let Some(value) = ::compiled_regex::PatternField::pattern_input(&self.{0}) {{
+    if !{matcher}::is_match(value) {{
+        errors.push(::compiled_regex::FieldError::new({0:?}, {1:?}));
+    }}
+}}",
+            field.field,
+            field.pattern,
+        ));
+    }
+
+    if !errors.is_empty() {
+        return Err(CompileError::Patterns(errors));
+    }
+
+    // The matchers are only visible in the body of `validate`
+    let body = format!("{}
+let mut errors = ::compiled_regex::__alloc::Vec::new();
+{}
+
+if errors.is_empty() {{
+    Ok(())
+}} else {{
+    Err(errors)
+}}",
+        matchers.concat(),
+        checks.concat(),
+    );
+
+    Ok(format!("
+impl<{}> {name}<{}> {} {{
+    /// Every field whose value does not match one of its patterns, in
+    /// the order the patterns are declared
+    #[allow(dead_code)]
+    pub fn validate(&self) -> Result<(), ::compiled_regex::__alloc::Vec<::compiled_regex::FieldError>> {{
+        {}
+    }}
+}}
+",
+        generics.params,
+        generics.args,
+        generics.where_clause,
+        body.replace('\n', "\n        "),
+    ))
+}
+
+/// The pattern a newtype of `pattern` matches, which has to match the
+/// whole string. The anchors are added to the parsed pattern, as text
+/// they could end up in a comment of `(?x)`.
+pub fn newtype_pattern(pattern: &str) -> Result<String> {
+    let hir = parse_hir(pattern)?;
+
+    Ok(Hir::concat(vec![Hir::look(Look::Start), hir, Hir::look(Look::End)]).to_string())
+}
+
+/// The type `parse_regex!(newtype Name = "pattern", options)` produces,
+/// a string that is known to match the pattern. It owns a `String`, or
+/// borrows a `&'lifetime str` when given a `lifetime`.
+pub fn newtype_type(
+    name: &str,
+    vis: &str,
+    lifetime: Option<&str>,
+    pattern: &str,
+    options: &Options,
+) -> Result<String> {
+    let matcher = format!("__{name}Pattern");
+    let whole = newtype_pattern(pattern)?;
+    let code = regex_type(&matcher, &whole, options, Naming::Hashed)?;
+    let doc = type_doc(&[pattern]);
+
+    // The conversions from a string are the only way to create one, so
+    // every value matches
+    let (declaration, conversions) = match lifetime {
+        None => (
+            format!("{vis}struct {name}(::compiled_regex::__alloc::String);"),
+            format!("
+impl ::core::str::FromStr for {name} {{
+    type Err = ::compiled_regex::InvalidFormat;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {{
+        Self::try_from(input)
+    }}
+}}
+
+impl TryFrom<&str> for {name} {{
+    type Error = ::compiled_regex::InvalidFormat;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {{
+        match {name}::is_match(input) {{
+            true => Ok({name}(::compiled_regex::__alloc::String::from(input))),
+            false => Err({name}::invalid()),
+        }}
+    }}
+}}
+
+impl TryFrom<::compiled_regex::__alloc::String> for {name} {{
+    type Error = ::compiled_regex::InvalidFormat;
+
+    fn try_from(input: ::compiled_regex::__alloc::String) -> Result<Self, Self::Error> {{
+        match {name}::is_match(&input) {{
+            true => Ok({name}(input)),
+            false => Err({name}::invalid()),
+        }}
+    }}
+}}
+
+impl From<{name}> for ::compiled_regex::__alloc::String {{
+    fn from(value: {name}) -> Self {{
+        value.0
+    }}
+}}
+
+impl {name} {{
+    /// The owned string
+    #[allow(dead_code)]
+    {vis}fn into_inner(self) -> ::compiled_regex::__alloc::String {{
+        self.0
+    }}
+}}
+
+::compiled_regex::__newtype_serde!({name});"),
+        ),
+        Some(lifetime) => (
+            format!("{vis}struct {name}<{lifetime}>(&{lifetime} str);"),
+            format!("
+impl<{lifetime}> TryFrom<&{lifetime} str> for {name}<{lifetime}> {{
+    type Error = ::compiled_regex::InvalidFormat;
+
+    fn try_from(input: &{lifetime} str) -> Result<Self, Self::Error> {{
+        match {name}::is_match(input) {{
+            true => Ok({name}(input)),
+            false => Err({name}::invalid()),
+        }}
+    }}
+}}
+
+impl<{lifetime}> {name}<{lifetime}> {{
+    /// The borrowed string, for as long as it is borrowed
+    #[allow(dead_code)]
+    {vis}fn into_inner(self) -> &{lifetime} str {{
+        self.0
+    }}
+}}
+
+::compiled_regex::__newtype_serde!({name}<{lifetime}>);"),
+        ),
+    };
+
+    // Methods not depending on the string are implemented for any
+    // lifetime, and a borrowed string is as cheap to copy as a reference
+    let (generics, copy) = match lifetime {
+        Some(_) => ("<'_>", "Clone, Copy"),
+        None => ("", "Clone"),
+    };
+
+    Ok(format!("{code}
+{doc}
+#[derive({copy}, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+{declaration}
+
+impl {name}{generics} {{
+    /// Whether the whole of `input` matches the pattern
+    #[allow(dead_code)]
+    {vis}fn is_match(input: &str) -> bool {{
+        {matcher}::is_match(input)
+    }}
+
+    fn invalid() -> ::compiled_regex::InvalidFormat {{
+        ::compiled_regex::InvalidFormat::new({name:?}, {pattern:?})
+    }}
+
+    #[allow(dead_code)]
+    {vis}fn as_str(&self) -> &str {{
+        &self.0
+    }}
+}}
+{conversions}
+
+impl AsRef<str> for {name}{generics} {{
+    fn as_ref(&self) -> &str {{
+        &self.0
+    }}
+}}
+
+impl ::core::ops::Deref for {name}{generics} {{
+    type Target = str;
+
+    fn deref(&self) -> &str {{
+        &self.0
+    }}
+}}
+
+impl ::core::fmt::Display for {name}{generics} {{
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {{
+        f.write_str(&self.0)
+    }}
+}}
+"))
+}
+
+/// A test running the generated `is_match` of the type `name` on its
+/// test vectors, and comparing it to `regex::Regex` when the `regex`
+/// feature of `compiled_regex` is enabled
+pub fn test_vectors_fn(name: &str, pattern: &str, vectors: &TestVectors) -> String {
+    let list = |inputs: &[String]| {
+        inputs
+            .iter()
+            .map(|input| format!("{input:?}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let matches = list(&vectors.matches);
+    let rejects = list(&vectors.rejects);
+
+    format!("
+#[cfg(test)]
+#[test]
+#[allow(non_snake_case)]
+fn __{name}_test_vectors() {{
+    let matches: &[&str] = &[{matches}];
+    let rejects: &[&str] = &[{rejects}];
+
+    for input in matches {{
+        assert!({name}::is_match(input), \"`{name}` should match {{input:?}}\");
+    }}
+
+    for input in rejects {{
+        assert!(!{name}::is_match(input), \"`{name}` should not match {{input:?}}\");
+    }}
+
+    for input in matches.iter().chain(rejects) {{
+        if let Some(expected) = ::compiled_regex::regex_is_match({pattern:?}, input) {{
+            assert_eq!({name}::is_match(input), expected, \"`{name}` differs from `regex` on {{input:?}}\");
+        }}
+    }}
+}}")
+}
+
+/// The type `parse_regex_set!(Name = ["pattern", ...], options)`
+/// produces
+pub fn regex_set_type(
+    name: &str,
+    patterns: &[&str],
+    options: &Options,
+    naming: Naming<'_>,
+) -> Result<String> {
+    let compiled = parse_regex_set(patterns, options)?;
+    let vis = naming.visibility();
+
+    let literals = patterns
+        .iter()
+        .map(|pattern| format!("{pattern:?}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let items = format!("{CHAR_GET_FUNC}{0}
+/// Whether any of the patterns matches
+#[allow(dead_code)]
+{vis}fn is_match(input: &str) -> bool {{
+    Self::__with_cache(|cache| Self::is_match_with(input, cache))
+}}
+
+/// Every pattern that matches anywhere in `input`
+#[allow(dead_code)]
+{vis}fn matches(input: &str) -> ::compiled_regex::SetMatches {{
+    Self::__with_cache(|cache| Self::matches_with(input, cache))
+}}
+
+/// Index of the pattern of the leftmost-first match, which is the
+/// one `regex::Regex::find` reports for the alternation of all
+/// patterns in order
+#[allow(dead_code)]
+{vis}fn first_match(input: &str) -> Option<usize> {{
+    Self::__with_cache(|cache| Self::first_match_with(input, cache))
+}}
+
+/// Memory for the searches of `is_match_with`, `matches_with` and
+/// `first_match_with`, so matching many inputs allocates it once
+#[allow(dead_code)]
+{vis}fn create_cache() -> ::compiled_regex::SetCache {{
+    ::compiled_regex::SetCache::new({states})
+}}
+
+/// `is_match` with the memory of `cache`
+#[allow(dead_code)]
+{vis}fn is_match_with(input: &str, cache: &mut ::compiled_regex::SetCache) -> bool {{
+    let mut matches = ::compiled_regex::SetMatches::new(0);
+
+    cache.ensure({states});
+    Self::__search(input, ::compiled_regex::SetMode::Earliest, &mut matches, cache).is_some()
+}}
+
+/// `matches` with the memory of `cache`
+#[allow(dead_code)]
+{vis}fn matches_with(input: &str, cache: &mut ::compiled_regex::SetCache) -> ::compiled_regex::SetMatches {{
+    let mut matches = ::compiled_regex::SetMatches::new({len});
+
+    cache.ensure({states});
+    Self::__search(input, ::compiled_regex::SetMode::All, &mut matches, cache);
+    matches
+}}
+
+/// `first_match` with the memory of `cache`
+#[allow(dead_code)]
+{vis}fn first_match_with(input: &str, cache: &mut ::compiled_regex::SetCache) -> Option<usize> {{
+    let mut matches = ::compiled_regex::SetMatches::new(0);
+
+    cache.ensure({states});
+    Self::__search(input, ::compiled_regex::SetMode::LeftmostFirst, &mut matches, cache)
+}}
+
+fn __with_cache<R>(search: impl FnOnce(&mut ::compiled_regex::SetCache) -> R) -> R {{
+    ::compiled_regex::__with_set_cache!({states}, search)
+}}
+
+/// Number of patterns in the set
+#[allow(dead_code)]
+{vis}fn len() -> usize {{
+    {len}
+}}
+
+/// The patterns in the order they were given
+#[allow(dead_code)]
+{vis}fn patterns() -> &'static [&'static str] {{
+    &[{literals}]
+}}",
+        compiled.items.trim_start(),
+        len = compiled.patterns,
+        states = compiled.states);
+
+    let doc = if patterns.len() <= 8 {
+        format!("/// Set of the patterns\n///\n{}", type_doc(patterns))
+    } else {
+        format!("/// Set of {} patterns", patterns.len())
+    };
+
+    Ok(declare(name, &doc, &items, naming))
+}
+
+/// The set `name` of all lines of a pattern file, followed by a type
+/// for every named line.
+///
+/// Errors are returned with the line they occurred on, or 0 for errors
+/// of the file as a whole.
+pub fn pattern_file_types(
+    name: &str,
+    lines: &[PatternLine],
+    options: &Options,
+    naming: Naming<'_>,
+) -> core::result::Result<String, Vec<(usize, CompileError)>> {
+    let patterns = lines
+        .iter()
+        .map(|line| line.pattern.as_str())
+        .collect::<Vec<_>>();
+
+    let mut code = match regex_set_type(name, &patterns, options, naming) {
+        Ok(code) => code,
+        Err(CompileError::Patterns(errors)) => {
+            return Err(errors
+                .into_iter()
+                .map(|(i, err)| (lines[i].line, err))
+                .collect())
+        }
+        Err(err) => return Err(vec![(0, err)]),
+    };
+
+    let mut names = std::collections::HashSet::new();
+
+    for line in lines {
+        let Some(name) = &line.name else {
+            continue;
+        };
+
+        if !names.insert(name) {
+            return Err(vec![(
+                line.line,
+                CompileError::DuplicateName(name.clone()),
+            )]);
+        }
+
+        let regex = regex_type(name, &line.pattern, &Options::default(), naming)
+            .map_err(|err| vec![(line.line, err)])?;
+
+        code.push_str(&regex);
+    }
+
+    Ok(code)
+}
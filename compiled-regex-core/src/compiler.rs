@@ -0,0 +1,724 @@
+use regex_syntax::hir::{Class, Hir, HirKind, Look, Repetition};
+
+use crate::program::{
+    Inst, InstChar, InstEmptyLook, InstLiterals, InstPtr, InstRanges,
+    InstRepeat, InstSave, InstSplit, Program,
+};
+use crate::types::{CompileError, Result};
+
+/// Repetitions with a bound of at least this are compiled into a
+/// single `Inst::Repeat` when their body allows it, instead of being
+/// unrolled into a copy per repetition
+pub const MIN_COUNTED_REPETITION: u32 = 2;
+
+/// Programs are not compiled past this many instructions, as the code
+/// generated from them would take rustc about forever to compile
+pub const MAX_INSTRUCTIONS: usize = 100_000;
+
+/// Placeholder for gotos that are yet to be filled
+const HOLE: InstPtr = InstPtr::MAX;
+
+/// A goto of an instruction that has not been given its target yet
+#[derive(Clone, Copy, Debug)]
+enum Hole {
+    Goto(InstPtr),
+    Goto1(InstPtr),
+    Goto2(InstPtr),
+}
+
+/// Compiled sub-expression, starting at `entry` and continuing to
+/// whatever the `holes` will be filled with
+#[derive(Debug)]
+struct Patch {
+    entry: InstPtr,
+    holes: Vec<Hole>,
+}
+
+/// Compiles the HIR of a pattern into a [`Program`].
+///
+/// Follows the layout of the `regex` compiler, except that counted
+/// repetitions are kept as `Inst::Repeat` rather than unrolled.
+#[derive(Default)]
+pub struct Compiler {
+    insts: Vec<Inst>,
+    unroll_repetitions: bool,
+    literal_tries: bool,
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Unroll counted repetitions like the `regex` compiler does.
+    ///
+    /// Needed by engines that remember the (instruction, position)
+    /// pairs they explored, since the count of an `Inst::Repeat` is not
+    /// part of that pair.
+    pub fn unroll_repetitions(mut self, yes: bool) -> Self {
+        self.unroll_repetitions = yes;
+        self
+    }
+
+    /// Compile alternations of literals into a single `Inst::Literals`.
+    ///
+    /// Only for engines that can consume more than one character in a
+    /// step, which the threads of a set can not.
+    pub fn literal_tries(mut self, yes: bool) -> Self {
+        self.literal_tries = yes;
+        self
+    }
+
+    pub fn compile(mut self, hir: &Hir) -> Result<Program> {
+        let patch = self.c_capture(0, hir)?;
+
+        let end = self.insts.len();
+        self.fill(&patch.holes, end);
+        self.insts.push(Inst::Match(0));
+
+        let properties = hir.properties();
+
+        Ok(Program {
+            insts: self.insts,
+            start: patch.entry,
+            is_anchored_start: properties
+                .look_set_prefix()
+                .contains(Look::Start),
+            is_anchored_end: properties
+                .look_set_suffix()
+                .contains(Look::End),
+        })
+    }
+
+    /// Compile the patterns of a set into a single program, where
+    /// pattern `i` ends in `Inst::Match(i)`.
+    ///
+    /// The patterns are tried in order through a chain of splits, so
+    /// earlier patterns take priority over later ones. The program is
+    /// anchored at the start when every pattern is.
+    pub fn compile_many(mut self, hirs: &[Hir]) -> Result<Program> {
+        let Some(last) = hirs.len().checked_sub(1) else {
+            return Err(CompileError::Unsupported(String::from(
+                "sets need at least one pattern",
+            )));
+        };
+
+        let mut entries = Vec::with_capacity(hirs.len());
+
+        for (i, hir) in hirs.iter().enumerate() {
+            let patch = self.c_capture(0, hir).map_err(|err| {
+                CompileError::Patterns(vec![(i, err)])
+            })?;
+
+            let end = self.insts.len();
+            self.fill(&patch.holes, end);
+            self.insts.push(Inst::Match(i));
+
+            entries.push(patch.entry);
+        }
+
+        let start = entries[..last].iter().rev().fold(
+            entries[last],
+            |goto2, &goto1| {
+                self.push(Inst::Split(InstSplit { goto1, goto2 }))
+            },
+        );
+
+        Ok(Program {
+            insts: self.insts,
+            start,
+            is_anchored_start: hirs.iter().all(|hir| {
+                hir.properties().look_set_prefix().contains(Look::Start)
+            }),
+            is_anchored_end: hirs.iter().all(|hir| {
+                hir.properties().look_set_suffix().contains(Look::End)
+            }),
+        })
+    }
+
+    /// Compile a sub-expression, `None` if it did not produce any
+    /// instructions since it only matches the empty string
+    fn c(&mut self, hir: &Hir) -> Result<Option<Patch>> {
+        // Every copy of an unrolled repetition passes here
+        if self.insts.len() > MAX_INSTRUCTIONS {
+            return Err(CompileError::Unsupported(format!(
+                "pattern compiles to more than {MAX_INSTRUCTIONS} instructions"
+            )));
+        }
+
+        match hir.kind() {
+            HirKind::Empty => Ok(None),
+            HirKind::Literal(literal) => {
+                let literal = std::str::from_utf8(&literal.0)
+                    .map_err(|_| {
+                        CompileError::Unsupported(format!(
+                            "literal {:?} is not valid UTF-8",
+                            literal
+                        ))
+                    })?;
+
+                Ok(self.c_chars(literal.chars().map(|c| {
+                    Inst::Char(InstChar { goto: HOLE, c })
+                })))
+            }
+            HirKind::Class(class) => {
+                let inst = class_inst(class)?;
+
+                Ok(self.c_chars(std::iter::once(inst)))
+            }
+            HirKind::Look(look) => {
+                let entry = self.push(Inst::EmptyLook(InstEmptyLook {
+                    goto: HOLE,
+                    look: *look,
+                }));
+
+                Ok(Some(Patch {
+                    entry,
+                    holes: vec![Hole::Goto(entry)],
+                }))
+            }
+            HirKind::Repetition(repetition) => {
+                self.c_repetition(repetition)
+            }
+            HirKind::Capture(capture) => self
+                .c_capture(2 * capture.index as usize, &capture.sub)
+                .map(Some),
+            HirKind::Concat(subs) => self.c_concat(subs),
+            HirKind::Alternation(subs) => self.c_alternation(subs),
+        }
+    }
+
+    fn c_capture(&mut self, first_slot: usize, hir: &Hir) -> Result<Patch> {
+        let entry = self.push(Inst::Save(InstSave {
+            goto: HOLE,
+            slot: first_slot,
+        }));
+        let mut holes = vec![Hole::Goto(entry)];
+
+        if let Some(patch) = self.c(hir)? {
+            self.fill(&holes, patch.entry);
+            holes = patch.holes;
+        }
+
+        let end = self.push(Inst::Save(InstSave {
+            goto: HOLE,
+            slot: first_slot + 1,
+        }));
+        self.fill(&holes, end);
+
+        Ok(Patch {
+            entry,
+            holes: vec![Hole::Goto(end)],
+        })
+    }
+
+    /// Chain of instructions that each consume a single character
+    fn c_chars(
+        &mut self,
+        insts: impl Iterator<Item = Inst>,
+    ) -> Option<Patch> {
+        let mut patch: Option<Patch> = None;
+
+        for inst in insts {
+            let i = self.push(inst);
+
+            match &mut patch {
+                Some(patch) => {
+                    self.fill(&patch.holes, i);
+                    patch.holes = vec![Hole::Goto(i)];
+                }
+                None => {
+                    patch = Some(Patch {
+                        entry: i,
+                        holes: vec![Hole::Goto(i)],
+                    })
+                }
+            }
+        }
+
+        patch
+    }
+
+    fn c_concat<'a>(
+        &mut self,
+        subs: impl IntoIterator<Item = &'a Hir>,
+    ) -> Result<Option<Patch>> {
+        let mut patch: Option<Patch> = None;
+
+        for sub in subs {
+            let Some(next) = self.c(sub)? else {
+                continue;
+            };
+
+            match &mut patch {
+                Some(patch) => {
+                    self.fill(&patch.holes, next.entry);
+                    patch.holes = next.holes;
+                }
+                None => patch = Some(next),
+            }
+        }
+
+        Ok(patch)
+    }
+
+    fn c_alternation(&mut self, subs: &[Hir]) -> Result<Option<Patch>> {
+        if self.literal_tries {
+            if let Some(literals) = alternation_literals(subs) {
+                let entry = self.push(Inst::Literals(InstLiterals {
+                    goto: HOLE,
+                    literals,
+                }));
+
+                return Ok(Some(Patch {
+                    entry,
+                    holes: vec![Hole::Goto(entry)],
+                }));
+            }
+        }
+
+        let mut entry = None;
+        let mut holes = vec![];
+        // The second goto of the previous split, which leads to the
+        // next alternative
+        let mut next_alternative: Option<Hole> = None;
+
+        for (i, sub) in subs.iter().enumerate() {
+            let is_last = i + 1 == subs.len();
+
+            let goto = if is_last {
+                next_alternative.unwrap()
+            } else {
+                let split = self.push_split();
+
+                match next_alternative {
+                    Some(hole) => self.fill(&[hole], split),
+                    None => entry = Some(split),
+                }
+
+                next_alternative = Some(Hole::Goto2(split));
+                Hole::Goto1(split)
+            };
+
+            match self.c(sub)? {
+                Some(patch) => {
+                    self.fill(&[goto], patch.entry);
+                    holes.extend(patch.holes);
+                }
+                // Empty alternative, continue directly after the
+                // alternation
+                None => holes.push(goto),
+            }
+        }
+
+        Ok(entry.map(|entry| Patch { entry, holes }))
+    }
+
+    fn c_repetition(
+        &mut self,
+        repetition: &Repetition,
+    ) -> Result<Option<Patch>> {
+        let Repetition {
+            min,
+            max,
+            greedy,
+            ref sub,
+        } = *repetition;
+
+        let is_counted = !self.unroll_repetitions
+            && (min >= MIN_COUNTED_REPETITION
+                || max.is_some_and(|max| max >= MIN_COUNTED_REPETITION));
+
+        if is_counted {
+            if let Some(body) = repeat_body(sub) {
+                let entry = self.push(Inst::Repeat(InstRepeat {
+                    goto: HOLE,
+                    min: min as usize,
+                    max: max.map(|max| max as usize),
+                    greedy,
+                    body,
+                }));
+
+                return Ok(Some(Patch {
+                    entry,
+                    holes: vec![Hole::Goto(entry)],
+                }));
+            }
+        }
+
+        match (min, max) {
+            (0, Some(1)) => self.c_zero_or_one(sub, greedy),
+            (0, None) => self.c_zero_or_more(sub, greedy),
+            (1, None) => self.c_one_or_more(sub, greedy),
+            // The last repetition is the one looping
+            (min, None) => {
+                let patch = self.c_concat(
+                    std::iter::repeat_n(sub.as_ref(), min as usize - 1),
+                )?;
+                let rest = self.c_one_or_more(sub, greedy)?;
+
+                Ok(self.join(patch, rest))
+            }
+            // Mandatory repetitions followed by nested optional ones,
+            // `x{2,4}` being `xx(?:x(?:x)?)?`
+            (min, Some(max)) => {
+                let mut patch = self.c_concat(
+                    std::iter::repeat_n(sub.as_ref(), min as usize),
+                )?;
+                let mut holes = vec![];
+
+                for _ in min..max {
+                    let split = self.push_split();
+                    let Some(optional) = self.c(sub)? else {
+                        self.insts.pop();
+                        break;
+                    };
+
+                    let (enter, skip) = if greedy {
+                        (Hole::Goto1(split), Hole::Goto2(split))
+                    } else {
+                        (Hole::Goto2(split), Hole::Goto1(split))
+                    };
+
+                    self.fill(&[enter], optional.entry);
+                    holes.push(skip);
+
+                    patch = self.join(
+                        patch,
+                        Some(Patch {
+                            entry: split,
+                            holes: optional.holes,
+                        }),
+                    );
+                }
+
+                Ok(patch.map(|mut patch| {
+                    patch.holes.extend(holes);
+                    patch
+                }))
+            }
+        }
+    }
+
+    fn c_zero_or_one(
+        &mut self,
+        sub: &Hir,
+        greedy: bool,
+    ) -> Result<Option<Patch>> {
+        let split = self.push_split();
+        let Some(mut patch) = self.c(sub)? else {
+            self.insts.pop();
+            return Ok(None);
+        };
+
+        let (enter, skip) = if greedy {
+            (Hole::Goto1(split), Hole::Goto2(split))
+        } else {
+            (Hole::Goto2(split), Hole::Goto1(split))
+        };
+
+        self.fill(&[enter], patch.entry);
+        patch.holes.push(skip);
+
+        Ok(Some(Patch {
+            entry: split,
+            holes: patch.holes,
+        }))
+    }
+
+    fn c_zero_or_more(
+        &mut self,
+        sub: &Hir,
+        greedy: bool,
+    ) -> Result<Option<Patch>> {
+        let split = self.push_split();
+        let Some(patch) = self.c(sub)? else {
+            self.insts.pop();
+            return Ok(None);
+        };
+
+        let (enter, exit) = if greedy {
+            (Hole::Goto1(split), Hole::Goto2(split))
+        } else {
+            (Hole::Goto2(split), Hole::Goto1(split))
+        };
+
+        self.fill(&[enter], patch.entry);
+        self.fill(&patch.holes, split);
+
+        Ok(Some(Patch {
+            entry: split,
+            holes: vec![exit],
+        }))
+    }
+
+    fn c_one_or_more(
+        &mut self,
+        sub: &Hir,
+        greedy: bool,
+    ) -> Result<Option<Patch>> {
+        let Some(patch) = self.c(sub)? else {
+            return Ok(None);
+        };
+
+        let split = self.push_split();
+
+        let (enter, exit) = if greedy {
+            (Hole::Goto1(split), Hole::Goto2(split))
+        } else {
+            (Hole::Goto2(split), Hole::Goto1(split))
+        };
+
+        self.fill(&patch.holes, split);
+        self.fill(&[enter], patch.entry);
+
+        Ok(Some(Patch {
+            entry: patch.entry,
+            holes: vec![exit],
+        }))
+    }
+
+    fn join(&mut self, a: Option<Patch>, b: Option<Patch>) -> Option<Patch> {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                self.fill(&a.holes, b.entry);
+
+                Some(Patch {
+                    entry: a.entry,
+                    holes: b.holes,
+                })
+            }
+            (a, None) => a,
+            (None, b) => b,
+        }
+    }
+
+    fn push(&mut self, inst: Inst) -> InstPtr {
+        self.insts.push(inst);
+        self.insts.len() - 1
+    }
+
+    fn push_split(&mut self) -> InstPtr {
+        self.push(Inst::Split(InstSplit {
+            goto1: HOLE,
+            goto2: HOLE,
+        }))
+    }
+
+    fn fill(&mut self, holes: &[Hole], target: InstPtr) {
+        for hole in holes {
+            match *hole {
+                Hole::Goto(i) => match &mut self.insts[i] {
+                    Inst::Save(x) => x.goto = target,
+                    Inst::EmptyLook(x) => x.goto = target,
+                    Inst::Char(x) => x.goto = target,
+                    Inst::Ranges(x) => x.goto = target,
+                    Inst::Bytes(x) => x.goto = target,
+                    Inst::Repeat(x) => x.goto = target,
+                    Inst::Literals(x) => x.goto = target,
+                    inst => unreachable!("fill: {inst:?} has no goto"),
+                },
+                Hole::Goto1(i) => match &mut self.insts[i] {
+                    Inst::Split(x) => x.goto1 = target,
+                    inst => unreachable!("fill: {inst:?} has no goto1"),
+                },
+                Hole::Goto2(i) => match &mut self.insts[i] {
+                    Inst::Split(x) => x.goto2 = target,
+                    inst => unreachable!("fill: {inst:?} has no goto2"),
+                },
+            }
+        }
+    }
+}
+
+/// Single character instruction for a class
+fn class_inst(class: &Class) -> Result<Inst> {
+    let ranges = match class {
+        Class::Unicode(class) => class
+            .ranges()
+            .iter()
+            .map(|range| (range.start(), range.end()))
+            .collect::<Vec<_>>(),
+        Class::Bytes(class) => {
+            if !class.is_ascii() {
+                return Err(CompileError::Unsupported(format!(
+                    "byte class {:?} matches non-ASCII bytes",
+                    class
+                )));
+            }
+
+            class
+                .ranges()
+                .iter()
+                .map(|range| (range.start() as char, range.end() as char))
+                .collect::<Vec<_>>()
+        }
+    };
+
+    Ok(match ranges.as_slice() {
+        &[(start, end)] if start == end => {
+            Inst::Char(InstChar { goto: HOLE, c: start })
+        }
+        _ => Inst::Ranges(InstRanges { goto: HOLE, ranges }),
+    })
+}
+
+/// The body of a repetition as single character instructions, if it
+/// always consumes the same number of characters and has a single way
+/// of matching.
+fn repeat_body(hir: &Hir) -> Option<Vec<Inst>> {
+    match hir.kind() {
+        HirKind::Literal(literal) => {
+            let literal = std::str::from_utf8(&literal.0).ok()?;
+
+            Some(
+                literal
+                    .chars()
+                    .map(|c| Inst::Char(InstChar { goto: HOLE, c }))
+                    .collect(),
+            )
+        }
+        HirKind::Class(class) => class_inst(class).ok().map(|x| vec![x]),
+        HirKind::Concat(subs) => subs.iter().try_fold(vec![], |mut body, sub| {
+            body.extend(repeat_body(sub)?);
+            Some(body)
+        }),
+        _ => None,
+    }
+}
+
+/// The alternatives as strings in order without duplicates, if they
+/// are all non-empty literals. A later duplicate can only match where
+/// the first one already did, so it is never needed.
+fn alternation_literals(subs: &[Hir]) -> Option<Vec<String>> {
+    let mut literals: Vec<String> = Vec::with_capacity(subs.len());
+
+    for sub in subs {
+        let HirKind::Literal(literal) = sub.kind() else {
+            return None;
+        };
+        let literal = std::str::from_utf8(&literal.0).ok()?;
+
+        if !literals.iter().any(|x| x == literal) {
+            literals.push(literal.to_string());
+        }
+    }
+
+    Some(literals)
+}
+
+#[cfg(test)]
+mod tests {
+    use regex_syntax::Parser;
+
+    use super::Compiler;
+    use crate::program::{Inst, InstSplit, Program};
+
+    fn compile(pattern: &str) -> Program {
+        let hir = Parser::new().parse(pattern).unwrap();
+
+        Compiler::new().compile(&hir).unwrap()
+    }
+
+    #[test]
+    fn counted_repetition_is_not_unrolled() {
+        let program = compile("[a-f0-9]{45,}/a{1000}");
+
+        // Save, Repeat, '/', Repeat, Save, Match
+        assert_eq!(program.insts.len(), 6);
+        assert!(matches!(
+            &program.insts[1],
+            Inst::Repeat(x) if x.min == 45 && x.max.is_none() && x.greedy
+        ));
+        assert!(matches!(
+            &program.insts[3],
+            Inst::Repeat(x) if x.min == 1000 && x.max == Some(1000)
+        ));
+    }
+
+    #[test]
+    fn instruction_limit() {
+        let patterns = [
+            "(?:ab|c){10000000}",
+            "^(?:$a){0,4000000000}$",
+            "(?:(?:ab|c){1000}){1000}",
+        ];
+
+        for pattern in patterns {
+            let hir = Parser::new().parse(pattern).unwrap();
+
+            assert!(Compiler::new().compile(&hir).is_err(), "{pattern}");
+        }
+
+        // Unless the repetition is counted
+        assert_eq!(compile("a{10000000}").insts.len(), 4);
+    }
+
+    #[test]
+    fn unrolled_repetition() {
+        let hir = Parser::new().parse("a{3,5}").unwrap();
+        let program = Compiler::new()
+            .unroll_repetitions(true)
+            .compile(&hir)
+            .unwrap();
+
+        assert!(!program.insts.iter().any(|x| matches!(x, Inst::Repeat(_))));
+        // Save, 3 times 'a', 2 times Split and 'a', Save, Match
+        assert_eq!(program.insts.len(), 10);
+    }
+
+    #[test]
+    fn set_of_patterns() {
+        let hirs = ["a", "^b"]
+            .map(|pattern| Parser::new().parse(pattern).unwrap());
+        let program = Compiler::new().compile_many(&hirs).unwrap();
+
+        assert_eq!(program.insts[3], Inst::Match(0));
+        assert_eq!(program.insts[8], Inst::Match(1));
+        assert_eq!(
+            program.insts[program.start],
+            Inst::Split(InstSplit { goto1: 0, goto2: 4 })
+        );
+        assert!(!program.is_anchored_start);
+
+        assert!(Compiler::new().compile_many(&[]).is_err());
+    }
+
+    #[test]
+    fn repetition_with_captures_is_unrolled() {
+        let program = compile("(a){3}");
+
+        assert!(!program
+            .insts
+            .iter()
+            .any(|inst| matches!(inst, Inst::Repeat(_))));
+        assert_eq!(
+            program
+                .insts
+                .iter()
+                .filter(|inst| matches!(inst, Inst::Char(_)))
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn literal_alternation() {
+        let hir = Parser::new().parse("(?:ab|abc|b|ab)d").unwrap();
+
+        let program = Compiler::new()
+            .literal_tries(true)
+            .compile(&hir)
+            .unwrap();
+        assert!(program.insts.iter().any(|inst| matches!(
+            inst,
+            Inst::Literals(x) if x.literals == ["ab", "abc", "b"]
+        )));
+
+        let program = compile("(?:ab|abc|b|ab)d");
+        assert!(!program
+            .insts
+            .iter()
+            .any(|inst| matches!(inst, Inst::Literals(_))));
+    }
+}
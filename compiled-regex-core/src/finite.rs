@@ -0,0 +1,262 @@
+use regex_syntax::hir::{Class, Hir, HirKind, Look};
+
+use crate::ir::functions::{
+    time_name, ProgramImplementation, INDEX_PARAM_NAME, INPUT_PARAM_NAME,
+};
+use crate::CompiledRegex;
+
+/// Most strings a language is enumerated to, larger ones are matched
+/// by an automaton
+pub const MAX_LANGUAGE: usize = 4096;
+
+/// A string of the language being enumerated, and whether it passed an
+/// end of input assertion, after which nothing can follow
+type Partial = (String, bool);
+
+/// Every string matched by `hir`, sorted by length and then by bytes.
+///
+/// `None` unless the pattern is anchored at both ends, which makes its
+/// only possible match the whole input, and matches at most
+/// `MAX_LANGUAGE` strings. Assertions other than the start and end of
+/// the input are not enumerated either.
+pub fn language(hir: &Hir) -> Option<Vec<String>> {
+    let properties = hir.properties();
+
+    if !properties.look_set_prefix().contains(Look::Start)
+        || !properties.look_set_suffix().contains(Look::End)
+    {
+        return None;
+    }
+
+    let mut language = extend(hir, vec![(String::new(), false)])?
+        .into_iter()
+        .map(|(string, _)| string)
+        .collect::<Vec<_>>();
+
+    language.sort_by(|a, b| (a.len(), a.as_bytes()).cmp(&(b.len(), b.as_bytes())));
+    language.dedup();
+
+    Some(language)
+}
+
+/// The strings of `partials` followed by every string of `hir`
+fn extend(hir: &Hir, partials: Vec<Partial>) -> Option<Vec<Partial>> {
+    let mut extended = match hir.kind() {
+        HirKind::Empty => partials,
+        HirKind::Literal(literal) => {
+            let literal = std::str::from_utf8(&literal.0).ok()?;
+
+            append(partials, &[literal.to_string()])?
+        }
+        HirKind::Class(class) => append(partials, &class_strings(class)?)?,
+        HirKind::Look(Look::Start) => partials
+            .into_iter()
+            .filter(|(string, _)| string.is_empty())
+            .collect(),
+        HirKind::Look(Look::End) => partials
+            .into_iter()
+            .map(|(string, _)| (string, true))
+            .collect(),
+        HirKind::Look(_) => return None,
+        HirKind::Repetition(repetition) => {
+            let max = repetition.max?;
+            let mut extended = vec![];
+            let mut repeated = partials;
+
+            for count in 0..=max {
+                if count >= repetition.min {
+                    extended.extend(repeated.iter().cloned());
+                }
+
+                if count < max {
+                    repeated = extend(&repetition.sub, repeated)?;
+                }
+
+                if extended.len() + repeated.len() > MAX_LANGUAGE {
+                    return None;
+                }
+            }
+
+            extended
+        }
+        HirKind::Capture(capture) => extend(&capture.sub, partials)?,
+        HirKind::Concat(subs) => subs
+            .iter()
+            .try_fold(partials, |partials, sub| extend(sub, partials))?,
+        HirKind::Alternation(subs) => {
+            let mut extended = vec![];
+
+            for sub in subs {
+                extended.extend(extend(sub, partials.clone())?);
+
+                if extended.len() > MAX_LANGUAGE {
+                    return None;
+                }
+            }
+
+            extended
+        }
+    };
+
+    extended.sort_unstable();
+    extended.dedup();
+
+    (extended.len() <= MAX_LANGUAGE).then_some(extended)
+}
+
+/// Every partial followed by every suffix, dropping the partials that
+/// passed the end of the input
+fn append(partials: Vec<Partial>, suffixes: &[String]) -> Option<Vec<Partial>> {
+    if partials.len().saturating_mul(suffixes.len()) > MAX_LANGUAGE {
+        return None;
+    }
+
+    let appended = partials
+        .into_iter()
+        .filter(|(_, ended)| !ended)
+        .flat_map(|(string, _)| {
+            suffixes
+                .iter()
+                .map(move |suffix| (format!("{string}{suffix}"), false))
+        })
+        .collect();
+
+    Some(appended)
+}
+
+/// Every character of `class` as a string, if there are not too many
+fn class_strings(class: &Class) -> Option<Vec<String>> {
+    let ranges = match class {
+        Class::Unicode(class) => class
+            .ranges()
+            .iter()
+            .map(|range| (range.start(), range.end()))
+            .collect::<Vec<_>>(),
+        Class::Bytes(class) if class.is_ascii() => class
+            .ranges()
+            .iter()
+            .map(|range| (range.start() as char, range.end() as char))
+            .collect::<Vec<_>>(),
+        Class::Bytes(_) => return None,
+    };
+
+    let len = ranges
+        .iter()
+        .map(|&(start, end)| end as usize - start as usize + 1)
+        .sum::<usize>();
+
+    (len <= MAX_LANGUAGE).then(|| {
+        ranges
+            .into_iter()
+            .flat_map(|(start, end)| start..=end)
+            .map(String::from)
+            .collect()
+    })
+}
+
+/// Generate the root function matching the strings of `language`, which
+/// can only match the whole input.
+///
+/// Instead of an automaton the length of the input selects a sorted
+/// table of the strings of that length, which is binary searched.
+pub fn parse(language: &[String]) -> CompiledRegex {
+    let mut arms = vec![];
+
+    for strings in language.chunk_by(|a, b| a.len() == b.len()) {
+        let table = strings
+            .iter()
+            .map(|string| {
+                let escaped = string
+                    .bytes()
+                    .map(|byte| std::ascii::escape_default(byte).to_string())
+                    .collect::<String>();
+
+                format!("b\"{escaped}\"")
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        arms.push(format!(
+            "{} => {{
+    const LANGUAGE: &[&[u8]] = &[{table}];
+    LANGUAGE.binary_search(&bytes).is_ok()
+}}",
+            strings[0].len()
+        ));
+    }
+
+    let body = format!(
+        "// Anchored at both ends, so only the whole input can match
+if *{INDEX_PARAM_NAME} != 0 {{
+    return false;
+}}
+let bytes = {INPUT_PARAM_NAME}.as_bytes();
+let is_member = match bytes.len() {{
+    {}
+    _ => false,
+}};
+if is_member {{
+    *{INDEX_PARAM_NAME} = bytes.len();
+}}
+is_member",
+        arms.join("\n").replace('\n', "\n    ")
+    );
+
+    CompiledRegex {
+        implementation: ProgramImplementation {
+            name: time_name!('F'),
+            body,
+            children: vec![],
+            parameters: "",
+            constants: vec![],
+        },
+        is_anchored_start: true,
+        is_anchored_end: true,
+        fallible: None,
+        bounded: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex_syntax::Parser;
+
+    use super::{language, parse};
+
+    fn enumerate(pattern: &str) -> Option<Vec<String>> {
+        language(&Parser::new().parse(pattern).unwrap())
+    }
+
+    #[test]
+    fn finite_languages() {
+        assert_eq!(
+            enumerate("^(GET|POST|PUT|DELETE|PATCH)$").unwrap(),
+            ["GET", "PUT", "POST", "PATCH", "DELETE"]
+        );
+        assert_eq!(
+            enumerate("^a{1,2}[xy]?$").unwrap(),
+            ["a", "aa", "ax", "ay", "aax", "aay"]
+        );
+        assert_eq!(enumerate("^(?:|b)$").unwrap(), ["", "b"]);
+        assert!(enumerate("^a$b$").unwrap().is_empty());
+    }
+
+    #[test]
+    fn infinite_or_unanchored() {
+        assert!(enumerate("^(GET|POST)").is_none());
+        assert!(enumerate("(GET|POST)$").is_none());
+        assert!(enumerate("^a+$").is_none());
+        assert!(enumerate(r"^\bGET$").is_none());
+        assert!(enumerate(r"^\w{3}$").is_none());
+    }
+
+    #[test]
+    fn tables_by_length() {
+        let compiled = parse(&enumerate("^(GET|POST|PUT)$").unwrap());
+        let body = &compiled.implementation.body;
+
+        assert!(body.contains("3 => {"));
+        assert!(body.contains(r#"&[b"GET", b"PUT"]"#));
+        assert!(body.contains(r#"&[b"POST"]"#));
+    }
+}
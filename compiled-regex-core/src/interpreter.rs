@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+
+use regex_syntax::hir::Look;
+
+use crate::program::{Inst, InstPtr, Program};
+
+/// Whether `program` matches anywhere in `input`, found by running its
+/// instructions instead of the code generated from them.
+///
+/// Only whether there is a match is needed, so the priority of the
+/// branches does not matter and every (instruction, position) pair is
+/// tried at most once.
+pub(crate) fn is_match(program: &Program, input: &str) -> bool {
+    let mut visited = HashSet::new();
+
+    (0..=input.len())
+        .filter(|&start| input.is_char_boundary(start))
+        .any(|start| run(program, input, start, &mut visited))
+}
+
+/// Whether `program` matches at `start`. A pair in `visited` failed
+/// from an earlier start, and so fails from this one too.
+fn run(
+    program: &Program,
+    input: &str,
+    start: usize,
+    visited: &mut HashSet<(InstPtr, usize)>,
+) -> bool {
+    let bytes = input.as_bytes();
+    let mut stack = vec![(program.start, start)];
+
+    while let Some((pc, at)) = stack.pop() {
+        if !visited.insert((pc, at)) {
+            continue;
+        }
+
+        match &program.insts[pc] {
+            Inst::Match(_) => return true,
+            Inst::Save(x) => stack.push((x.goto, at)),
+            Inst::Split(x) => {
+                stack.push((x.goto2, at));
+                stack.push((x.goto1, at));
+            }
+            Inst::EmptyLook(x) => {
+                if is_look_match(x.look, input, at) {
+                    stack.push((x.goto, at));
+                }
+            }
+            Inst::Char(x) => {
+                if let Some(next) = step(&program.insts[pc], input, at) {
+                    stack.push((x.goto, next));
+                }
+            }
+            Inst::Ranges(x) => {
+                if let Some(next) = step(&program.insts[pc], input, at) {
+                    stack.push((x.goto, next));
+                }
+            }
+            Inst::Bytes(x) => {
+                if bytes.get(at).is_some_and(|b| (x.start..=x.end).contains(b)) {
+                    stack.push((x.goto, at + 1));
+                }
+            }
+            Inst::Repeat(x) => {
+                // Every count between the minimum and the most
+                // repetitions that match continues at the same goto
+                let mut end = at;
+                let mut count = 0;
+
+                while x.max.is_none_or(|max| count < max) {
+                    let next = x
+                        .body
+                        .iter()
+                        .try_fold(end, |at, inst| step(inst, input, at));
+
+                    match next {
+                        Some(next) => end = next,
+                        None => break,
+                    }
+
+                    count += 1;
+
+                    if count >= x.min {
+                        stack.push((x.goto, end));
+                    }
+                }
+
+                if x.min == 0 {
+                    stack.push((x.goto, at));
+                }
+            }
+            Inst::Literals(x) => {
+                for literal in &x.literals {
+                    if bytes[at..].starts_with(literal.as_bytes()) {
+                        stack.push((x.goto, at + literal.len()));
+                    }
+                }
+            }
+        }
+    }
+
+    false
+}
+
+/// Position after the character at `at` when the `Char` or `Ranges`
+/// instruction `inst` accepts it
+fn step(inst: &Inst, input: &str, at: usize) -> Option<usize> {
+    let c = input.get(at..)?.chars().next()?;
+    let accepted = match inst {
+        Inst::Char(x) => x.c == c,
+        Inst::Ranges(x) => x.ranges.iter().any(|&(start, end)| (start..=end).contains(&c)),
+        _ => false,
+    };
+
+    accepted.then_some(at + c.len_utf8())
+}
+
+fn is_look_match(look: Look, input: &str, at: usize) -> bool {
+    // `Bytes` instructions can stop inside a character, which is
+    // neither a word character nor a line terminator
+    let before = input.get(..at).and_then(|s| s.chars().next_back());
+    let after = input.get(at..).and_then(|s| s.chars().next());
+
+    let is_word_ascii = |c: Option<char>| {
+        c.is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    let is_word = |c: Option<char>| c.is_some_and(regex_syntax::is_word_character);
+
+    match look {
+        Look::Start => at == 0,
+        Look::End => at == input.len(),
+        Look::StartLF => at == 0 || before == Some('\n'),
+        Look::EndLF => at == input.len() || after == Some('\n'),
+        Look::StartCRLF => match before {
+            _ if at == 0 => true,
+            Some('\n') => true,
+            Some('\r') => after != Some('\n'),
+            _ => false,
+        },
+        Look::EndCRLF => match after {
+            _ if at == input.len() => true,
+            Some('\r') => true,
+            Some('\n') => before != Some('\r'),
+            _ => false,
+        },
+        Look::WordAscii => is_word_ascii(before) != is_word_ascii(after),
+        Look::WordAsciiNegate => is_word_ascii(before) == is_word_ascii(after),
+        Look::WordUnicode => is_word(before) != is_word(after),
+        Look::WordUnicodeNegate => is_word(before) == is_word(after),
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+
+use sha2::{Digest, Sha256};
+
+use super::functions::hash_name;
+
+/// Classes with at most this many ranges are tested with a `match`
+/// pattern instead of a lookup table
+pub const SMALL_CLASS_RANGES: usize = 4;
+
+/// Tables emitted for character classes, keyed by their name.
+///
+/// The names are derived from the content of the table, so a class
+/// that appears more than once in a pattern will only produce a single
+/// table that is shared by every instruction testing it.
+#[derive(Debug, Default)]
+pub struct ClassTables {
+    tables: BTreeMap<String, String>,
+    /// The conditions are used in a `const fn`
+    const_fn: bool,
+}
+
+impl ClassTables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Search the tables with loops instead of closures, which can not
+    /// be called in a `const fn`
+    pub fn const_fn(mut self, yes: bool) -> Self {
+        self.const_fn = yes;
+        self
+    }
+
+    pub fn is_const_fn(&self) -> bool {
+        self.const_fn
+    }
+
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// The `const` items of all registered tables, to be placed inside
+    /// the `impl` block of the generated type
+    pub fn into_items(self) -> Vec<String> {
+        self.tables.into_values().collect()
+    }
+
+    fn register(&mut self, name: &str, item: impl FnOnce() -> String) {
+        if !self.tables.contains_key(name) {
+            self.tables.insert(name.to_string(), item());
+        }
+    }
+}
+
+/// Create a boolean expression testing whether the `char` in
+/// `variable` is contained in the class described by `ranges`.
+///
+/// Small classes become a `matches!`, larger classes are split into an
+/// ASCII part tested with a 128 bit bitmap and a non-ASCII part tested
+/// with a binary search over a sorted range table.
+pub fn class_condition(
+    ranges: &[(char, char)],
+    variable: &str,
+    tables: &mut ClassTables,
+) -> String {
+    if ranges.len() <= SMALL_CLASS_RANGES {
+        return match_condition(ranges, variable);
+    }
+
+    let ascii = ranges
+        .iter()
+        .filter(|(start, _)| start.is_ascii())
+        .map(|&(start, end)| (start, end.min('\x7F')))
+        .collect::<Vec<_>>();
+    let unicode = ranges
+        .iter()
+        .filter(|(_, end)| !end.is_ascii())
+        .map(|&(start, end)| (start.max('\u{80}'), end))
+        .collect::<Vec<_>>();
+
+    let ascii_condition = if ascii.is_empty() {
+        String::from("false")
+    } else if ascii.len() <= SMALL_CLASS_RANGES {
+        match_condition(&ascii, variable)
+    } else {
+        let bitmap = ascii_bitmap(&ascii);
+        let name = hash_name!('A', bitmap.to_ne_bytes());
+
+        tables.register(&name, || {
+            format!("const {name}: u128 = {bitmap:#x};\n")
+        });
+
+        format!("Self::{name} >> ({variable} as u32) & 1 == 1")
+    };
+
+    let unicode_condition = if unicode.is_empty() {
+        String::from("false")
+    } else if unicode.len() <= SMALL_CLASS_RANGES {
+        match_condition(&unicode, variable)
+    } else {
+        let mut hasher = Sha256::new();
+
+        unicode.iter().for_each(|&(start, end)| {
+            hasher.update((start as u32).to_ne_bytes());
+            hasher.update((end as u32).to_ne_bytes());
+        });
+
+        let name = hash_name!(hasher, 'R');
+
+        tables.register(&name, || {
+            super::functions::character_range_to_literal(
+                &unicode, &name,
+            )
+        });
+
+        // Closures can not be called in a `const fn`
+        if tables.const_fn {
+            format!("{{
+    let mut low = 0;
+    let mut high = Self::{name}.len();
+    let mut found = false;
+    while low < high {{
+        let middle = (low + high) / 2;
+        let (start, end) = Self::{name}[middle];
+        if end < {variable} {{
+            low = middle + 1;
+        }} else if {variable} < start {{
+            high = middle;
+        }} else {{
+            found = true;
+            break;
+        }}
+    }}
+    found
+}}")
+        } else {
+            format!(
+                "Self::{name}.binary_search_by(|&(start, end)| {{
+    if end < {variable} {{
+        ::core::cmp::Ordering::Less
+    }} else if {variable} < start {{
+        ::core::cmp::Ordering::Greater
+    }} else {{
+        ::core::cmp::Ordering::Equal
+    }}
+}}).is_ok()"
+            )
+        }
+    };
+
+    format!(
+        "if {variable}.is_ascii() {{
+    {}
+}} else {{
+    {}
+}}",
+        ascii_condition.replace('\n', "\n    "),
+        unicode_condition.replace('\n', "\n    ")
+    )
+}
+
+/// `matches!` over the ranges, used for classes that are too small to
+/// warrant a table
+fn match_condition(ranges: &[(char, char)], variable: &str) -> String {
+    if ranges.is_empty() {
+        return String::from("false");
+    }
+
+    let patterns = ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                format!("'{}'", start.escape_unicode())
+            } else {
+                format!(
+                    "'{}'..='{}'",
+                    start.escape_unicode(),
+                    end.escape_unicode()
+                )
+            }
+        })
+        .collect::<Vec<_>>();
+
+    format!("matches!({variable}, {})", patterns.join(" | "))
+}
+
+/// Bit `n` is set when the ASCII character `n` is part of the ranges
+fn ascii_bitmap(ranges: &[(char, char)]) -> u128 {
+    ranges.iter().fold(0, |bitmap, &(start, end)| {
+        (start as u32..=end as u32)
+            .fold(bitmap, |bitmap, c| bitmap | (1 << c))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ascii_bitmap, class_condition, ClassTables};
+
+    #[test]
+    fn small_class_is_match() {
+        let mut tables = ClassTables::new();
+
+        assert_eq!(
+            class_condition(&[('0', '9'), ('_', '_')], "c", &mut tables),
+            "matches!(c, '\\u{30}'..='\\u{39}' | '\\u{5f}')"
+        );
+        assert!(tables.is_empty());
+    }
+
+    #[test]
+    fn ascii_bitmap_digits() {
+        assert_eq!(ascii_bitmap(&[('0', '9')]), 0x3FF << 0x30);
+    }
+
+    #[test]
+    fn shared_tables() {
+        let ranges = [
+            ('$', '$'),
+            ('0', '9'),
+            ('A', 'Z'),
+            ('_', '_'),
+            ('a', 'z'),
+            ('\u{aa}', '\u{aa}'),
+            ('\u{b5}', '\u{b5}'),
+            ('\u{ba}', '\u{ba}'),
+            ('\u{c0}', '\u{d6}'),
+            ('\u{d8}', '\u{f6}'),
+        ];
+        let mut tables = ClassTables::new();
+
+        let a = class_condition(&ranges, "a", &mut tables);
+        let b = class_condition(&ranges, "a", &mut tables);
+
+        assert_eq!(a, b);
+        // one bitmap for the ASCII part, one range table for the rest
+        assert_eq!(tables.len(), 2);
+    }
+}
@@ -1,13 +1,15 @@
 #[allow(unused_imports)]
 use std::{fmt::Display, time::{SystemTime, UNIX_EPOCH}, char::EscapeUnicode};
 
-use regex::internal::Inst;
+use regex_syntax::hir::{Class, HirKind, Look};
 
-use sha2::{Digest, Sha256};
+use crate::program::{Inst, InstRepeat};
 
 use const_format::formatcp;
 
+use super::classes::{class_condition, ClassTables};
 use super::sections::Program;
+use super::trie::Trie;
 
 use crate::types::Result;
 
@@ -15,24 +17,54 @@ pub struct ProgramImplementation {
     pub body: String,
     pub name: String,
     pub children: Vec<ProgramImplementation>,
+    /// Parameters after the input and the index, empty for every function
+    /// but those of the structured engine within a loop body
+    pub parameters: &'static str,
+    /// Items shared between all functions, such as character class
+    /// tables. Placed in the `impl` block before any function.
+    pub constants: Vec<String>,
 }
 
-pub const INPUT_PARAM_NAME: &'static str = "input";
-pub const INPUT_PARAM_TYPE: &'static str = "&str";
-pub const INDEX_PARAM_NAME: &'static str = "index";
-pub const INDEX_PARAM_TYPE_INNER: &'static str = "usize";
-pub const INDEX_PARAM_TYPE: &'static str = formatcp!("&mut {INDEX_PARAM_TYPE_INNER}");
-pub const CHAR_GET_FUNC_NAME: &'static str = "__get_char";
+pub const INPUT_PARAM_NAME: &str = "input";
+pub const INPUT_PARAM_TYPE: &str = "&str";
+pub const INDEX_PARAM_NAME: &str = "index";
+pub const INDEX_PARAM_TYPE_INNER: &str = "usize";
+pub const INDEX_PARAM_TYPE: &str = formatcp!("&mut {INDEX_PARAM_TYPE_INNER}");
+pub const CHAR_GET_FUNC_NAME: &str = "__get_char";
 // public since its faster than attaching it as a child to the root ProgramImplementation
-pub const CHAR_GET_FUNC: &'static str =  formatcp!(
+pub const CHAR_GET_FUNC: &str =  formatcp!(
 "fn {CHAR_GET_FUNC_NAME}({INPUT_PARAM_NAME}: {INPUT_PARAM_TYPE}, {INDEX_PARAM_NAME}: {INDEX_PARAM_TYPE}) -> Option<char> {{
     {INPUT_PARAM_NAME}[*{INDEX_PARAM_NAME}..].chars().next()
 }}\n");
-pub const INNER_INDEX_NAME: &'static str = "inner_index";
-pub const INNER_INDEX_INIT: &'static str = formatcp!("let mut {INNER_INDEX_NAME}: {INDEX_PARAM_TYPE_INNER} = *{INDEX_PARAM_NAME};");
-pub const INNER_INDEX_END: &'static str = formatcp!("*{INDEX_PARAM_NAME} = {INNER_INDEX_NAME};");
-
-static mut PROG_COUNTER: usize = 0;
+/// `CHAR_GET_FUNC` as a `const fn`, decoding the UTF-8 of the character
+/// by hand since iterators are not `const`
+pub const CONST_CHAR_GET_FUNC: &str = formatcp!(
+"const fn {CHAR_GET_FUNC_NAME}({INPUT_PARAM_NAME}: {INPUT_PARAM_TYPE}, {INDEX_PARAM_NAME}: {INDEX_PARAM_TYPE}) -> Option<char> {{
+    let bytes = {INPUT_PARAM_NAME}.as_bytes();
+    let at = *{INDEX_PARAM_NAME};
+    if at >= bytes.len() {{
+        return None;
+    }}
+    let first = bytes[at] as u32;
+    let (len, mut c) = if first < 0x80 {{
+        (1, first)
+    }} else if first < 0xE0 {{
+        (2, first & 0x1F)
+    }} else if first < 0xF0 {{
+        (3, first & 0x0F)
+    }} else {{
+        (4, first & 0x07)
+    }};
+    let mut i = 1;
+    while i < len {{
+        c = (c << 6) | (bytes[at + i] as u32 & 0x3F);
+        i += 1;
+    }}
+    char::from_u32(c)
+}}\n");
+pub const INNER_INDEX_NAME: &str = "inner_index";
+pub const INNER_INDEX_INIT: &str = formatcp!("let mut {INNER_INDEX_NAME}: {INDEX_PARAM_TYPE_INNER} = *{INDEX_PARAM_NAME};");
+pub const INNER_INDEX_END: &str = formatcp!("*{INDEX_PARAM_NAME} = {INNER_INDEX_NAME};");
 
 macro_rules! hash_name {
     ($prefix:literal, $($x:expr),* ) => {
@@ -74,38 +106,70 @@ macro_rules! time_name {
 pub(crate) use hash_name;
 pub(crate) use time_name;
 
-impl<'a> ProgramImplementation {
+impl ProgramImplementation {
     pub fn empty() -> Self {
         ProgramImplementation {
             name: String::new(),
             body: String::new(),
-            children: vec![]
+            children: vec![],
+            parameters: "",
+            constants: vec![],
         }
     }
 
+    /// Generate the functions of a sectioned `program`
     pub(crate) fn try_parse(
         program: &Program,
     ) -> Result<ProgramImplementation> {
-        match program {
-            Program::Normal(inst) => try_parse_instructions(inst),
-            Program::Loop(program) => try_parse_loop(program),
-            Program::Choice(a, b) => try_parse_choice(a, b),
-            Program::Linear(programs) => try_parse_linear(programs),
-        }
+        let mut tables = ClassTables::new();
+        let mut generator = Generator {
+            tables: &mut tables,
+            children: vec![],
+            accept: None,
+            count: 0,
+            depth: 0,
+        };
+
+        let body = generator.code(program, None);
+        let children = generator.children;
+
+        Ok(ProgramImplementation {
+            name: time_name!('F'),
+            body,
+            children,
+            parameters: "",
+            constants: tables.into_items(),
+        })
     }
 }
 
-impl Display for ProgramImplementation {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.children.iter().try_for_each(|x| write!(f, "{}",x))?;
+impl ProgramImplementation {
+    /// The items with every function a `const fn`, for bodies that only
+    /// use `const` operations
+    pub fn const_fns(&self) -> String {
+        let mut items = String::new();
+        self.write_items(&mut items, "const ").unwrap();
+        items
+    }
+
+    fn write_items(&self, f: &mut impl std::fmt::Write, qualifiers: &str) -> std::fmt::Result {
+        self.constants.iter().try_for_each(|x| write!(f, "{}", x))?;
+        self.children.iter().try_for_each(|x| x.write_items(f, qualifiers))?;
         write!(f,
-            /*\n#[inline(always)]*/"\nfn {0}({INPUT_PARAM_NAME}: {INPUT_PARAM_TYPE}, {INDEX_PARAM_NAME}: {INDEX_PARAM_TYPE}) -> bool {{\n    {1}\n}}\n",
+            /*\n#[inline(always)]*/"\n{qualifiers}fn {0}({INPUT_PARAM_NAME}: {INPUT_PARAM_TYPE}, {INDEX_PARAM_NAME}: {INDEX_PARAM_TYPE}{1}) -> bool {{\n    {2}\n}}\n",
             self.name,
+            self.parameters,
             self.body.replace("\n", "\n    "))
     }
 }
 
-fn character_range_to_literal(range: &[(char, char)], literal_name: &str) -> String {
+impl Display for ProgramImplementation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_items(f, "")
+    }
+}
+
+pub(super) fn character_range_to_literal(range: &[(char, char)], literal_name: &str) -> String {
     let ranges = range
         .iter()
         .map(|(start, end)|
@@ -119,13 +183,14 @@ fn character_range_to_literal(range: &[(char, char)], literal_name: &str) -> Str
 
 pub fn instruction_code(
     instruction: &Inst,
+    tables: &mut ClassTables,
 ) -> String {
     match instruction {
         // Single Character match
         Inst::Char(x) => {
             format!("
 // From: {instruction:?}
-if Self::{CHAR_GET_FUNC_NAME}({INPUT_PARAM_NAME}, &mut {INNER_INDEX_NAME}) != Some('{0}') {{
+if !matches!(Self::{CHAR_GET_FUNC_NAME}({INPUT_PARAM_NAME}, &mut {INNER_INDEX_NAME}), Some('{0}')) {{
     return false
 }}
 {INNER_INDEX_NAME} += {1};", x.c.escape_unicode(), x.c.len_utf8())
@@ -133,30 +198,19 @@ if Self::{CHAR_GET_FUNC_NAME}({INPUT_PARAM_NAME}, &mut {INNER_INDEX_NAME}) != So
 
         // Range of Characters match
         Inst::Ranges(x) => {
-            let mut hasher = Sha256::new();
-
-            x.ranges.iter().for_each(|&(start, end)| {
-                hasher.update((start as u32).to_ne_bytes());
-                hasher.update((end as u32).to_ne_bytes());
-            });
-
-            let range_name = hash_name!(hasher, 'R');
+            let variable = time_name!('V');
 
             format!("
 // From: {instruction:?}
-{0}
-let {2} = Self::{CHAR_GET_FUNC_NAME}({INPUT_PARAM_NAME}, &mut {INNER_INDEX_NAME});
-if {2}.is_some() {{
-    let {2} = {2}.unwrap();
-    if {1}.iter().find(|(start, end)| *start <= {2} && {2} <= *end).is_some() {{
-        {INNER_INDEX_NAME} += {2}.len_utf8();
-    }} else {{
-        return false
-    }}
-}} else {{
+let Some({variable}) = Self::{CHAR_GET_FUNC_NAME}({INPUT_PARAM_NAME}, &mut {INNER_INDEX_NAME}) else {{
+    return false
+}};
+let is_member = {0};
+if !is_member {{
     return false
 }}
-", character_range_to_literal(&x.ranges, &range_name), range_name, time_name!('V'))
+{INNER_INDEX_NAME} += {variable}.len_utf8();
+", class_condition(&x.ranges, &variable, tables))
         },
 
         // Range of Bytes
@@ -168,177 +222,571 @@ if {}u8 <= {INPUT_PARAM_NAME}.as_bytes()[INNER_INDEX_NAME] && {INPUT_PARAM_NAME}
 }}
 {INDEX_PARAM_NAME} += 1;\n",
             x.start, x.end),
+        // Counted repetition, see `repeat_code` for why it does not
+        // backtrack here
+        Inst::Repeat(x) => format!("
+// From: {instruction:?}
+{}", repeat_code(x, None, "", tables)),
+        // zero-width assertions
+        Inst::EmptyLook(x) => format!("
+// From: {instruction:?}
+let holds = {};
+if !holds {{
+    return false
+}}
+", look_condition(x.look, INNER_INDEX_NAME, tables)),
         // match is only used for regex sets
         Inst::Match(_) |
         // save is used for location saving
-        Inst::Save(_) |
-        // zero-width assertions, might not be necessary for our scope
-        Inst::EmptyLook(_) => 
+        Inst::Save(_) =>
             String::new(),
         x => panic!("try_parse_instructions: {x:?} did not expect instruction type.")
     }
 }
 
+/// Expression checking the zero-width assertion `look` at the byte
+/// offset `position` of the input
+pub fn look_condition(
+    look: Look,
+    position: &str,
+    tables: &mut ClassTables,
+) -> String {
+    let bytes = format!("{INPUT_PARAM_NAME}.as_bytes()");
+    let at_start = format!("{position} == 0");
+    let at_end = format!("{position} == {INPUT_PARAM_NAME}.len()");
+    let before = |byte: &str| format!("{bytes}[{position} - 1] == b'{byte}'");
+    let after = |byte: &str| format!("{bytes}[{position}] == b'{byte}'");
+
+    match look {
+        Look::Start => at_start,
+        Look::End => at_end,
+        Look::StartLF => format!("{at_start} || {}", before("\\n")),
+        Look::EndLF => format!("{at_end} || {}", after("\\n")),
+        Look::StartCRLF => format!(
+            "{at_start} || {} || ({} && ({at_end} || !({})))",
+            before("\\n"),
+            before("\\r"),
+            after("\\n")
+        ),
+        Look::EndCRLF => format!(
+            "{at_end} || {} || ({} && ({at_start} || !({})))",
+            after("\\r"),
+            after("\\n"),
+            before("\\r")
+        ),
+        Look::WordAscii | Look::WordAsciiNegate => {
+            let boundary = format!("{{
+    let before = {position} > 0 && ({bytes}[{position} - 1] == b'_' || {bytes}[{position} - 1].is_ascii_alphanumeric());
+    let after = {position} < {INPUT_PARAM_NAME}.len() && ({bytes}[{position}] == b'_' || {bytes}[{position}].is_ascii_alphanumeric());
+    before != after
+}}");
+
+            if look == Look::WordAscii {
+                boundary
+            } else {
+                format!("!{boundary}")
+            }
+        }
+        Look::WordUnicode | Look::WordUnicodeNegate => {
+            let is_word = class_condition(&unicode_word_ranges(), "c", tables);
+            let boundary = if tables.is_const_fn() {
+                const_word_boundary(&is_word, position)
+            } else {
+                format!("{{
+    let is_word = |c: char| -> bool {{
+        {0}
+    }};
+    let before = {INPUT_PARAM_NAME}[..{position}].chars().next_back().is_some_and(is_word);
+    let after = {INPUT_PARAM_NAME}[{position}..].chars().next().is_some_and(is_word);
+    before != after
+}}", is_word.replace('\n', "\n        "))
+            };
+
+            if look == Look::WordUnicode {
+                boundary
+            } else {
+                format!("!{boundary}")
+            }
+        }
+    }
+}
 
-fn try_parse_instructions(
-    instructions: &[Inst],
-) -> Result<ProgramImplementation> {
-    let prog_num = unsafe {
-        PROG_COUNTER += 1;
-        PROG_COUNTER
-    };
-
-    let mut parts = instructions.iter().map(instruction_code).collect::<Vec<_>>();
+/// Unicode word boundary at `position` for a `const fn`, decoding the
+/// characters around it with `CONST_CHAR_GET_FUNC` instead of closures
+fn const_word_boundary(is_word: &str, position: &str) -> String {
+    let is_word = is_word.replace('\n', "\n        ");
+
+    format!("{{
+    let mut after_index = {position};
+    let after = match Self::{CHAR_GET_FUNC_NAME}({INPUT_PARAM_NAME}, &mut after_index) {{
+        Some(c) => {is_word},
+        None => false,
+    }};
+    let mut before_index = {position};
+    while before_index > 0 {{
+        before_index -= 1;
+        if {INPUT_PARAM_NAME}.as_bytes()[before_index] & 0xC0 != 0x80 {{
+            break;
+        }}
+    }}
+    let before = {position} > 0 && match Self::{CHAR_GET_FUNC_NAME}({INPUT_PARAM_NAME}, &mut before_index) {{
+        Some(c) => {is_word},
+        None => false,
+    }};
+    before != after
+}}")
+}
 
-    let mut init = String::from(INNER_INDEX_INIT);
-    init.insert_str(0, "    ");
-    parts.insert(0, init);
+/// The ranges of the Unicode aware `\w`
+fn unicode_word_ranges() -> Vec<(char, char)> {
+    let hir = regex_syntax::Parser::new().parse(r"\w").unwrap();
 
-    let mut end = String::from(INNER_INDEX_END);
-    end.insert_str(0, "    ");
-    parts.push(end);
-    parts.push(String::from("    return true"));
+    match hir.kind() {
+        HirKind::Class(Class::Unicode(class)) => class
+            .ranges()
+            .iter()
+            .map(|range| (range.start(), range.end()))
+            .collect(),
+        kind => unreachable!("unicode_word_ranges: \\w parsed into {kind:?}"),
+    }
+}
 
-    let mut body = parts.join("\n");
+/// `let {name} = |index: &mut usize| -> bool {..};`, a closure matching
+/// a single repetition of `repeat` at `index` and moving it past the
+/// repetition when successful
+pub fn repeat_body_closure(
+    repeat: &InstRepeat,
+    name: &str,
+    tables: &mut ClassTables,
+) -> String {
+    let body = repeat
+        .body
+        .iter()
+        .map(|inst| instruction_code(inst, tables))
+        .collect::<Vec<_>>()
+        .join("\n");
 
-    body.insert_str(0, &format!("    // Index: {}\n", prog_num));
+    format!("
+let {name} = |{INDEX_PARAM_NAME}: {INDEX_PARAM_TYPE}| -> bool {{
+    {INNER_INDEX_INIT}
+    {0}
+    {INNER_INDEX_END}
+    return true
+}};", body.replace('\n', "\n    "))
+}
 
-    let mut hasher = Sha256::new();
+/// Statement moving the byte offset in `variable` back by one
+/// repetition of `repeat`, which for ASCII only bodies is a known
+/// number of bytes
+pub fn repeat_step_back(repeat: &InstRepeat, variable: &str) -> String {
+    let width = repeat.width();
 
-    hasher.update(&body);
-    hasher.update(prog_num.to_ne_bytes());
+    if repeat.is_ascii() {
+        return format!("{variable} -= {width};");
+    }
 
-    let name = hash_name!(hasher);
+    let step_back = format!("{variable} -= {INPUT_PARAM_NAME}[..{variable}].chars().next_back().map_or(0, char::len_utf8);");
 
-    Ok(ProgramImplementation {
-        name,
-        body,
-        children: vec![],
-    })
+    if width == 1 {
+        step_back
+    } else {
+        format!("for _ in 0..{width} {{\n    {step_back}\n}}")
+    }
 }
 
-// FIXME: Does not care for greedyness
-fn try_parse_loop(
-    prog_loop: &Program,
-) -> Result<ProgramImplementation> {
-    let prog_num = unsafe {
-        PROG_COUNTER += 1;
-        PROG_COUNTER
+/// Code looping over the repetitions of `repeat`, starting at and
+/// updating `INNER_INDEX_NAME`.
+///
+/// With a `continuation`, the function of that name is tried after
+/// every valid number of repetitions, in the order preferred by the
+/// greediness, and the code always returns. Without one, as many
+/// repetitions as possible are taken and never given back, like the
+/// loops of the sectioned programs.
+pub fn repeat_code(
+    repeat: &InstRepeat,
+    continuation: Option<&str>,
+    arguments: &str,
+    tables: &mut ClassTables,
+) -> String {
+    let mut code = repeat_body_closure(repeat, "repeat_body", tables);
+    code.push_str("\nlet mut count: usize = 0;\n");
+
+    let min = repeat.min;
+    let below_max = match repeat.max {
+        Some(max) => format!("count < {max} && "),
+        None => String::new(),
     };
 
-    let loop_body = ProgramImplementation::try_parse(prog_loop)?;
+    let Some(continuation) = continuation else {
+        code.push_str(&format!("
+while {below_max}repeat_body(&mut {INNER_INDEX_NAME}) {{
+    count += 1;
+}}
+if count < {min} {{
+    return false
+}}"));
+        return code;
+    };
 
-    // FIXME: not fully thought out, what to return?
-    let mut body = format!("
-    {INNER_INDEX_INIT}
-    loop {{
-        if !Self::{0}({INPUT_PARAM_NAME}, &mut {INNER_INDEX_NAME}) {{
-            break;
-        }}
-        {INNER_INDEX_NAME} += 1;
+    let attempt = format!("
+let mut next_index = {INNER_INDEX_NAME};
+if Self::{continuation}({INPUT_PARAM_NAME}, &mut next_index{arguments}) {{
+    *{INDEX_PARAM_NAME} = next_index;
+    return true
+}}");
+
+    if repeat.greedy {
+        // Repetitions are given back one at a time
+        let step_back = repeat_step_back(repeat, INNER_INDEX_NAME);
+
+        code.push_str(&format!("
+while {below_max}repeat_body(&mut {INNER_INDEX_NAME}) {{
+    count += 1;
+}}"));
+
+        if min > 0 {
+            code.push_str(&format!("
+if count < {min} {{
+    return false
+}}"));
+        }
+
+        code.push_str(&format!("
+loop {{
+    {0}
+    if count == {min} {{
+        return false
     }}
-    {INNER_INDEX_END}
-    return true",
-        loop_body.name
-    );
+    {1}
+    count -= 1;
+}}", attempt.replace('\n', "\n    "), step_back.replace('\n', "\n    ")));
+    } else {
+        if min > 0 {
+            code.push_str(&format!("
+while count < {min} {{
+    if !repeat_body(&mut {INNER_INDEX_NAME}) {{
+        return false
+    }}
+    count += 1;
+}}"));
+        }
+
+        let at_max = match repeat.max {
+            Some(max) => format!("count == {max} || "),
+            None => String::new(),
+        };
+
+        code.push_str(&format!("
+loop {{
+    {0}
+    if {at_max}!repeat_body(&mut {INNER_INDEX_NAME}) {{
+        return false
+    }}
+    count += 1;
+}}", attempt.replace('\n', "\n    ")));
+    }
+
+    code
+}
 
-    body.insert_str(0, &format!("    // Index: {}\n", prog_num));
+/// Code walking a trie of `literals` from `INNER_INDEX_NAME` and trying
+/// the function `continuation` after every literal found, in order of
+/// priority. The code always returns.
+pub fn literals_code(literals: &[String], continuation: &str, arguments: &str) -> String {
+    let mut trie = Trie::new();
 
-    let mut hasher = Sha256::new();
+    for (priority, literal) in literals.iter().enumerate() {
+        trie.insert(literal.as_bytes(), priority);
+    }
 
-    hasher.update(&body);
-    hasher.update(prog_num.to_ne_bytes());
+    let walk = trie.code("bytes", "at", &mut |found| {
+        let attempts = found
+            .iter()
+            .map(|(_, len)| {
+                format!(
+                    "let mut end = at + {len};
+if Self::{continuation}({INPUT_PARAM_NAME}, &mut end{arguments}) {{
+    *{INDEX_PARAM_NAME} = end;
+    return true
+}}
+"
+                )
+            })
+            .collect::<String>();
 
-    let name = hash_name!(hasher);
+        format!("{{\n    {}false\n}}", attempts.replace('\n', "\n    "))
+    });
 
-    Ok(ProgramImplementation {
-        name,
-        body,
-        children: vec![loop_body],
-    })
+    format!("let bytes = {INPUT_PARAM_NAME}.as_bytes();\nlet at = {INNER_INDEX_NAME};\n{walk}")
 }
 
-fn try_parse_choice(
-    prog_a: &Program,
-    prog_b: &Program,
-) -> Result<ProgramImplementation> {
-    let prog_num = unsafe {
-        PROG_COUNTER += 1;
-        PROG_COUNTER
-    };
+/// Functions of a sectioned program, generated in continuation passing
+/// style.
+///
+/// The code of every section is followed by a call to the function
+/// matching the rest of the program, its continuation, so a section
+/// that can match in more than one way tries the rest after each of
+/// them. Sections following each other are still emitted as straight
+/// line code within one function, and loops whose body is a sequence of
+/// characters as a native `loop` that gives back one repetition at a
+/// time, so only choices and other loops need functions of their own.
+///
+/// Other loops run their body with a continuation collecting where it
+/// ends into a `Vec` of ends, which every function within the body takes
+/// as a parameter, and try those ends in a native `loop` with a stack of
+/// its own.
+struct Generator<'t> {
+    tables: &'t mut ClassTables,
+    children: Vec<ProgramImplementation>,
+    /// Name of the function accepting at its index, once needed
+    accept: Option<String>,
+    /// Functions named so far
+    count: usize,
+    /// Loop bodies the functions being generated are within
+    depth: usize,
+}
+
+impl Generator<'_> {
+    fn name(&mut self) -> String {
+        self.count += 1;
+        format!("S{}", self.count)
+    }
 
+    fn push(&mut self, name: String, body: String) -> String {
+        self.children.push(ProgramImplementation {
+            name: name.clone(),
+            body,
+            children: vec![],
+            parameters: self.parameters(),
+            constants: vec![],
+        });
 
-    let prog_a = ProgramImplementation::try_parse(prog_a)?;
-    let prog_b = ProgramImplementation::try_parse(prog_b)?;
+        name
+    }
 
-    let mut body = format!("
-    let mut prog_a_index = *{INDEX_PARAM_NAME};
-    let mut prog_b_index = *{INDEX_PARAM_NAME};
+    /// Parameters of the functions being generated after the index
+    fn parameters(&self) -> &'static str {
+        match self.depth {
+            0 => "",
+            _ => ", ends: &mut ::compiled_regex::__alloc::Vec<usize>",
+        }
+    }
 
-    if Self::{0}({INPUT_PARAM_NAME}, &mut prog_a_index) {{
-        *{INDEX_PARAM_NAME} = prog_a_index;
-        return true
-    }}
-    else if Self::{1}({INPUT_PARAM_NAME}, &mut prog_b_index) {{
-        *{INDEX_PARAM_NAME} = prog_b_index;
-        return true
-    }}
-    else {{
-        return false
-    }}", prog_a.name, prog_b.name);
+    /// Arguments of calls to the functions being generated after the
+    /// index
+    fn arguments(&self) -> &'static str {
+        match self.depth {
+            0 => "",
+            _ => ", ends",
+        }
+    }
 
-    body.insert_str(0, &format!("    // Index: {}\n", prog_num));
+    /// Name of the function matching `program` followed by `then`
+    fn function(&mut self, program: &Program, then: Option<&str>) -> String {
+        if let Program::Linear(programs) = program {
+            if programs.is_empty() {
+                return self.continuation(then);
+            }
+        }
 
-    let mut hasher = Sha256::new();
+        let body = self.code(program, then);
+        let name = self.name();
 
-    hasher.update(body.as_bytes());
-    hasher.update(prog_num.to_ne_bytes());
+        self.push(name, body)
+    }
 
-    let name = hash_name!(hasher);
-    
-    Ok(ProgramImplementation {
-        name,
-        body,
-        children: vec![prog_a, prog_b],
-    })
-}
+    /// Name of the function `then`, which when `None` accepts
+    fn continuation(&mut self, then: Option<&str>) -> String {
+        if let Some(then) = then {
+            return then.to_string();
+        }
 
-fn try_parse_linear(programs: &[Program]) -> Result<ProgramImplementation> { 
-    let prog_num = unsafe {
-        PROG_COUNTER += 1;
-        PROG_COUNTER
-    };
+        if let Some(accept) = &self.accept {
+            return accept.clone();
+        }
 
-    let implementations = programs.iter().map(ProgramImplementation::try_parse).collect::<Result<Vec<_>>>()?;
+        let name = self.name();
+        self.accept = Some(name.clone());
+        self.push(name, String::from("return true"))
+    }
 
-    let mut body = format!("
-    {INNER_INDEX_INIT}
-    if {} {{
-        {INNER_INDEX_END}
-        return true
-    }} else {{
-        return false
-    }}",
-        implementations
-            .iter()
-            .map(|imple| format!("Self::{}({INPUT_PARAM_NAME}, &mut {INNER_INDEX_NAME})", imple.name))
-            .collect::<Vec<_>>()
-            .join(" && "));
+    /// Statements ending the code of a section at `INNER_INDEX_NAME`
+    fn finish(&mut self, then: Option<&str>) -> String {
+        match then {
+            Some(then) => format!("{INNER_INDEX_END}\nreturn Self::{then}({INPUT_PARAM_NAME}, {INDEX_PARAM_NAME}{})", self.arguments()),
+            None => format!("{INNER_INDEX_END}\nreturn true"),
+        }
+    }
 
-    body.insert_str(0, &format!("    // Index: {}\n", prog_num));
+    /// Body of a function matching `program` from `*INDEX_PARAM_NAME`
+    /// followed by `then`, which always returns
+    fn code(&mut self, program: &Program, then: Option<&str>) -> String {
+        match program {
+            Program::Normal(instructions) => self.normal(instructions, then),
+            Program::Linear(programs) => match programs.split_first() {
+                Some((first, rest)) => {
+                    let then = rest.iter().rev().fold(
+                        then.map(String::from),
+                        |then, program| Some(self.function(program, then.as_deref())),
+                    );
+
+                    self.code(first, then.as_deref())
+                }
+                None => format!("{INNER_INDEX_INIT}\n{}", self.finish(then)),
+            },
+            // The second alternative is only tried once the first and
+            // everything following it failed
+            Program::Choice(a, b) => {
+                let a = self.function(a, then);
+
+                format!(
+                    "let mut index1 = *{INDEX_PARAM_NAME};
+if Self::{a}({INPUT_PARAM_NAME}, &mut index1{}) {{
+    *{INDEX_PARAM_NAME} = index1;
+    return true
+}}
+{}",
+                    self.arguments(),
+                    self.code(b, then)
+                )
+            }
+            Program::Loop(body, greedy) => self.repetition(body, *greedy, then),
+        }
+    }
 
-    let mut hasher = Sha256::new();
+    fn normal(&mut self, instructions: &[Inst], then: Option<&str>) -> String {
+        let mut code = vec![INNER_INDEX_INIT.to_string()];
+
+        for (i, inst) in instructions.iter().enumerate() {
+            // Instructions that can match in more than one way try the
+            // rest of the section after each
+            let rest = match &instructions[i + 1..] {
+                [] => None,
+                rest => Some(Program::Normal(rest)),
+            };
+
+            match inst {
+                Inst::Repeat(x) => {
+                    let then = match rest {
+                        Some(rest) => self.function(&rest, then),
+                        None => self.continuation(then),
+                    };
+
+                    code.push(repeat_code(x, Some(&then), self.arguments(), self.tables));
+                    return code.join("\n");
+                }
+                Inst::Literals(x) => {
+                    let then = match rest {
+                        Some(rest) => self.function(&rest, then),
+                        None => self.continuation(then),
+                    };
+
+                    code.push(literals_code(&x.literals, &then, self.arguments()));
+                    return code.join("\n");
+                }
+                inst => code.push(instruction_code(inst, self.tables)),
+            }
+        }
 
-    hasher.update(&body);
-    hasher.update(prog_num.to_ne_bytes());
+        code.push(self.finish(then));
+        code.join("\n")
+    }
 
-    let name = hash_name!(hasher);
+    /// Loops over a sequence of characters become a counted repetition
+    /// without bounds, every other loop a function trying the ends of
+    /// its body one at a time, and its continuation
+    fn repetition(
+        &mut self,
+        body: &Program,
+        greedy: bool,
+        then: Option<&str>,
+    ) -> String {
+        if let Program::Normal(instructions) = body {
+            let body = instructions
+                .iter()
+                .filter(|inst| !matches!(inst, Inst::Save(_)))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            let is_characters = !body.is_empty()
+                && body
+                    .iter()
+                    .all(|inst| matches!(inst, Inst::Char(_) | Inst::Ranges(_)));
+
+            if is_characters {
+                let repeat = InstRepeat {
+                    goto: 0,
+                    min: 0,
+                    max: None,
+                    greedy,
+                    body,
+                };
+                let then = self.continuation(then);
+
+                return format!(
+                    "{INNER_INDEX_INIT}{}",
+                    repeat_code(&repeat, Some(&then), self.arguments(), self.tables)
+                );
+            }
+        }
 
-    Ok(ProgramImplementation {
-        name,
-        body,
-        children: implementations
-    })
+        let name = self.name();
+        let then = self.continuation(then);
+        let arguments = self.arguments();
+        self.children.push(ProgramImplementation::empty());
+        let slot = self.children.len() - 1;
+
+        // The body continues with the function collecting its ends
+        self.depth += 1;
+        let collect = self.name();
+        let collect = self.push(collect, String::from("ends.push(*index);\nreturn false"));
+        let body = self.function(body, Some(&collect));
+        self.depth -= 1;
+
+        // Frames are either a position to run the body from, or one to
+        // try the continuation at. Every end of the body is a position
+        // to run the body from again, unless the body matched the empty
+        // string, which would repeat forever, or it was found before
+        // with a higher priority, which would fail the same way.
+        let exit = "stack.push((false, at));";
+        let (before, after) = if greedy { (exit, "") } else { ("", exit) };
+
+        self.children[slot] = ProgramImplementation {
+            name: name.clone(),
+            body: format!(
+                "::compiled_regex::__requires_alloc!(\"a loop of `engine = structured` over more than characters\");
+let mut found = ::compiled_regex::__alloc::Vec::new();
+let mut stack = ::compiled_regex::__alloc::Vec::new();
+stack.push((true, *{INDEX_PARAM_NAME}));
+while let Some((is_body, at)) = stack.pop() {{
+    if !is_body {{
+        let mut next_index = at;
+        if Self::{then}({INPUT_PARAM_NAME}, &mut next_index{arguments}) {{
+            *{INDEX_PARAM_NAME} = next_index;
+            return true
+        }}
+        continue;
+    }}
+    found.clear();
+    Self::{body}({INPUT_PARAM_NAME}, &mut at.clone(), &mut found);
+    {before}
+    for (i, &end) in found.iter().enumerate().rev() {{
+        if end != at && !found[..i].contains(&end) {{
+            stack.push((true, end));
+        }}
+    }}
+    {after}
+}}
+return false"
+            )
+            .replace("\n    \n", "\n"),
+            children: vec![],
+            parameters: self.parameters(),
+            constants: vec![],
+        };
+
+        format!("return Self::{name}({INPUT_PARAM_NAME}, {INDEX_PARAM_NAME}{arguments})")
+    }
 }
 
 #[cfg(test)]
@@ -349,7 +797,7 @@ mod tests {
     fn character_range_literal_empty() {
         assert_eq!(
             &character_range_to_literal(&[], "LITERAL"),
-            "const LITERAL: [char; 0] = [];"
+            "const LITERAL: [(char, char); 0] = [];\n"
         )
     }
 
@@ -357,7 +805,7 @@ mod tests {
     fn character_range_literal_two() {
         assert_eq!(
             &character_range_to_literal(&[('a', 'b'), ('e', 'ä')], "LITERAL"),
-            "const LITERAL: [char; 2] = [('\\u{61}', '\\u{62}'), ('\\u{65}', '\\u{e4}')];"
+            "const LITERAL: [(char, char); 2] = [('\\u{61}', '\\u{62}'), ('\\u{65}', '\\u{e4}')];\n"
         )
     }
 }
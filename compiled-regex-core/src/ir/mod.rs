@@ -1,2 +1,5 @@
+pub(super) mod classes;
 pub(super) mod sections;
 pub(super) mod functions;
+pub(super) mod nodes;
+pub(super) mod trie;
@@ -0,0 +1,437 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use regex_syntax::hir::Look;
+
+use crate::program::{Inst, InstPtr, InstRepeat};
+
+use super::classes::ClassTables;
+use super::functions::{
+    instruction_code, literals_code, look_condition, repeat_code, time_name,
+    ProgramImplementation, INDEX_PARAM_NAME, INNER_INDEX_END,
+    INNER_INDEX_INIT, INNER_INDEX_NAME, INPUT_PARAM_NAME,
+};
+
+/// Block of the instruction it was created from, which names the
+/// function it is generated as
+pub type BlockId = InstPtr;
+
+/// Step of a block, every one of which has to succeed for the block to
+/// reach its exit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Node {
+    /// Consume a character with a `Char`, `Ranges` or `Bytes`
+    /// instruction, whose `goto` is not used
+    Consume(Inst),
+    /// Zero-width assertion
+    Assert(Look),
+    /// Position of a capture slot, which no matcher reports
+    Save(usize),
+}
+
+// `Look` is not `Hash`, its representation is
+impl Hash for Node {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Node::Consume(inst) => (0, inst).hash(state),
+            Node::Assert(look) => (1, look.as_repr()).hash(state),
+            Node::Save(slot) => (2, slot).hash(state),
+        }
+    }
+}
+
+/// Where a block continues once all of its nodes succeeded
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Exit {
+    Jump(BlockId),
+    /// Try the first block, then the second from the same position
+    Branch(BlockId, BlockId),
+    /// Counted repetition, trying the block after every valid number of
+    /// repetitions. The `goto` of the repetition is not used.
+    Repeat(InstRepeat, BlockId),
+    /// Alternation of literals, trying the block after every literal
+    /// found in order of priority
+    Literals(Vec<String>, BlockId),
+    Accept,
+}
+
+impl Exit {
+    fn targets(&self) -> Vec<BlockId> {
+        match self {
+            Exit::Jump(x) | Exit::Repeat(_, x) | Exit::Literals(_, x) => {
+                vec![*x]
+            }
+            Exit::Branch(a, b) => vec![*a, *b],
+            Exit::Accept => vec![],
+        }
+    }
+
+    fn targets_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            Exit::Jump(x) | Exit::Repeat(_, x) | Exit::Literals(_, x) => {
+                vec![x]
+            }
+            Exit::Branch(a, b) => vec![a, b],
+            Exit::Accept => vec![],
+        }
+    }
+}
+
+/// Straight-line nodes followed by an exit, generated as a function
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Block {
+    pub nodes: Vec<Node>,
+    pub exit: Exit,
+}
+
+/// Typed form of the functions the recursive engine generates, one
+/// block per instruction to begin with, which is optimized before any
+/// Rust is emitted
+#[derive(Debug)]
+pub struct Blocks {
+    pub blocks: BTreeMap<BlockId, Block>,
+    pub entry: BlockId,
+}
+
+impl Blocks {
+    /// A block for every instruction, with the one at `start` as entry
+    pub fn new(instructions: &[Inst], start: InstPtr) -> Self {
+        let blocks = instructions
+            .iter()
+            .enumerate()
+            .map(|(i, inst)| {
+                let (nodes, exit) = match inst {
+                    Inst::Char(_) | Inst::Ranges(_) | Inst::Bytes(_) => {
+                        let (consume, goto) = without_goto(inst);
+                        (vec![Node::Consume(consume)], Exit::Jump(goto))
+                    }
+                    Inst::EmptyLook(x) => {
+                        (vec![Node::Assert(x.look)], Exit::Jump(x.goto))
+                    }
+                    Inst::Save(x) => (vec![Node::Save(x.slot)], Exit::Jump(x.goto)),
+                    Inst::Split(x) => (vec![], Exit::Branch(x.goto1, x.goto2)),
+                    Inst::Repeat(x) => {
+                        let mut repeat = x.clone();
+                        repeat.goto = 0;
+                        (vec![], Exit::Repeat(repeat, x.goto))
+                    }
+                    Inst::Literals(x) => {
+                        (vec![], Exit::Literals(x.literals.clone(), x.goto))
+                    }
+                    Inst::Match(_) => (vec![], Exit::Accept),
+                };
+
+                (i, Block { nodes, exit })
+            })
+            .collect();
+
+        Blocks { blocks, entry: start }
+    }
+
+    /// Run every pass until none of them changes anything
+    pub fn optimize(&mut self) {
+        self.remove_saves();
+
+        loop {
+            let changed = self.thread_jumps()
+                | self.merge_identical()
+                | self.inline_single_use();
+
+            self.remove_unreachable();
+
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// Drop the `Save` nodes, which leaves the blocks of `Save`
+    /// instructions as pure forwarding
+    fn remove_saves(&mut self) {
+        for block in self.blocks.values_mut() {
+            block.nodes.retain(|node| !matches!(node, Node::Save(_)));
+        }
+    }
+
+    /// Target the block a chain of forwarding blocks ends at directly,
+    /// and turn branches to the same block into jumps
+    fn thread_jumps(&mut self) -> bool {
+        let forward = |blocks: &BTreeMap<BlockId, Block>, mut id: BlockId| {
+            // Bounded, an empty loop can not be threaded
+            for _ in 0..blocks.len() {
+                match &blocks[&id] {
+                    Block { nodes, exit: Exit::Jump(next) } if nodes.is_empty() => id = *next,
+                    _ => break,
+                }
+            }
+
+            id
+        };
+
+        let mut changed = false;
+        let ids = self.blocks.keys().copied().collect::<Vec<_>>();
+
+        for id in ids {
+            let mut exit = self.blocks[&id].exit.clone();
+
+            for target in exit.targets_mut() {
+                *target = forward(&self.blocks, *target);
+            }
+
+            if let Exit::Branch(a, b) = exit {
+                if a == b {
+                    exit = Exit::Jump(a);
+                }
+            }
+
+            if exit != self.blocks[&id].exit {
+                self.blocks.get_mut(&id).unwrap().exit = exit;
+                changed = true;
+            }
+        }
+
+        let entry = forward(&self.blocks, self.entry);
+        changed |= entry != self.entry;
+        self.entry = entry;
+
+        changed
+    }
+
+    /// Keep a single block of those with the same nodes and exit
+    fn merge_identical(&mut self) -> bool {
+        let mut first = HashMap::<&Block, BlockId>::new();
+        let mut replaced = HashMap::new();
+
+        for (&id, block) in &self.blocks {
+            let kept = *first.entry(block).or_insert(id);
+
+            if kept != id {
+                replaced.insert(id, kept);
+            }
+        }
+
+        if replaced.is_empty() {
+            return false;
+        }
+
+        for block in self.blocks.values_mut() {
+            for target in block.exit.targets_mut() {
+                if let Some(&kept) = replaced.get(target) {
+                    *target = kept;
+                }
+            }
+        }
+
+        if let Some(&kept) = replaced.get(&self.entry) {
+            self.entry = kept;
+        }
+
+        true
+    }
+
+    /// Append the blocks that are only jumped to from a single block to
+    /// that block, which continues at the same position anyway
+    fn inline_single_use(&mut self) -> bool {
+        let mut uses = HashMap::<BlockId, usize>::new();
+
+        *uses.entry(self.entry).or_default() += 1;
+        for block in self.blocks.values() {
+            for target in block.exit.targets() {
+                *uses.entry(target).or_default() += 1;
+            }
+        }
+
+        let mut changed = false;
+        let ids = self.blocks.keys().copied().collect::<Vec<_>>();
+
+        for id in ids {
+            let Some(Block { exit: Exit::Jump(target), .. }) = self.blocks.get(&id) else {
+                continue;
+            };
+            let target = *target;
+
+            if target == id || uses.get(&target) != Some(&1) {
+                continue;
+            }
+
+            let inlined = self.blocks.remove(&target).unwrap();
+            let block = self.blocks.get_mut(&id).unwrap();
+
+            block.nodes.extend(inlined.nodes);
+            block.exit = inlined.exit;
+            changed = true;
+        }
+
+        changed
+    }
+
+    /// Drop the blocks that can not be reached from the entry
+    fn remove_unreachable(&mut self) {
+        let mut reachable = HashSet::from([self.entry]);
+        let mut pending = vec![self.entry];
+
+        while let Some(id) = pending.pop() {
+            for target in self.blocks[&id].exit.targets() {
+                if reachable.insert(target) {
+                    pending.push(target);
+                }
+            }
+        }
+
+        self.blocks.retain(|id, _| reachable.contains(id));
+    }
+
+    /// Emit a function per block, the entry being generated as the root
+    /// function unless another block continues at it
+    pub fn implementation(&self, mut tables: ClassTables) -> ProgramImplementation {
+        let entry_is_target = self
+            .blocks
+            .values()
+            .any(|block| block.exit.targets().contains(&self.entry));
+
+        let children = self
+            .blocks
+            .iter()
+            .filter(|&(&id, _)| entry_is_target || id != self.entry)
+            .map(|(id, block)| ProgramImplementation {
+                name: format!("F{id}"),
+                body: block_code(block, &mut tables),
+                children: vec![],
+                parameters: "",
+                constants: vec![],
+            })
+            .collect();
+
+        let body = if entry_is_target {
+            format!(
+                "return Self::F{}({INPUT_PARAM_NAME}, {INDEX_PARAM_NAME})",
+                self.entry
+            )
+        } else {
+            block_code(&self.blocks[&self.entry], &mut tables)
+        };
+
+        ProgramImplementation {
+            name: time_name!('F'),
+            body,
+            children,
+            parameters: "",
+            constants: tables.into_items(),
+        }
+    }
+}
+
+/// A consuming instruction with its `goto` cleared, so the same
+/// instructions compare equal wherever they continue, and the `goto`
+fn without_goto(inst: &Inst) -> (Inst, InstPtr) {
+    let mut inst = inst.clone();
+    let goto = match &mut inst {
+        Inst::Char(x) => std::mem::take(&mut x.goto),
+        Inst::Ranges(x) => std::mem::take(&mut x.goto),
+        Inst::Bytes(x) => std::mem::take(&mut x.goto),
+        inst => unreachable!("without_goto: {inst:?} does not consume"),
+    };
+
+    (inst, goto)
+}
+
+/// Body of the function of `block`, matching from `*index` and moving it
+/// to the end of the match when successful
+fn block_code(block: &Block, tables: &mut ClassTables) -> String {
+    let mut code = vec![INNER_INDEX_INIT.to_string()];
+
+    for node in &block.nodes {
+        code.push(match node {
+            Node::Consume(inst) => instruction_code(inst, tables)
+                .trim_start_matches('\n')
+                .to_string(),
+            Node::Assert(look) => format!(
+                "let holds = {};\nif !holds {{\n    return false\n}}",
+                look_condition(*look, INNER_INDEX_NAME, tables)
+            ),
+            Node::Save(_) => continue,
+        });
+    }
+
+    let call = |target: BlockId, index: &str| {
+        format!("Self::F{target}({INPUT_PARAM_NAME}, &mut {index})")
+    };
+
+    code.push(match &block.exit {
+        Exit::Jump(target) => format!(
+            "{INNER_INDEX_END}\nreturn Self::F{target}({INPUT_PARAM_NAME}, {INDEX_PARAM_NAME})"
+        ),
+        Exit::Branch(a, b) => format!(
+            "let mut index1 = {INNER_INDEX_NAME};
+if {} {{
+    *{INDEX_PARAM_NAME} = index1;
+    return true
+}}
+let mut index2 = {INNER_INDEX_NAME};
+if {} {{
+    *{INDEX_PARAM_NAME} = index2;
+    return true
+}}
+return false",
+            call(*a, "index1"),
+            call(*b, "index2")
+        ),
+        Exit::Repeat(repeat, target) => {
+            repeat_code(repeat, Some(&format!("F{target}")), "", tables)
+                .trim_start_matches('\n')
+                .to_string()
+        }
+        Exit::Literals(literals, target) => {
+            literals_code(literals, &format!("F{target}"), "")
+        }
+        Exit::Accept => format!("{INNER_INDEX_END}\nreturn true"),
+    });
+
+    code.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use regex_syntax::Parser;
+
+    use super::{Blocks, ClassTables, Exit};
+    use crate::compiler::Compiler;
+
+    fn optimized(pattern: &str) -> (usize, Blocks) {
+        let hir = Parser::new().parse(pattern).unwrap();
+        let program = Compiler::new().compile(&hir).unwrap();
+        let mut blocks = Blocks::new(&program.insts, program.start);
+
+        blocks.optimize();
+        (program.insts.len(), blocks)
+    }
+
+    #[test]
+    fn no_forwarding_blocks() {
+        let (insts, blocks) = optimized(r"(a)\b(?:bc|d)");
+
+        assert!(blocks.blocks.len() < insts);
+        assert!(!blocks.blocks.values().any(|block| {
+            block.nodes.is_empty() && matches!(block.exit, Exit::Jump(_))
+        }));
+    }
+
+    #[test]
+    fn single_use_inlined() {
+        // Everything but the branches of the alternation is straight-line
+        let (_, blocks) = optimized(r"ab(?:cc|dd)e");
+
+        assert_eq!(blocks.blocks.len(), 4);
+        assert_eq!(blocks.blocks[&blocks.entry].nodes.len(), 2);
+        assert!(matches!(blocks.blocks[&blocks.entry].exit, Exit::Branch(..)));
+    }
+
+    #[test]
+    fn identical_merged() {
+        // Both alternatives continue at their own copy of `x`
+        let (_, blocks) = optimized(r"(?:a+x|b+x)");
+        let code = blocks.implementation(ClassTables::new()).to_string();
+
+        assert_eq!(code.matches("Some('\\u{78}')").count(), 1);
+    }
+}
@@ -1,18 +1,26 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
+    ops::Range,
     rc::Rc,
 };
 
-use regex::internal::Inst;
+use crate::program::{Inst, InstPtr, InstSplit};
 
-use crate::types::Result;
+use crate::types::{CompileError, Result};
 
 type Ptr<T> = Rc<T>;
 
+/// Structure of a program, recovered from the jumps between its
+/// instructions
 #[derive(Debug)]
 pub(crate) enum Program<'a> {
+    /// Instructions following each other, none of which is a split
     Normal(&'a [Inst]),
-    Loop(Ptr<Program<'a>>),
+    /// Loop body and whether the loop is greedy, that is if it prefers
+    /// to run the body again over exiting
+    Loop(Ptr<Program<'a>>, bool),
+    /// Alternatives in order of priority, which continue at the same
+    /// instruction
     Choice(Ptr<Program<'a>>, Ptr<Program<'a>>),
     Linear(Vec<Program<'a>>),
 }
@@ -20,308 +28,330 @@ pub(crate) enum Program<'a> {
 impl<'a> Program<'a> {
     pub(crate) fn try_parse(
         instructions: &'a [Inst],
+        start: InstPtr,
     ) -> Result<Program<'a>> {
-        #[cfg(debug_assertions)]
-        println!("Section Program Parsing:");
-
-        let mut programs = HashMap::new();
-        let program = try_parse_program(
-            &mut programs,
+        let end = instructions
+            .iter()
+            .position(|inst| matches!(inst, Inst::Match(_)))
+            .ok_or_else(|| {
+                CompileError::Unsupported(String::from(
+                    "program without a match instruction",
+                ))
+            })?;
+
+        let mut sections = Sections {
             instructions,
-            0,
-            instructions.len(),
-        );
+            programs: HashMap::new(),
+        };
+        let program = sections.parse(start, end);
+
         #[cfg(debug_assertions)]
         println!("\nSectioned Program:\n{:#?}", program);
+
         program
     }
 }
 
-fn get_program<'a>(
-    programs: &mut HashMap<usize, Rc<Program<'a>>>,
+// Program "[abc]b|(t*)*":
+// 0000 Save(0) (start)
+// 0001 Split(2, 4)
+// 0002 'a'-'c'
+// 0003 'b' (goto: 9)     <- end of Alt LHS
+// 0004 Split(5, 9)       <- Start of (_)*
+// 0005 Save(2)
+// 0006 Split(7, 8)       <- Start of t*
+// 0007 't' (goto: 6)     <- End of t*
+// 0008 Save(3) (goto: 4) <- End of (_)*
+// 0009 Save(1)
+// 0010 Match(0)
+
+// Program "(?:a|b)+":
+// 0000 Save(0) (start)
+// 0001 Split(2, 3)       <- Start of a|b and of the loop body
+// 0002 'a' (goto: 4)
+// 0003 'b' (goto: 4)
+// 0004 Split(1, 5)       <- End of (_)+, jumping back to its body
+// 0005 Save(1)
+// 0006 Match(0)
+struct Sections<'a> {
     instructions: &'a [Inst],
-    start: usize,
-    end: usize,
-) -> Result<Rc<Program<'a>>> {
-    // Check if program already exists
-    if let Some(program) = programs.get(&start) {
-        Ok(program.clone())
-    }
-    // If not, parse the section, register it, and return it
-    else {
-        // Parse section specified by start and end
-        let program =
-            try_parse_program(programs, instructions, start, end)?;
-        // Surround with pointer
-        let program = Ptr::new(program);
-        // Register to location
-        _ = programs.insert(start, program.clone());
-        // Return pointer
-        Ok(program)
-    }
+    /// Programs already parsed, by the instructions they start and end
+    /// at
+    programs: HashMap<(InstPtr, InstPtr), Ptr<Program<'a>>>,
 }
 
-fn try_parse_program<'a>(
-    programs: &mut HashMap<usize, Rc<Program<'a>>>,
-    instructions: &'a [Inst],
-    start: usize,
-    end: usize,
-) -> Result<Program<'a>> {
-    let mut sections = Vec::new();
-    let mut i = start;
-    let mut unused_insts_since = i;
-
-    fn update_unused_since(unused: &mut usize, new: usize) {
-        if unused == &0 {
-            *unused = new;
-        } else {
-            *unused = usize::min(*unused, new);
+impl<'a> Sections<'a> {
+    fn get(&mut self, from: InstPtr, exit: InstPtr) -> Result<Ptr<Program<'a>>> {
+        if let Some(program) = self.programs.get(&(from, exit)) {
+            return Ok(program.clone());
         }
-    }
 
-    // Program "[abc]b|(t*)*":
-    // 0000 Save(0) (start)
-    // 0001 Split(2, 4)
-    // 0002 'a'-'c'
-    // 0003 'b' (goto: 9)     <- end of Alt LHS
-    // 0004 Split(5, 9)       <- Start of (_)*
-    // 0005 Save(2)
-    // 0006 Split(7, 8)       <- Start of t*
-    // 0007 't' (goto: 6)     <- End of t*
-    // 0008 Save(3) (goto: 4) <- End of (_)*
-    // 0009 Save(1)
-    // 0010 Match(0)
-
-    // Program "a{1,3}b":
-    // 0000 Save(0) (start)
-    // 0001 'a'
-    // 0002 Split(3, 6)
-    // 0003 'a'
-    // 0004 Split(5, 6)
-    // 0005 'a'
-    // 0006 'b'
-    // 0007 Save(1)
-    // 0008 Match(0)
-    while i < end {
-        let inst = &instructions[i];
-        #[cfg(debug_assertions)]
-        println!("i={:04}: {:?}", i, inst);
-
-        match inst {
-            Inst::Split(split) => {
-                // Check if there are any unused instructions that
-                // need to be pushed in
-                if unused_insts_since != i {
-                    sections.push(Program::Normal(
-                        &instructions[unused_insts_since..i],
-                    ));
-                }
+        let program = Ptr::new(self.parse(from, exit)?);
+        self.programs.insert((from, exit), program.clone());
 
-                // Find out if there is a loop end to this split
-                let loop_end = find_loop_body_end(instructions, i, i);
-
-                // A loop exists; zero or more (*), one or more (+),
-                // or custom quanity with no end ({1,})
-                if let Some(end) = loop_end {
-                    // Parse the section within
-                    let program = try_parse_program(
-                        programs,
-                        instructions,
-                        i + 1,
-                        end,
-                    )?;
-                    // Surround it with a pointer
-                    let program = Ptr::new(program);
-
-                    // Set section to loop
-                    let section = Program::Loop(program);
-                    // Add section to list
-                    sections.push(section);
+        Ok(program)
+    }
 
-                    // Update index
-                    i = end;
-                    unused_insts_since = i;
-                }
-                // No loop; alternation (|), zero or one (?), or
-                // custom quantity ({1,3})
-                else {
-                    // Parse the first section
-                    let program_a = get_program(
-                        programs,
-                        instructions,
-                        split.goto1,
-                        split.goto2,
-                    )?;
-
-                    #[cfg(debug_assertions)]
-                    {
-                        println!(
-                            "Program A ({}-{}):",
-                            split.goto1, split.goto2
-                        );
-                        println!(
-                            "{:?}",
-                            &instructions[split.goto1..split.goto2]
-                        );
-                        println!("{:?}", &program_a);
-                    }
+    /// Program of the instructions from `from` up to, but not including,
+    /// `exit`, which every path from `from` passes
+    fn parse(&mut self, from: InstPtr, exit: InstPtr) -> Result<Program<'a>> {
+        let mut sections = Vec::new();
+        // Instructions of this section, to find the ends of loops
+        let mut walked = HashSet::new();
+        let mut normal: Option<Range<InstPtr>> = None;
+        let mut at = from;
+
+        while at != exit {
+            if !walked.insert(at) {
+                return Err(unstructured(at));
+            }
 
-                    // Find the joining fork for both programs
-                    let program_b_end =
-                        get_goto1(&instructions[split.goto2 - 1]) + 1;
-
-                    // Parse the second section
-                    let program_b = get_program(
-                        programs,
-                        instructions,
-                        split.goto2,
-                        program_b_end,
-                    )?;
-
-                    #[cfg(debug_assertions)]
-                    {
-                        println!(
-                            "Program B ({}-{}):",
-                            split.goto2, program_b_end
-                        );
-                        println!(
-                            "{:?}",
-                            &instructions[split.goto2..program_b_end]
-                        );
-                        println!("{:?}", &program_a);
+            at = match &self.instructions[at] {
+                Inst::Split(split) => {
+                    if let Some(run) = normal.take() {
+                        sections.push(Program::Normal(&self.instructions[run]));
                     }
 
-                    // Set section to choice
-                    let section =
-                        Program::Choice(program_a, program_b);
-
-                    // Add section to list
+                    let (section, next) = self.split(at, split, &walked, exit)?;
                     sections.push(section);
-
-                    // Update index
-                    i = program_b_end;
-                    unused_insts_since = i;
+                    next
                 }
+                // The match instruction can only be the exit of the
+                // whole program
+                Inst::Match(_) => return Err(unstructured(at)),
+                inst => {
+                    // Instructions only share a section while they are
+                    // consecutive
+                    normal = match normal.take() {
+                        Some(run) if run.end == at => Some(run.start..at + 1),
+                        Some(run) => {
+                            sections.push(Program::Normal(&self.instructions[run]));
+                            Some(at..at + 1)
+                        }
+                        None => Some(at..at + 1),
+                    };
+
+                    gotos(inst)[0]
+                }
+            };
+        }
+
+        if let Some(run) = normal {
+            sections.push(Program::Normal(&self.instructions[run]));
+        }
+
+        // If there is only one section parsed, then just return that section
+        if sections.len() == 1 {
+            Ok(sections.pop().unwrap())
+        }
+        // Otherwise, return the sections as linear progression
+        else {
+            Ok(Program::Linear(sections))
+        }
+    }
+
+    /// Section starting at the split `at` and the instruction following
+    /// it
+    fn split(
+        &mut self,
+        at: InstPtr,
+        split: &InstSplit,
+        walked: &HashSet<InstPtr>,
+        exit: InstPtr,
+    ) -> Result<(Program<'a>, InstPtr)> {
+        let InstSplit { goto1, goto2 } = *split;
+        // Greedy loops prefer the body, which is the first goto
+        let branches = [(goto1, goto2, true), (goto2, goto1, false)];
+
+        // One or more (+): the split follows the body, which this
+        // section already matched once, and jumps back to its start
+        for (back, out, greedy) in branches {
+            if back < at && walked.contains(&back) {
+                let body = self.get(back, at)?;
+
+                return Ok((Program::Loop(body, greedy), out));
             }
+        }
+
+        // Zero or more (*): the body directly follows the split and
+        // jumps back to it at its end
+        if let Some(last) = self.loop_ends(at).max() {
+            let body = at + 1..last + 1;
 
-            // Normal instructions, those that should just be put into
-            // Program::Normal
-            Inst::Char(_)
-            | Inst::Bytes(_)
-            | Inst::Ranges(_)
-            | Inst::EmptyLook(_)
-            | Inst::Save(_) => {
-                update_unused_since(&mut unused_insts_since, i)
+            for (enter, out, greedy) in branches {
+                if body.contains(&enter) && !body.contains(&out) {
+                    let body = self.get(enter, at)?;
+
+                    return Ok((Program::Loop(body, greedy), out));
+                }
             }
 
-            // The match instructions is only to tell the program
-            // that a specific regex has been matched. Only relevant
-            // for multiple regex programs
-            Inst::Match(_) => break,
+            return Err(unstructured(at));
         }
 
-        i += 1;
+        // Alternation (|) or zero or one (?): both branches continue at
+        // the first instruction every path from the split passes
+        let join = self.join(at, exit)?;
+        let a = self.get(goto1, join)?;
+        let b = self.get(goto2, join)?;
+
+        Ok((Program::Choice(a, b), join))
     }
 
-    // Handle any potential unused instructions
-    if unused_insts_since != i {
-        sections.push(Program::Normal(
-            &instructions[unused_insts_since..i],
-        ));
+    /// Instructions jumping back to the header `at` at the end of a
+    /// loop body directly following it
+    fn loop_ends(&self, at: InstPtr) -> impl Iterator<Item = InstPtr> + '_ {
+        (at + 1..self.instructions.len()).filter(move |&i| {
+            let inst = &self.instructions[i];
+
+            gotos(inst).contains(&at)
+                && !(matches!(inst, Inst::Split(_)) && self.is_repeat_end(i, at))
+        })
     }
 
-    // If there is only one section parsed, then just return that section
-    if sections.len() == 1 {
-        Ok(sections.pop().unwrap())
+    /// Whether the split `at` jumping back to `start` ends a one or
+    /// more loop, whose body is everything in between. Nothing in the
+    /// body can leave it, unlike when jumping back to a zero or more
+    /// loop, whose header leaves it.
+    fn is_repeat_end(&self, at: InstPtr, start: InstPtr) -> bool {
+        start < at
+            && self.instructions[start..at]
+                .iter()
+                .flat_map(gotos)
+                .all(|goto| (start..=at).contains(&goto))
     }
-    // Otherwise, return the sections as linear progression
-    else {
-        Ok(Program::Linear(sections))
+
+    /// Closest instruction after the split `at` that every path from it
+    /// to `exit` passes, its immediate post-dominator
+    fn join(&self, at: InstPtr, exit: InstPtr) -> Result<InstPtr> {
+        let mut reachable = vec![at];
+        let mut seen = BTreeSet::from([at]);
+        let mut i = 0;
+
+        while let Some(&inst) = reachable.get(i) {
+            i += 1;
+
+            if inst == exit {
+                continue;
+            }
+
+            for goto in gotos(&self.instructions[inst]) {
+                if seen.insert(goto) {
+                    reachable.push(goto);
+                }
+            }
+        }
+
+        // Instructions every path from an instruction passes, starting
+        // from all of them and shrinking until nothing changes
+        let mut passed: HashMap<InstPtr, BTreeSet<InstPtr>> = reachable
+            .iter()
+            .map(|&inst| match inst == exit {
+                true => (inst, BTreeSet::from([exit])),
+                false => (inst, seen.clone()),
+            })
+            .collect();
+
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for &inst in reachable.iter().rev().filter(|&&inst| inst != exit) {
+                let mut common: Option<BTreeSet<InstPtr>> = None;
+
+                for goto in gotos(&self.instructions[inst]) {
+                    common = Some(match common {
+                        Some(common) => {
+                            common.intersection(&passed[&goto]).copied().collect()
+                        }
+                        None => passed[&goto].clone(),
+                    });
+                }
+
+                let mut common = common.unwrap_or_else(|| seen.clone());
+                common.insert(inst);
+
+                if common != passed[&inst] {
+                    passed.insert(inst, common);
+                    changed = true;
+                }
+            }
+        }
+
+        // The closest is passed on the way to all others
+        passed[&at]
+            .iter()
+            .filter(|&&inst| inst != at)
+            .max_by_key(|inst| passed[inst].len())
+            .copied()
+            .ok_or_else(|| unstructured(at))
     }
 }
 
-fn get_goto1(inst: &Inst) -> usize {
+/// Instructions `inst` continues at, in order of priority
+fn gotos(inst: &Inst) -> Vec<InstPtr> {
     match inst {
-        Inst::Char(c) => c.goto,
-        Inst::Ranges(r) => r.goto,
-        Inst::Bytes(b) => b.goto,
-        Inst::EmptyLook(e) => e.goto,
-        // Split instructions contain branches which mean that
-        // there is a need to handle further loops but still find
-        // the backtrace to the header of the current loop.
-        Inst::Split(s) => s.goto1,
-        // All other instructions are utility instructions
-        Inst::Save(x) => x.goto,
-        // Instrucitons that do not lead anywhere
-        _ => panic!("get_goto1: Assumed INST would have goto. {:?} does not have a goto.", inst),
+        Inst::Char(c) => vec![c.goto],
+        Inst::Ranges(r) => vec![r.goto],
+        Inst::Bytes(b) => vec![b.goto],
+        Inst::EmptyLook(e) => vec![e.goto],
+        Inst::Repeat(r) => vec![r.goto],
+        Inst::Literals(l) => vec![l.goto],
+        Inst::Save(x) => vec![x.goto],
+        Inst::Split(s) => vec![s.goto1, s.goto2],
+        Inst::Match(_) => vec![],
     }
 }
 
-// FIXME: Does describe if loop is greedy or not
-// May have to return a vector of references with the lifespan of the
-// original vector.
-/// Find the end of the loop by finding an instruction that points back
-/// to the loop header
-fn find_loop_body_end<'lt>(
-    instructions: &'lt [Inst],
-    start: usize,
-    header: usize,
-) -> Option<usize> {
-    // first index is one of the indexes after the header, that is one
-    // of the goto values a split instruction
-    let mut inst_index = start;
-    // collect all passed instructions to make sure that we do not enter
-    // a loop
-    let mut passed_inst = HashSet::new();
-
-    // loop through all of the instructions and branches until it is
-    // found
-    loop {
-        // make sure that the instruction has not already been passed
-        if passed_inst.contains(&inst_index) {
-            return None;
+fn unstructured(at: InstPtr) -> CompileError {
+    CompileError::Unsupported(format!(
+        "instruction {at} can not be structured into sections"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use regex_syntax::Parser;
+
+    use super::Program;
+    use crate::compiler::Compiler;
+
+    fn shape(program: &Program) -> String {
+        match program {
+            Program::Normal(instructions) => format!("{}", instructions.len()),
+            Program::Loop(body, true) => format!("Loop({})", shape(body)),
+            Program::Loop(body, false) => format!("Loop?({})", shape(body)),
+            Program::Choice(a, b) => format!("Choice({}, {})", shape(a), shape(b)),
+            Program::Linear(programs) => format!(
+                "[{}]",
+                programs.iter().map(shape).collect::<Vec<_>>().join(", ")
+            ),
         }
+    }
 
-        let next_index = match &instructions[inst_index] {
-            // Normal match instructions, these will just be consecutive.
-            // No need for fancy logic to deal with the indexes.
-            Inst::Char(c) => c.goto,
-            Inst::Ranges(r) => r.goto,
-            Inst::Bytes(b) => b.goto,
-            Inst::EmptyLook(e) => e.goto,
-            // Split instructions contain branches which mean that
-            // there is a need to handle further loops but still find
-            // the backtrace to the header of the current loop.
-            Inst::Split(s) => {
-                find_loop_body_end(instructions, s.goto1, header)
-                    .or_else(|| {
-                        find_loop_body_end(
-                            instructions,
-                            s.goto2,
-                            header,
-                        )
-                    })?
-            }
-            // All other instructions are utility instructions
-            Inst::Save(x) => x.goto,
-            // Instrucitons that do not lead anywhere
-            _ => return None,
-        };
+    fn sections(pattern: &str) -> String {
+        let hir = Parser::new().parse(pattern).unwrap();
+        let program = Compiler::new().compile(&hir).unwrap();
 
-        // If the next prospective instruction index is the header,
-        // the end has been found and should be returned (by exiting
-        // this loop)
-        if next_index == header {
-            break;
-        }
-        // Otherwise the instruction index needs to be updated so the
-        // automata can be followed.
-        else {
-            passed_inst.insert(inst_index);
-            inst_index = next_index;
-        }
+        shape(&Program::try_parse(&program.insts, program.start).unwrap())
     }
 
-    // The end index of the loop has been found and will be returned
-    Some(inst_index)
+    #[test]
+    fn loops() {
+        // Saves and characters are counted, the body of `x+` is matched
+        // once before its loop
+        assert_eq!(sections("a*b"), "[1, Loop(1), 2]");
+        assert_eq!(sections("a*?b"), "[1, Loop?(1), 2]");
+        assert_eq!(sections("(?:ab|cd)+e"), "[1, Choice(2, 2), Loop(Choice(2, 2)), 2]");
+        assert_eq!(sections("(?:a+?b)*c"), "[1, Loop([1, Loop?(1), 1]), 2]");
+        assert_eq!(sections("(?:x?)*y"), "[1, Loop(Choice(1, [])), 2]");
+    }
+
+    #[test]
+    fn choices() {
+        assert_eq!(sections("ab|cd|ef"), "[1, Choice(2, Choice(2, 2)), 1]");
+        assert_eq!(sections("x(?:y(?:zz|ww)*)?v"), "[2, Choice([1, Loop(Choice(2, 2))], []), 2]");
+        assert_eq!(sections("ab??"), "[2, Choice([], 1), 1]");
+    }
 }
@@ -0,0 +1,208 @@
+/// Trie of the bytes of literals, generated as nested `match`es on the
+/// bytes of the input so that shared prefixes are only compared once
+pub struct Trie {
+    nodes: Vec<Node>,
+}
+
+#[derive(Default)]
+struct Node {
+    /// Sorted by byte
+    children: Vec<(u8, usize)>,
+    /// Values of the literals ending at this node
+    values: Vec<usize>,
+}
+
+impl Trie {
+    pub fn new() -> Self {
+        Trie {
+            nodes: vec![Node::default()],
+        }
+    }
+
+    /// Add `literal`, reported as `value` when it is found
+    pub fn insert(&mut self, literal: &[u8], value: usize) {
+        let mut node = 0;
+
+        for &byte in literal {
+            node = match self.nodes[node]
+                .children
+                .binary_search_by_key(&byte, |&(byte, _)| byte)
+            {
+                Ok(i) => self.nodes[node].children[i].1,
+                Err(i) => {
+                    let child = self.nodes.len();
+
+                    self.nodes.push(Node::default());
+                    self.nodes[node].children.insert(i, (byte, child));
+                    child
+                }
+            };
+        }
+
+        self.nodes[node].values.push(value);
+    }
+
+    /// Expression walking the trie over the byte slice `bytes` from the
+    /// offset `at`.
+    ///
+    /// Where the walk stops its value is the code `found` returns for the
+    /// literals found on the way, as `(value, length)` pairs sorted by
+    /// value.
+    pub fn code(
+        &self,
+        bytes: &str,
+        at: &str,
+        found: &mut impl FnMut(&[(usize, usize)]) -> String,
+    ) -> String {
+        self.node_code(0, 0, &mut vec![], bytes, at, found)
+    }
+
+    fn node_code(
+        &self,
+        node: usize,
+        depth: usize,
+        path: &mut Vec<(usize, usize)>,
+        bytes: &str,
+        at: &str,
+        found: &mut impl FnMut(&[(usize, usize)]) -> String,
+    ) -> String {
+        let path_len = path.len();
+        path.extend(self.nodes[node].values.iter().map(|&value| (value, depth)));
+
+        let mut sorted = path.clone();
+        sorted.sort_unstable();
+
+        let stop = found(&sorted);
+        let offset = |depth: usize| match depth {
+            0 => at.to_string(),
+            _ => format!("{at} + {depth}"),
+        };
+
+        let code = match self.nodes[node].children.as_slice() {
+            [] => stop,
+            // A chain of nodes with a single child and no literals is
+            // compared as a whole
+            &[(byte, child)] => {
+                let mut run = vec![byte];
+                let mut next = child;
+
+                while let (&[(byte, child)], []) = (
+                    self.nodes[next].children.as_slice(),
+                    self.nodes[next].values.as_slice(),
+                ) {
+                    run.push(byte);
+                    next = child;
+                }
+
+                let end = depth + run.len();
+                let next = self.node_code(next, end, path, bytes, at, found);
+
+                if run.len() == 1 {
+                    format!(
+                        "match {bytes}.get({}) {{
+    Some(&b'{}') => {{
+        {}
+    }}
+    _ => {{
+        {}
+    }}
+}}",
+                        offset(depth),
+                        escape(byte),
+                        next.replace('\n', "\n        "),
+                        stop.replace('\n', "\n        ")
+                    )
+                } else {
+                    format!(
+                        "if {bytes}.get({}..{}) == Some(b\"{}\".as_slice()) {{
+    {}
+}} else {{
+    {}
+}}",
+                        offset(depth),
+                        offset(end),
+                        run.into_iter().map(escape).collect::<String>(),
+                        next.replace('\n', "\n    "),
+                        stop.replace('\n', "\n    ")
+                    )
+                }
+            }
+            children => {
+                let arms = children
+                    .iter()
+                    .map(|&(byte, child)| {
+                        let next = self
+                            .node_code(child, depth + 1, path, bytes, at, found);
+
+                        format!(
+                            "Some(&b'{}') => {{\n    {}\n}}",
+                            escape(byte),
+                            next.replace('\n', "\n    ")
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                format!(
+                    "match {bytes}.get({}) {{
+    {}
+    _ => {{
+        {}
+    }}
+}}",
+                    offset(depth),
+                    arms.replace('\n', "\n    "),
+                    stop.replace('\n', "\n        ")
+                )
+            }
+        };
+
+        path.truncate(path_len);
+        code
+    }
+}
+
+/// `byte` escaped for a byte or byte string literal
+fn escape(byte: u8) -> String {
+    std::ascii::escape_default(byte).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Trie;
+
+    #[test]
+    fn shared_prefixes() {
+        let mut trie = Trie::new();
+
+        for (value, literal) in ["ab", "abcd", "b", "abce"].iter().enumerate() {
+            trie.insert(literal.as_bytes(), value);
+        }
+
+        let mut stops = vec![];
+        let code = trie.code("bytes", "at", &mut |found| {
+            stops.push(found.to_vec());
+            format!("{found:?}")
+        });
+
+        // The prefix shared by three literals is compared once
+        assert_eq!(code.matches("Some(&b'a')").count(), 1);
+        assert!(stops.contains(&vec![(0, 2), (1, 4)]));
+        assert!(stops.contains(&vec![(0, 2), (3, 4)]));
+        assert!(stops.contains(&vec![(2, 1)]));
+        assert!(stops.contains(&vec![]));
+    }
+
+    #[test]
+    fn chains() {
+        let mut trie = Trie::new();
+
+        trie.insert(b"tripadvisor.at", 0);
+        trie.insert(b"tripadvisor.be", 1);
+
+        let code = trie.code("bytes", "at", &mut |found| format!("{found:?}"));
+
+        assert!(code.contains("bytes.get(at..at + 12) == Some(b\"tripadvisor.\".as_slice())"));
+        assert!(code.contains("Some(&b't')"));
+    }
+}
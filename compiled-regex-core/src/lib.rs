@@ -1,38 +1,254 @@
 #![allow(dead_code)]
-use regex::internal::Compiler;
-use regex::internal::Inst;
+use regex_syntax::hir::{Hir, HirKind};
 use regex_syntax::Parser;
 
+mod backtrack;
+pub mod build;
+pub mod cache;
+pub mod codegen;
+mod compiler;
+mod finite;
+mod interpreter;
 mod ir;
 mod parser;
+pub mod options;
+pub mod pattern_file;
+pub mod prefilter;
+mod program;
+mod reverse;
+mod set;
+mod simplify;
 pub mod types;
-pub use ir::functions::CHAR_GET_FUNC;
+pub mod vectors;
+
+use compiler::Compiler;
+use options::{Engine, Options};
+use program::{Inst, InstPtr};
+pub use ir::functions::{CHAR_GET_FUNC, CONST_CHAR_GET_FUNC};
 
 use parser::parse;
 
+/// A pattern compiled into the functions of its generated type
+pub struct CompiledRegex {
+    pub implementation: ir::functions::ProgramImplementation,
+    /// Matches can only start at the beginning of the input
+    pub is_anchored_start: bool,
+    /// Matches can only end at the end of the input
+    pub is_anchored_end: bool,
+    /// Function with the same parameters as the root function that
+    /// returns `Result<bool, StackExhausted>`, generated when the
+    /// backtracking stack has a `stack_limit`
+    pub fallible: Option<String>,
+    /// Set by `Engine::Bounded`, whose visited set is shared between
+    /// all start positions of a search
+    pub bounded: Option<Bounded>,
+}
+
+/// Patterns compiled into the functions of a single generated type
+pub struct CompiledRegexSet {
+    /// Items of the `impl` block, the entry point being `__search`,
+    /// `__longest` for a lexer or `__automaton` for a stream or a chunked
+    /// haystack
+    pub items: String,
+    /// Number of patterns in the set
+    pub patterns: usize,
+    /// Number of states, needed to create a `SetCache`
+    pub states: usize,
+}
+
+/// Matching with a set of visited (instruction, position) pairs
+pub struct Bounded {
+    /// Expression creating an empty visited set for `input`
+    pub visited: String,
+    /// Statement emptying `visited` to be used for `input`, keeping
+    /// its memory
+    pub reset: String,
+    /// Function taking `input`, `index` and `&mut` the visited set,
+    /// otherwise the same as the root function
+    pub matcher: String,
+}
+
 pub fn parse_regex(
     input: &str,
-) -> Result<ir::functions::ProgramImplementation, types::CompileError> {
+    options: &Options,
+) -> Result<CompiledRegex, types::CompileError> {
+    options.validate()?;
+
+    let hir = parse_hir(input)?;
+
+    // Patterns only matching a few whole inputs are looked up instead of
+    // matched, unless the options ask for a specific engine
+    if *options == Options::default() {
+        if let Some(language) = finite::language(&hir) {
+            return Ok(finite::parse(&language));
+        }
+    }
+
+    let program = compile_program(&hir, options)?;
+
+    #[cfg(debug_assertions)]
+    println!("Program \"{}\":\n{:?}", input, program);
+
+    Ok(match options.engine {
+        Engine::Backtrack | Engine::Bounded => {
+            backtrack::parse(&program, options)
+        }
+        Engine::Recursive | Engine::Structured => CompiledRegex {
+            implementation: parse_program(
+                &program.insts,
+                program.start,
+                options,
+            )?,
+            is_anchored_start: program.is_anchored_start,
+            is_anchored_end: program.is_anchored_end,
+            fallible: None,
+            bounded: None,
+        },
+    })
+}
+
+fn parse_hir(input: &str) -> Result<Hir, types::CompileError> {
     let hir = Parser::new()
         .parse(input)
-        .map_err(types::CompileError::RegexSyntaxError)?;
+        .map_err(|err| types::CompileError::RegexSyntaxError(Box::new(err)))?;
+
+    Ok(simplify::simplify(&hir))
+}
+
+/// The program the engines of `options` generate code from
+fn compile_program(
+    hir: &Hir,
+    options: &Options,
+) -> Result<program::Program, types::CompileError> {
+    // Counted repetitions keep a count that is not part of the pairs
+    // visited by the bounded engine, and like literal tries use closures
+    // and iterators that can not be called in a `const fn`
+    Compiler::new()
+        .unroll_repetitions(options.engine == Engine::Bounded || options.const_fn)
+        .literal_tries(!options.const_fn)
+        .compile(hir)
+}
+
+pub fn parse_regex_set(
+    patterns: &[&str],
+    options: &Options,
+) -> Result<CompiledRegexSet, types::CompileError> {
+    if *options != Options::default() {
+        return Err(types::CompileError::InvalidOption(String::from(
+            "sets do not take options",
+        )));
+    }
+
+    let hirs = parse_hirs(patterns)?;
+
+    // Sets of plain literals, such as lists of domains, do not need any
+    // threads at all
+    let literals = hirs
+        .iter()
+        .map(|hir| match hir.kind() {
+            HirKind::Literal(literal) => std::str::from_utf8(&literal.0).ok(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+
+    if let Some(literals) = literals {
+        return Ok(set::parse_literals(&literals));
+    }
 
+    // The threads of a set only remember their instruction, not a count
     let program = Compiler::new()
-        .compile(std::slice::from_ref(&hir))
-        .map_err(types::CompileError::RegexError)?;
+        .unroll_repetitions(true)
+        .compile_many(&hirs)?;
 
-    #[cfg(debug_assertions)]
-    println!("Program \"{}\":\n{:?}", input, program);
+    Ok(set::parse(&program, patterns.len()))
+}
 
-    parse_program(&program.insts)
+/// What an input has to contain to be matched by the pattern `input`
+pub fn parse_prefilter(input: &str) -> Result<prefilter::Prefilter, types::CompileError> {
+    Ok(prefilter::Prefilter::new(&parse_hir(input)?))
 }
 
-fn parse_program<'lt>(
-    instructions: &'lt [Inst],
+/// The functions of a matcher reading one character at a time, as
+/// streams and chunked haystacks do, the entry point being `__automaton`
+pub fn parse_automaton(input: &str) -> Result<CompiledRegexSet, types::CompileError> {
+    compile_automaton(parse_hir(input)?)
+}
+
+/// `parse_automaton` of the pattern matching the reversed text of what
+/// the pattern `input` matches, which reads the input backwards
+pub fn parse_reverse_automaton(
+    input: &str,
+) -> Result<CompiledRegexSet, types::CompileError> {
+    compile_automaton(reverse::reverse(&parse_hir(input)?)?)
+}
+
+fn compile_automaton(hir: Hir) -> Result<CompiledRegexSet, types::CompileError> {
+    let program = Compiler::new()
+        .unroll_repetitions(true)
+        .compile_many(&[hir])?;
+
+    Ok(set::parse_automaton(&program))
+}
+
+/// The functions of a lexer trying every pattern at a position, the
+/// entry point being `__longest`
+pub fn parse_lexer(
+    patterns: &[&str],
+) -> Result<CompiledRegexSet, types::CompileError> {
+    let hirs = parse_hirs(patterns)?;
+
+    // A token has to consume something, or the lexer would not advance
+    let errors = hirs
+        .iter()
+        .enumerate()
+        .filter(|(_, hir)| hir.properties().minimum_len() == Some(0))
+        .map(|(i, _)| {
+            (i, types::CompileError::Unsupported(String::from(
+                "tokens can not match the empty string",
+            )))
+        })
+        .collect::<Vec<_>>();
+
+    if !errors.is_empty() {
+        return Err(types::CompileError::Patterns(errors));
+    }
+
+    let program = Compiler::new()
+        .unroll_repetitions(true)
+        .compile_many(&hirs)?;
+
+    Ok(set::parse_lexer(&program, patterns.len()))
+}
+
+/// Every pattern is parsed, so all invalid ones can be reported
+fn parse_hirs(patterns: &[&str]) -> Result<Vec<Hir>, types::CompileError> {
+    let mut hirs = Vec::with_capacity(patterns.len());
+    let mut errors = vec![];
+
+    for (i, pattern) in patterns.iter().enumerate() {
+        match parse_hir(pattern) {
+            Ok(hir) => hirs.push(hir),
+            Err(err) => errors.push((i, err)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(types::CompileError::Patterns(errors));
+    }
+
+    Ok(hirs)
+}
+
+fn parse_program(
+    instructions: &[Inst],
+    start: InstPtr,
+    options: &Options,
 ) -> Result<ir::functions::ProgramImplementation, types::CompileError> {
-    // let program = ir::sections::Program::try_parse(instructions)?;
+    if options.engine == Engine::Structured {
+        let program = ir::sections::Program::try_parse(instructions, start)?;
+
+        return ir::functions::ProgramImplementation::try_parse(&program);
+    }
 
-    // ir::functions::ProgramImplementation::try_parse(&program)
-    
-    Ok(parse(instructions))
+    Ok(parse(instructions, start, options.const_fn))
 }
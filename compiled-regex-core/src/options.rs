@@ -0,0 +1,201 @@
+use crate::types::{CompileError, Result};
+
+/// Bytes the visited set of `Engine::Bounded` may use before falling
+/// back to a sparse set, the same default as the `BoundedBacktracker`
+/// of `regex-automata`
+pub const DEFAULT_VISITED_CAPACITY: usize = 256 * 1024;
+
+/// How the instructions of a pattern are turned into code
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Engine {
+    /// A single function stepping through the instructions in a loop,
+    /// keeping the alternatives that are still to be tried on an
+    /// explicit stack. Uses a constant amount of the call stack no
+    /// matter the length of the input.
+    #[default]
+    Backtrack,
+    /// Like `Backtrack`, but remembers the (instruction, position) pairs
+    /// that were explored so none is explored twice. Takes at most time
+    /// proportional to the length of the input times the number of
+    /// instructions, even for patterns like `(a|a)*b`.
+    Bounded,
+    /// A function per instruction, each calling the function of the
+    /// next instruction. The call depth grows with the length of the
+    /// match, so long inputs can overflow the stack.
+    Recursive,
+    /// Functions for the loops and alternations of the pattern, with
+    /// everything in between as straight line code. Every loop is a
+    /// native loop, keeping the ends of its body that are still to be
+    /// tried on a stack of its own when the body is not characters, and
+    /// alternations call a function per alternative tried.
+    Structured,
+}
+
+/// Options given to `parse_regex!` after the pattern, such as
+/// `parse_regex!(Name = "pattern", engine = recursive)`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    pub engine: Engine,
+    /// Maximum number of alternatives kept on the backtracking stack.
+    ///
+    /// When set the stack is a fixed size array on the call stack
+    /// instead of a heap allocation, and the generated type gains
+    /// `try_is_match` and `try_find`, which report running out of
+    /// space as an error instead of panicking.
+    pub stack_limit: Option<usize>,
+    /// Bytes the bitset of `Engine::Bounded` may use, inputs needing a
+    /// larger bitset use a sparse set instead. Defaults to
+    /// [`DEFAULT_VISITED_CAPACITY`].
+    pub visited_capacity: Option<usize>,
+    /// Make `is_match` and `find` `const fn`, so they can be evaluated
+    /// at compile time. Repetitions are unrolled and character classes
+    /// are searched with loops instead of iterators, which only the
+    /// recursive engine supports.
+    pub const_fn: bool,
+    /// Also generate `stream_find`, which finds every match in the text
+    /// of an `std::io::Read` chunk by chunk. Runs the threads of a Pike
+    /// VM, whose state is kept between chunks.
+    pub stream: bool,
+    /// Also generate `is_match_in` and `find_in`, which match a
+    /// `Haystack` read a character at a time, such as the segments of a
+    /// rope, with the same threads as `stream`.
+    pub chunked: bool,
+    /// Also generate `rfind`, which searches backwards from the end of
+    /// the input with the threads of the reversed pattern. Patterns
+    /// anchored at the end only, like `\.gif$`, are then searched that
+    /// way by `is_match` and `find` too, unless they are `const_fn`.
+    /// CRLF-aware line anchors, `(?R)`, are not supported.
+    pub reverse: bool,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the option `key` from the text of its `value`
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let invalid = || {
+            CompileError::InvalidOption(format!(
+                "invalid value `{value}` for option `{key}`"
+            ))
+        };
+
+        match key {
+            "engine" => {
+                self.engine = match value {
+                    "backtrack" => Engine::Backtrack,
+                    "bounded" => Engine::Bounded,
+                    "recursive" => Engine::Recursive,
+                    "structured" => Engine::Structured,
+                    _ => return Err(invalid()),
+                }
+            }
+            "stack_limit" => {
+                let limit = value.parse().map_err(|_| invalid())?;
+
+                if limit == 0 {
+                    return Err(invalid());
+                }
+
+                self.stack_limit = Some(limit);
+            }
+            "visited_capacity" => {
+                self.visited_capacity =
+                    Some(value.parse().map_err(|_| invalid())?);
+            }
+            "const_fn" => {
+                self.const_fn = value.parse().map_err(|_| invalid())?;
+            }
+            "stream" => {
+                self.stream = value.parse().map_err(|_| invalid())?;
+            }
+            "chunked" => {
+                self.chunked = value.parse().map_err(|_| invalid())?;
+            }
+            "reverse" => {
+                self.reverse = value.parse().map_err(|_| invalid())?;
+            }
+            _ => {
+                return Err(CompileError::InvalidOption(format!(
+                    "unknown option `{key}`"
+                )))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that the options can be used together
+    pub fn validate(&self) -> Result<()> {
+        if self.stack_limit.is_some() && self.engine != Engine::Backtrack {
+            return Err(CompileError::InvalidOption(String::from(
+                "`stack_limit` requires `engine = backtrack`",
+            )));
+        }
+
+        if self.visited_capacity.is_some() && self.engine != Engine::Bounded
+        {
+            return Err(CompileError::InvalidOption(String::from(
+                "`visited_capacity` requires `engine = bounded`",
+            )));
+        }
+
+        if self.const_fn && self.engine != Engine::Recursive {
+            return Err(CompileError::InvalidOption(String::from(
+                "`const_fn` requires `engine = recursive`",
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Engine, Options};
+
+    #[test]
+    fn set_options() {
+        let mut options = Options::new();
+
+        options.set("engine", "recursive").unwrap();
+        assert_eq!(options.engine, Engine::Recursive);
+        assert!(options.validate().is_ok());
+
+        options.set("stack_limit", "128").unwrap();
+        assert_eq!(options.stack_limit, Some(128));
+        assert!(options.validate().is_err());
+
+        assert!(options.set("stack_limit", "0").is_err());
+        options.set("engine", "structured").unwrap();
+        assert_eq!(options.engine, Engine::Structured);
+        assert!(options.set("engine", "fast").is_err());
+        assert!(options.set("colour", "blue").is_err());
+
+        let mut options = Options::new();
+
+        options.set("visited_capacity", "1024").unwrap();
+        assert!(options.validate().is_err());
+
+        options.set("engine", "bounded").unwrap();
+        assert!(options.validate().is_ok());
+
+        let mut options = Options::new();
+
+        options.set("const_fn", "true").unwrap();
+        assert!(options.validate().is_err());
+
+        options.set("engine", "recursive").unwrap();
+        assert!(options.validate().is_ok());
+        assert!(options.set("const_fn", "yes").is_err());
+
+        // Streaming works with every engine
+        options.set("stream", "true").unwrap();
+        assert!(options.stream && options.validate().is_ok());
+        options.set("chunked", "true").unwrap();
+        assert!(options.chunked && options.validate().is_ok());
+        options.set("reverse", "true").unwrap();
+        assert!(options.reverse && options.validate().is_ok());
+    }
+}
@@ -0,0 +1,90 @@
+/// A pattern read from a line of a pattern file
+#[derive(Debug, PartialEq, Eq)]
+pub struct PatternLine {
+    /// Line of the pattern, starting at 1
+    pub line: usize,
+    /// Name of a `name = pattern` line
+    pub name: Option<String>,
+    pub pattern: String,
+}
+
+/// The patterns of a file with one pattern per line.
+///
+/// Blank lines and lines starting with `#` are skipped. A line that
+/// starts with an identifier followed by ` = ` gives a name to the
+/// pattern after it, the spaces around the `=` telling it apart from
+/// patterns such as `ad_type=banner`.
+pub fn parse_pattern_file(text: &str) -> Vec<PatternLine> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim_start();
+
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(|(i, line)| {
+            let (name, pattern) = match line.split_once(" = ") {
+                Some((name, pattern)) if is_identifier(name.trim()) => {
+                    (Some(name.trim().to_string()), pattern.trim_start())
+                }
+                _ => (None, line),
+            };
+
+            PatternLine {
+                line: i + 1,
+                name,
+                pattern: pattern.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// `path:line` for messages about a line of a pattern file, only `path`
+/// for line 0
+pub fn location(path: &str, line: usize) -> String {
+    if line == 0 {
+        path.to_string()
+    } else {
+        format!("{path}:{line}")
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_pattern_file, PatternLine};
+
+    #[test]
+    fn lines() {
+        let text = "# comment\n\n&adstype=\n  # indented comment\nBanner = -ad-banner-\nad_type=banner\r\n";
+
+        assert_eq!(
+            parse_pattern_file(text),
+            [
+                PatternLine {
+                    line: 3,
+                    name: None,
+                    pattern: String::from("&adstype="),
+                },
+                PatternLine {
+                    line: 5,
+                    name: Some(String::from("Banner")),
+                    pattern: String::from("-ad-banner-"),
+                },
+                PatternLine {
+                    line: 6,
+                    name: None,
+                    pattern: String::from("ad_type=banner"),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use regex_syntax::hir::literal::{ExtractKind, Extractor};
+use regex_syntax::hir::{Hir, Look};
+
+/// What an input has to contain for a pattern to match it at all, which
+/// `is_match_many` and `filter` check before running the matcher on
+/// each input
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Prefilter {
+    /// Shortest input that can match
+    pub min_len: usize,
+    /// Longest input that can match, only known when matches span the
+    /// whole input
+    pub max_len: Option<usize>,
+    /// Text every match contains
+    pub literal: Option<Literal>,
+    /// Text every match ends with
+    pub suffix: Option<String>,
+    /// Matches can only end at the end of the input
+    pub is_anchored_end: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Literal {
+    /// The input starts with the text, for patterns anchored at the start
+    Prefix(String),
+    /// The input contains the text somewhere
+    Substring(String),
+}
+
+impl Prefilter {
+    pub fn new(hir: &Hir) -> Self {
+        let properties = hir.properties();
+        let is_anchored_start = properties.look_set_prefix().contains(Look::Start);
+        let is_anchored_end = properties.look_set_suffix().contains(Look::End);
+
+        // Every match starts with one of the prefixes, so with the text
+        // they all start with
+        let prefixes = Extractor::new().extract(hir);
+        let literal = prefixes
+            .longest_common_prefix()
+            .map(|prefix| match std::str::from_utf8(prefix) {
+                Ok(prefix) => prefix,
+                // Cut in the middle of a character
+                Err(error) => std::str::from_utf8(&prefix[..error.valid_up_to()]).unwrap(),
+            })
+            .filter(|prefix| !prefix.is_empty())
+            .map(|prefix| match is_anchored_start {
+                true => Literal::Prefix(prefix.to_string()),
+                false => Literal::Substring(prefix.to_string()),
+            });
+
+        let suffixes = Extractor::new().kind(ExtractKind::Suffix).extract(hir);
+        let suffix = suffixes
+            .longest_common_suffix()
+            .map(|suffix| {
+                // Cut in the middle of a character
+                let start = suffix
+                    .iter()
+                    .position(|&byte| byte & 0xC0 != 0x80)
+                    .unwrap_or(suffix.len());
+
+                std::str::from_utf8(&suffix[start..]).unwrap()
+            })
+            .filter(|suffix| !suffix.is_empty())
+            .map(String::from);
+
+        Prefilter {
+            min_len: properties.minimum_len().unwrap_or(0),
+            max_len: properties
+                .maximum_len()
+                .filter(|_| is_anchored_start && is_anchored_end),
+            literal,
+            suffix,
+            is_anchored_end,
+        }
+    }
+
+    /// Expression that is `false` for an `input` the pattern can not match
+    pub fn condition(&self) -> String {
+        let mut conditions = vec![];
+
+        if self.min_len > 0 {
+            conditions.push(format!("input.len() >= {}", self.min_len));
+        }
+
+        if let Some(max_len) = self.max_len {
+            conditions.push(format!("input.len() <= {max_len}"));
+        }
+
+        match &self.literal {
+            Some(Literal::Prefix(prefix)) => {
+                conditions.push(format!("input.starts_with({prefix:?})"))
+            }
+            Some(Literal::Substring(substring)) => {
+                conditions.push(format!("input.contains({substring:?})"))
+            }
+            None => (),
+        }
+
+        // Looking for the suffix too would search the input twice
+        match &self.suffix {
+            Some(suffix) if self.is_anchored_end => {
+                conditions.push(format!("input.ends_with({suffix:?})"))
+            }
+            Some(suffix) if self.literal.is_none() => {
+                conditions.push(format!("input.contains({suffix:?})"))
+            }
+            _ => (),
+        }
+
+        match conditions.is_empty() {
+            true => String::from("true"),
+            false => conditions.join(" && "),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex_syntax::Parser;
+
+    use super::{Literal, Prefilter};
+
+    fn prefilter(pattern: &str) -> Prefilter {
+        Prefilter::new(&Parser::new().parse(pattern).unwrap())
+    }
+
+    #[test]
+    fn literals() {
+        let anchored = prefilter(r"^tripadvisor\.(?:at|be|com\.ar)#");
+
+        assert_eq!(anchored.literal, Some(Literal::Prefix(String::from("tripadvisor."))));
+        assert_eq!(anchored.min_len, 15);
+        assert_eq!(anchored.max_len, None);
+
+        let substring = prefilter(r"[a-z]+\.amazonaws\.com/");
+
+        assert_eq!(substring.literal, None);
+        assert_eq!(prefilter(r"s3\.\w+\.amazonaws").literal, Some(Literal::Substring(String::from("s3."))));
+        assert_eq!(prefilter("(?i)abc").literal, None);
+        assert_eq!(prefilter("αβ|αγ").literal, Some(Literal::Substring(String::from("α"))));
+        assert_eq!(prefilter("a*").condition(), "true");
+    }
+
+    #[test]
+    fn suffixes() {
+        let gif = prefilter(r"[a-z]+\.gif$");
+
+        assert_eq!(gif.suffix.as_deref(), Some(".gif"));
+        assert_eq!(gif.condition(), r#"input.len() >= 5 && input.ends_with(".gif")"#);
+        assert_eq!(prefilter(r"\w+/ads\.js").condition(), r#"input.len() >= 8 && input.contains("/ads.js")"#);
+        assert_eq!(prefilter("αβ|γβ").suffix.as_deref(), Some("β"));
+        assert_eq!(prefilter("x+").suffix.as_deref(), Some("x"));
+        assert_eq!(prefilter("x*").suffix, None);
+    }
+
+    #[test]
+    fn lengths() {
+        let whole = prefilter(r"^[0-9]{2,4}-x$");
+
+        assert_eq!((whole.min_len, whole.max_len), (4, Some(6)));
+        assert_eq!(whole.condition(), r#"input.len() >= 4 && input.len() <= 6 && input.ends_with("-x")"#);
+        assert_eq!(prefilter(r"[0-9]{2,4}-x$").max_len, None);
+    }
+}
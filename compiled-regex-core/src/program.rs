@@ -0,0 +1,206 @@
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+
+use regex_syntax::hir::Look;
+
+/// Index of an instruction in [`Program::insts`]
+pub type InstPtr = usize;
+
+/// The instructions of a compiled pattern.
+///
+/// Mirrors the instruction set of `regex::internal::Inst` so that the
+/// code generators can treat both the same, with the addition of
+/// instructions that the `regex` compiler has no notion of.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Inst {
+    Match(usize),
+    Save(InstSave),
+    Split(InstSplit),
+    EmptyLook(InstEmptyLook),
+    Char(InstChar),
+    Ranges(InstRanges),
+    Bytes(InstBytes),
+    Repeat(InstRepeat),
+    Literals(InstLiterals),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InstSave {
+    pub goto: InstPtr,
+    pub slot: usize,
+}
+
+/// Try `goto1` first, then `goto2`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InstSplit {
+    pub goto1: InstPtr,
+    pub goto2: InstPtr,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstEmptyLook {
+    pub goto: InstPtr,
+    pub look: Look,
+}
+
+// `Look` is not `Hash`, its representation is
+impl Hash for InstEmptyLook {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.goto.hash(state);
+        self.look.as_repr().hash(state);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InstChar {
+    pub goto: InstPtr,
+    pub c: char,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InstRanges {
+    pub goto: InstPtr,
+    pub ranges: Vec<(char, char)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InstBytes {
+    pub goto: InstPtr,
+    pub start: u8,
+    pub end: u8,
+}
+
+/// Counted repetition of a body that always consumes the same number
+/// of characters, such as `[a-f0-9]{45,}` or `(?:ab){1000}`.
+///
+/// Replaces the unrolled copies the `regex` compiler would produce with
+/// a single instruction that is generated as a loop with a counter.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InstRepeat {
+    pub goto: InstPtr,
+    pub min: usize,
+    /// `None` for repetitions without an upper bound
+    pub max: Option<usize>,
+    pub greedy: bool,
+    /// Matched in order for every repetition. Only contains `Char` and
+    /// `Ranges` instructions, whose `goto` is not used.
+    pub body: Vec<Inst>,
+}
+
+impl InstRepeat {
+    /// Number of characters consumed by one repetition
+    pub fn width(&self) -> usize {
+        self.body.len()
+    }
+
+    /// Every repetition consumes exactly `width()` bytes when the body
+    /// only accepts ASCII characters
+    pub fn is_ascii(&self) -> bool {
+        self.body.iter().all(|inst| match inst {
+            Inst::Char(x) => x.c.is_ascii(),
+            Inst::Ranges(x) => x.ranges.iter().all(|(_, end)| end.is_ascii()),
+            _ => false,
+        })
+    }
+}
+
+/// Alternation of literals such as `foo|foobar|bar`, tried in order.
+///
+/// Replaces the chain of splits that would match the shared prefixes
+/// once per literal with a trie of their bytes, which is walked once to
+/// find every literal at the current position.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InstLiterals {
+    pub goto: InstPtr,
+    /// Without duplicates, in order of priority
+    pub literals: Vec<String>,
+}
+
+pub struct Program {
+    pub insts: Vec<Inst>,
+    /// Instruction matching starts at
+    pub start: InstPtr,
+    pub is_anchored_start: bool,
+    pub is_anchored_end: bool,
+}
+
+impl Program {
+    /// Instructions that a backward jump goes to and that can be reached
+    /// from themselves without consuming any input, the heads of loops
+    /// whose body can match the empty string.
+    ///
+    /// Every cycle of the program goes through a backward jump, so a
+    /// path repeating a loop without making progress passes one of them
+    /// twice at the same position.
+    pub fn empty_loop_heads(&self) -> Vec<InstPtr> {
+        let mut heads = self
+            .insts
+            .iter()
+            .enumerate()
+            .flat_map(|(i, inst)| self.gotos(inst).into_iter().filter(move |&goto| goto <= i))
+            .collect::<Vec<_>>();
+        heads.sort_unstable();
+        heads.dedup();
+        heads.retain(|&head| self.reaches_without_input(head));
+        heads
+    }
+
+    /// Instructions `inst` continues at
+    fn gotos(&self, inst: &Inst) -> Vec<InstPtr> {
+        match inst {
+            Inst::Match(_) => vec![],
+            Inst::Save(x) => vec![x.goto],
+            Inst::Split(x) => vec![x.goto1, x.goto2],
+            Inst::EmptyLook(x) => vec![x.goto],
+            Inst::Char(x) => vec![x.goto],
+            Inst::Ranges(x) => vec![x.goto],
+            Inst::Bytes(x) => vec![x.goto],
+            Inst::Repeat(x) => vec![x.goto],
+            Inst::Literals(x) => vec![x.goto],
+        }
+    }
+
+    /// Instructions `inst` may continue at without consuming input
+    fn empty_gotos(&self, inst: &Inst) -> Vec<InstPtr> {
+        match inst {
+            Inst::Save(_) | Inst::Split(_) | Inst::EmptyLook(_) => self.gotos(inst),
+            Inst::Repeat(x) if x.min == 0 => vec![x.goto],
+            Inst::Literals(x) if x.literals.iter().any(String::is_empty) => vec![x.goto],
+            _ => vec![],
+        }
+    }
+
+    /// Whether `head` can be reached from itself without consuming input
+    fn reaches_without_input(&self, head: InstPtr) -> bool {
+        let mut seen = vec![false; self.insts.len()];
+        let mut stack = self.empty_gotos(&self.insts[head]);
+
+        while let Some(pc) = stack.pop() {
+            if pc == head {
+                return true;
+            }
+
+            if !std::mem::replace(&mut seen[pc], true) {
+                stack.extend(self.empty_gotos(&self.insts[pc]));
+            }
+        }
+
+        false
+    }
+}
+
+impl Debug for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, inst) in self.insts.iter().enumerate() {
+            write!(f, "{:04} {:?}", i, inst)?;
+
+            if i == self.start {
+                write!(f, " (start)")?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
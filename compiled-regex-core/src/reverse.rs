@@ -0,0 +1,83 @@
+use regex_syntax::hir::{Capture, Hir, HirKind, Literal, Look, Repetition};
+
+use crate::types::{CompileError, Result};
+
+/// Pattern matching the reversed text of every text `hir` matches, so
+/// matches can be searched from the end of the input backwards.
+///
+/// Characters keep their bytes, which is why literals are reversed by
+/// character instead of by byte.
+pub(crate) fn reverse(hir: &Hir) -> Result<Hir> {
+    Ok(match hir.kind() {
+        HirKind::Empty => Hir::empty(),
+        HirKind::Literal(Literal(bytes)) => match std::str::from_utf8(bytes) {
+            Ok(text) => Hir::literal(text.chars().rev().collect::<String>().into_bytes()),
+            Err(_) => Hir::literal(bytes.iter().rev().copied().collect::<Vec<_>>()),
+        },
+        HirKind::Class(class) => Hir::class(class.clone()),
+        HirKind::Look(look) => Hir::look(reverse_look(*look)?),
+        HirKind::Repetition(repetition) => Hir::repetition(Repetition {
+            sub: Box::new(reverse(&repetition.sub)?),
+            ..repetition.clone()
+        }),
+        HirKind::Capture(capture) => Hir::capture(Capture {
+            sub: Box::new(reverse(&capture.sub)?),
+            ..capture.clone()
+        }),
+        HirKind::Concat(hirs) => {
+            Hir::concat(hirs.iter().rev().map(reverse).collect::<Result<_>>()?)
+        }
+        HirKind::Alternation(hirs) => {
+            Hir::alternation(hirs.iter().map(reverse).collect::<Result<_>>()?)
+        }
+    })
+}
+
+/// The assertion holding at the same place of the reversed input
+fn reverse_look(look: Look) -> Result<Look> {
+    Ok(match look {
+        Look::Start => Look::End,
+        Look::End => Look::Start,
+        Look::StartLF => Look::EndLF,
+        Look::EndLF => Look::StartLF,
+        // Reversed, `\r\n` reads `\n\r`, which `EndCRLF` and `StartCRLF`
+        // do not see as a line break
+        Look::StartCRLF | Look::EndCRLF => {
+            return Err(CompileError::Unsupported(String::from(
+                "CRLF-aware line anchors can not be searched in reverse",
+            )))
+        }
+        // Word boundaries look at both sides
+        look => look,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use regex_syntax::Parser;
+
+    use super::reverse;
+
+    fn reversed(pattern: &str) -> String {
+        reverse(&Parser::new().parse(pattern).unwrap()).unwrap().to_string()
+    }
+
+    fn parsed(pattern: &str) -> String {
+        Parser::new().parse(pattern).unwrap().to_string()
+    }
+
+    #[test]
+    fn reversed_patterns() {
+        assert_eq!(reversed(r"ab+c$"), parsed(r"^cb+a"));
+        assert_eq!(reversed(r"(?m)^x|yαβ"), parsed(r"(?m)x$|βαy"));
+        assert_eq!(reversed(r"\bfoo(?:bar)*"), parsed(r"(?:rab)*oof\b"));
+        assert_eq!(reversed(r"[a-z]{2,3}?\.gif"), parsed(r"fig\.[a-z]{2,3}?"));
+    }
+
+    #[test]
+    fn crlf_lines() {
+        let hir = Parser::new().parse(r"(?Rm)^\n").unwrap();
+
+        assert!(reverse(&hir).is_err());
+    }
+}
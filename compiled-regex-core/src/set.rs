@@ -0,0 +1,410 @@
+use crate::program::{Inst, Program};
+use crate::CompiledRegexSet;
+
+use crate::ir::classes::{class_condition, ClassTables};
+use crate::ir::trie::Trie;
+use crate::ir::functions::{
+    look_condition, CHAR_GET_FUNC_NAME, INPUT_PARAM_NAME, INPUT_PARAM_TYPE,
+};
+
+/// The functions running the threads of `program`, shared by sets and
+/// lexers: `__follow` adds the states following one without consuming
+/// a character, `__step` consumes one and `__accepts` names the pattern
+/// a state matched
+fn states(program: &Program, tables: &mut ClassTables) -> String {
+    let mut follow = vec![];
+    let mut step = vec![];
+    let mut accepts = vec![];
+
+    for (i, inst) in program.insts.iter().enumerate() {
+        match inst {
+            Inst::Split(x) => follow.push(format!(
+                "{i} => {{\n    stack.push({});\n    stack.push({});\n}}",
+                x.goto2, x.goto1
+            )),
+            Inst::Save(x) => follow.push(format!("{i} => stack.push({}),", x.goto)),
+            Inst::EmptyLook(x) => follow.push(format!(
+                "{i} => {{\n    let holds = {};\n    if holds {{\n        stack.push({});\n    }}\n}}",
+                look_condition(x.look, "at", tables)
+                    .replace('\n', "\n    "),
+                x.goto
+            )),
+            Inst::Char(x) => step.push(format!(
+                "{i} => (c == '{}').then_some({}),",
+                x.c.escape_unicode(),
+                x.goto
+            )),
+            Inst::Ranges(x) => step.push(format!(
+                "{i} => {{\n    let is_member = {};\n    is_member.then_some({})\n}}",
+                class_condition(&x.ranges, "c", tables)
+                    .replace('\n', "\n    "),
+                x.goto
+            )),
+            // Only ASCII bytes are compiled, which are the same as the
+            // character
+            Inst::Bytes(x) => step.push(format!(
+                "{i} => matches!(c as u32, {}..={}).then_some({}),",
+                x.start, x.end, x.goto
+            )),
+            Inst::Match(pattern) => {
+                accepts.push(format!("{i} => Some({pattern}),"))
+            }
+            Inst::Repeat(_) => unreachable!(
+                "set::states: counted repetitions have to be unrolled"
+            ),
+            Inst::Literals(_) => unreachable!(
+                "set::states: threads consume a single character per step"
+            ),
+        }
+    }
+
+    let arms = |arms: Vec<String>| {
+        arms.join("\n").replace('\n', "\n            ")
+    };
+
+    format!(
+        "
+/// Add `pc` and the states following it without consuming a character
+/// to `set`, in order of priority
+fn __follow({INPUT_PARAM_NAME}: {INPUT_PARAM_TYPE}, at: usize, pc: usize, set: &mut ::compiled_regex::SparseSet, stack: &mut ::compiled_regex::__alloc::Vec<usize>) {{
+    stack.push(pc);
+
+    while let Some(pc) = stack.pop() {{
+        if !set.insert(pc) {{
+            continue;
+        }}
+
+        match pc {{
+            {}
+            _ => (),
+        }}
+    }}
+}}
+
+/// The state after `pc` consumed `c`, if it accepts `c`
+fn __step(pc: usize, c: char) -> Option<usize> {{
+    match pc {{
+        {}
+        _ => None,
+    }}
+}}
+
+/// The pattern matched by reaching `pc`
+fn __accepts(pc: usize) -> Option<usize> {{
+    match pc {{
+        {}
+        _ => None,
+    }}
+}}
+",
+        arms(follow),
+        arms(step),
+        arms(accepts),
+    )
+}
+
+/// Generate the functions of a set of patterns compiled into a single
+/// `program`, with a `Match(i)` for every pattern `i`.
+///
+/// Rather than backtracking, the patterns run side by side as threads
+/// of a Pike VM: every instruction is a state that is either followed
+/// right away (splits, saves, assertions) or consumes the next
+/// character. The states are kept in insertion order, which is the
+/// order of priority, so the VM can report the leftmost-first match as
+/// well as every pattern that matched, in time proportional to the
+/// length of the input times the number of instructions.
+///
+/// The generated `__search` takes a `SetMode`, the `SetMatches` to fill
+/// for `SetMode::All` and a `SetCache` of `states` instructions.
+pub fn parse(program: &Program, patterns: usize) -> CompiledRegexSet {
+    let mut tables = ClassTables::new();
+    let states = states(program, &mut tables);
+
+    // Anchored sets can not start new threads after the start, so
+    // there is nothing left to do once all threads died
+    let is_done = if program.is_anchored_start {
+        "cache.current.is_empty()"
+    } else {
+        "cache.current.is_empty() && first.is_some()"
+    };
+    let may_start = if program.is_anchored_start {
+        "first.is_none() && at == 0"
+    } else {
+        "first.is_none()"
+    };
+
+    let items = format!(
+        "{states}
+fn __search({INPUT_PARAM_NAME}: {INPUT_PARAM_TYPE}, mode: ::compiled_regex::SetMode, matches: &mut ::compiled_regex::SetMatches, cache: &mut ::compiled_regex::SetCache) -> Option<usize> {{
+    let mut first = None;
+    let mut at = 0;
+
+    cache.current.clear();
+    cache.next.clear();
+
+    loop {{
+        // New threads have a lower priority than the running ones, and
+        // are only started until the first match
+        if {may_start} {{
+            Self::__follow({INPUT_PARAM_NAME}, at, {}, &mut cache.current, &mut cache.stack);
+        }}
+
+        if {is_done} {{
+            break;
+        }}
+
+        let c = Self::{CHAR_GET_FUNC_NAME}({INPUT_PARAM_NAME}, &mut at);
+
+        for &pc in cache.current.as_slice() {{
+            if let Some(pattern) = Self::__accepts(pc) {{
+                match mode {{
+                    ::compiled_regex::SetMode::Earliest => return Some(pattern),
+                    // Every later thread has a lower priority
+                    ::compiled_regex::SetMode::LeftmostFirst => {{
+                        first = Some(pattern);
+                        break;
+                    }}
+                    ::compiled_regex::SetMode::All => {{
+                        matches.insert(pattern);
+                        continue;
+                    }}
+                }}
+            }}
+
+            if let Some(c) = c {{
+                if let Some(goto) = Self::__step(pc, c) {{
+                    Self::__follow({INPUT_PARAM_NAME}, at + c.len_utf8(), goto, &mut cache.next, &mut cache.stack);
+                }}
+            }}
+        }}
+
+        let Some(c) = c else {{
+            break;
+        }};
+
+        if mode == ::compiled_regex::SetMode::All && matches.matched_all() {{
+            break;
+        }}
+
+        at += c.len_utf8();
+        ::core::mem::swap(&mut cache.current, &mut cache.next);
+        cache.next.clear();
+    }}
+
+    first
+}}
+",
+        program.start,
+    );
+
+    let mut constants = tables.into_items().concat();
+    constants.push_str(&items);
+
+    CompiledRegexSet {
+        items: constants,
+        patterns,
+        states: program.insts.len(),
+    }
+}
+
+/// Generate the functions of a matcher of the single pattern of
+/// `program` that reads one character at a time, which are run by the
+/// `Automaton` of `compiled_regex`.
+///
+/// Its threads are resumed with every character, so the state of a
+/// search is the set of threads and the position. The assertions of
+/// `__follow` only look at the characters next to `at`, which is why
+/// streams and haystacks made of segments can give it just those.
+pub fn parse_automaton(program: &Program) -> CompiledRegexSet {
+    let mut tables = ClassTables::new();
+    let states = states(program, &mut tables);
+
+    let items = format!(
+        "{states}
+fn __automaton() -> ::compiled_regex::Automaton {{
+    ::compiled_regex::Automaton {{
+        states: {},
+        start: {},
+        is_anchored_start: {},
+        follow: Self::__follow,
+        step: Self::__step,
+        accepts: Self::__accepts,
+    }}
+}}
+",
+        program.insts.len(),
+        program.start,
+        program.is_anchored_start,
+    );
+
+    let mut constants = tables.into_items().concat();
+    constants.push_str(&items);
+
+    CompiledRegexSet {
+        items: constants,
+        patterns: 1,
+        states: program.insts.len(),
+    }
+}
+
+/// Generate the functions of a lexer of `program`, which runs the
+/// threads of every pattern like `parse` but only from a single start
+/// position.
+///
+/// The generated `__longest` takes the input, the start and a
+/// `SetCache` of `states` instructions, and returns the pattern with
+/// the longest match and the end of the match. Of the patterns with
+/// matches of the same length, the one with the lowest index wins.
+pub fn parse_lexer(program: &Program, patterns: usize) -> CompiledRegexSet {
+    let mut tables = ClassTables::new();
+    let states = states(program, &mut tables);
+
+    let items = format!(
+        "{states}
+fn __longest({INPUT_PARAM_NAME}: {INPUT_PARAM_TYPE}, start: usize, cache: &mut ::compiled_regex::SetCache) -> Option<(usize, usize)> {{
+    let mut longest = None;
+    let mut at = start;
+
+    cache.current.clear();
+    cache.next.clear();
+    Self::__follow({INPUT_PARAM_NAME}, at, {}, &mut cache.current, &mut cache.stack);
+
+    while !cache.current.is_empty() {{
+        let c = Self::{CHAR_GET_FUNC_NAME}({INPUT_PARAM_NAME}, &mut at);
+        // Matches ending here are longer than the ones found before
+        let mut found: Option<usize> = None;
+
+        for &pc in cache.current.as_slice() {{
+            if let Some(pattern) = Self::__accepts(pc) {{
+                found = Some(found.map_or(pattern, |found| found.min(pattern)));
+                continue;
+            }}
+
+            if let Some(c) = c {{
+                if let Some(goto) = Self::__step(pc, c) {{
+                    Self::__follow({INPUT_PARAM_NAME}, at + c.len_utf8(), goto, &mut cache.next, &mut cache.stack);
+                }}
+            }}
+        }}
+
+        if let Some(pattern) = found {{
+            longest = Some((pattern, at));
+        }}
+
+        let Some(c) = c else {{
+            break;
+        }};
+
+        at += c.len_utf8();
+        ::core::mem::swap(&mut cache.current, &mut cache.next);
+        cache.next.clear();
+    }}
+
+    longest
+}}
+",
+        program.start,
+    );
+
+    let mut constants = tables.into_items().concat();
+    constants.push_str(&items);
+
+    CompiledRegexSet {
+        items: constants,
+        patterns,
+        states: program.insts.len(),
+    }
+}
+
+/// Generate the functions of a set made only of literals, like `parse`
+/// but searching with a trie of the literals instead of a Pike VM.
+///
+/// The trie is walked from every position of the input, which finds
+/// every literal starting there sorted by pattern. A literal is valid
+/// UTF-8, so it can only be found at a character boundary.
+pub fn parse_literals(literals: &[&str]) -> CompiledRegexSet {
+    let mut trie = Trie::new();
+
+    for (pattern, literal) in literals.iter().enumerate() {
+        trie.insert(literal.as_bytes(), pattern);
+    }
+
+    let walk = trie.code("bytes", "at", &mut |found| {
+        let patterns = found
+            .iter()
+            .map(|(pattern, _)| pattern.to_string())
+            .collect::<Vec<_>>();
+
+        format!("&[{}]", patterns.join(", "))
+    });
+
+    let items = format!(
+        "
+fn __search({INPUT_PARAM_NAME}: {INPUT_PARAM_TYPE}, mode: ::compiled_regex::SetMode, matches: &mut ::compiled_regex::SetMatches, cache: &mut ::compiled_regex::SetCache) -> Option<usize> {{
+    let bytes = {INPUT_PARAM_NAME}.as_bytes();
+
+    for at in 0..bytes.len() {{
+        let found: &[usize] = {};
+
+        for &pattern in found {{
+            match mode {{
+                // The first pattern has the highest priority
+                ::compiled_regex::SetMode::Earliest
+                | ::compiled_regex::SetMode::LeftmostFirst => return Some(pattern),
+                ::compiled_regex::SetMode::All => matches.insert(pattern),
+            }}
+        }}
+
+        if mode == ::compiled_regex::SetMode::All && matches.matched_all() {{
+            break;
+        }}
+    }}
+
+    None
+}}
+",
+        walk.replace('\n', "\n        "),
+    );
+
+    CompiledRegexSet {
+        items,
+        patterns: literals.len(),
+        states: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex_syntax::Parser;
+
+    use super::{parse, parse_literals};
+    use crate::compiler::Compiler;
+
+    #[test]
+    fn every_pattern_is_accepted() {
+        let hirs = ["a+", "b", "[0-9]{2}"]
+            .map(|pattern| Parser::new().parse(pattern).unwrap());
+        let program = Compiler::new()
+            .unroll_repetitions(true)
+            .compile_many(&hirs)
+            .unwrap();
+
+        let compiled = parse(&program, hirs.len());
+
+        assert_eq!(compiled.states, program.insts.len());
+        for pattern in 0..hirs.len() {
+            assert!(compiled.items.contains(&format!("=> Some({pattern}),")));
+        }
+    }
+
+    #[test]
+    fn literals_share_a_trie() {
+        let compiled = parse_literals(&["foo", "foobar", "bar", "foo"]);
+
+        assert_eq!(compiled.patterns, 4);
+        assert_eq!(compiled.states, 0);
+        assert!(compiled.items.contains("&[0, 3]"));
+        assert!(compiled.items.contains("&[0, 1, 3]"));
+        assert!(compiled.items.contains("&[2]"));
+    }
+}
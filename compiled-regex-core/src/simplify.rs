@@ -0,0 +1,322 @@
+use regex_syntax::hir::{Class, ClassUnicode, ClassUnicodeRange, Hir, HirKind, Repetition};
+
+/// Rewrite `hir` into an equivalent pattern with less redundancy, so
+/// fewer instructions are generated and there is less to backtrack.
+///
+/// Every rewrite keeps the order in which a backtracking matcher tries
+/// the ways of matching, so leftmost-first matches stay the same:
+///
+/// - Capture groups are dropped, no matcher reports them
+/// - Later duplicates of an alternative are dropped, they can only fail
+///   where the first one did
+/// - Adjacent alternatives of a single character become one class
+/// - Prefixes of adjacent alternatives are factored out when they can
+///   only match in one way, `a\.com|a\.net` becomes `a\.(?:com|net)`
+/// - Suffixes of adjacent alternatives are factored out, `x\.com|y\.com`
+///   becomes `[xy]\.com`
+/// - Adjacent repetitions of the same character, class or literal are
+///   merged, `x*x*` becomes `x*` and `xx*` becomes `x+`
+pub fn simplify(hir: &Hir) -> Hir {
+    match hir.kind() {
+        HirKind::Empty
+        | HirKind::Literal(_)
+        | HirKind::Class(_)
+        | HirKind::Look(_) => hir.clone(),
+        HirKind::Repetition(repetition) => Hir::repetition(Repetition {
+            min: repetition.min,
+            max: repetition.max,
+            greedy: repetition.greedy,
+            sub: Box::new(simplify(&repetition.sub)),
+        }),
+        HirKind::Capture(capture) => simplify(&capture.sub),
+        HirKind::Concat(subs) => concat(subs.iter().map(simplify).collect()),
+        HirKind::Alternation(subs) => {
+            alternation(subs.iter().map(simplify).collect())
+        }
+    }
+}
+
+fn concat(subs: Vec<Hir>) -> Hir {
+    let mut merged: Vec<Hir> = Vec::with_capacity(subs.len());
+
+    for sub in subs {
+        match merged.last().and_then(|last| merge_repetitions(last, &sub)) {
+            Some(repetition) => *merged.last_mut().unwrap() = repetition,
+            None => merged.push(sub),
+        }
+    }
+
+    Hir::concat(merged)
+}
+
+fn alternation(subs: Vec<Hir>) -> Hir {
+    let mut unique: Vec<Hir> = Vec::with_capacity(subs.len());
+
+    for sub in subs {
+        if !unique.contains(&sub) {
+            unique.push(sub);
+        }
+    }
+
+    let merged = merge_classes(unique);
+    let factored = factor(merged, Side::Prefix);
+    let factored = factor(factored, Side::Suffix);
+
+    Hir::alternation(factored)
+}
+
+/// `a{m,n}` followed by `a{o,p}` as `a{m+o,n+p}`, where a bare `a`
+/// counts as `a{1}`.
+///
+/// Only for characters, classes and literals, which match in a single
+/// way, and repetitions that are both greedy or both lazy. The ends are
+/// then tried in the same order.
+fn merge_repetitions(a: &Hir, b: &Hir) -> Option<Hir> {
+    fn parts(hir: &Hir) -> (&Hir, u32, Option<u32>, Option<bool>) {
+        match hir.kind() {
+            HirKind::Repetition(x) => (&x.sub, x.min, x.max, Some(x.greedy)),
+            _ => (hir, 1, Some(1), None),
+        }
+    }
+
+    let (sub, min_a, max_a, greedy_a) = parts(a);
+    let (sub_b, min_b, max_b, greedy_b) = parts(b);
+
+    let is_single_way =
+        matches!(sub.kind(), HirKind::Literal(_) | HirKind::Class(_));
+
+    if sub != sub_b || !is_single_way {
+        return None;
+    }
+
+    let greedy = match (greedy_a, greedy_b) {
+        (Some(a), Some(b)) if a != b => return None,
+        (Some(greedy), _) | (_, Some(greedy)) => greedy,
+        // Two bare copies are left to the literal merging of the
+        // concatenation
+        (None, None) => return None,
+    };
+
+    Some(Hir::repetition(Repetition {
+        min: min_a.saturating_add(min_b),
+        max: max_a.zip(max_b).map(|(a, b)| a.saturating_add(b)),
+        greedy,
+        sub: Box::new(sub.clone()),
+    }))
+}
+
+/// The single character `hir` matches, as a class
+fn single_char_class(hir: &Hir) -> Option<ClassUnicode> {
+    match hir.kind() {
+        HirKind::Class(Class::Unicode(class)) => Some(class.clone()),
+        HirKind::Literal(literal) => {
+            let literal = std::str::from_utf8(&literal.0).ok()?;
+            let mut chars = literal.chars();
+
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => {
+                    Some(ClassUnicode::new([ClassUnicodeRange::new(c, c)]))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Runs of adjacent alternatives matching a single character as one
+/// class each. Both match the same single character, so which one does
+/// makes no difference.
+fn merge_classes(subs: Vec<Hir>) -> Vec<Hir> {
+    fn flush(run: &mut Vec<(Hir, ClassUnicode)>, merged: &mut Vec<Hir>) {
+        match run.len() {
+            0 => (),
+            // A lone literal is left as is, for the literal alternations
+            1 => merged.push(run.pop().unwrap().0),
+            _ => {
+                let mut class = ClassUnicode::empty();
+
+                for (_, next) in run.drain(..) {
+                    class.union(&next);
+                }
+
+                merged.push(Hir::class(Class::Unicode(class)));
+            }
+        }
+    }
+
+    let mut merged: Vec<Hir> = Vec::with_capacity(subs.len());
+    let mut run = vec![];
+
+    for sub in subs {
+        match single_char_class(&sub) {
+            Some(class) => run.push((sub, class)),
+            None => {
+                flush(&mut run, &mut merged);
+                merged.push(sub);
+            }
+        }
+    }
+
+    flush(&mut run, &mut merged);
+    merged
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Side {
+    Prefix,
+    Suffix,
+}
+
+/// The sequence `hir` is a concatenation of, with literals split into
+/// their characters so literals can share a prefix or suffix
+fn atoms(hir: &Hir) -> Vec<Hir> {
+    let split = |hir: &Hir| match hir.kind() {
+        HirKind::Literal(literal) => match std::str::from_utf8(&literal.0) {
+            Ok(literal) => literal
+                .chars()
+                .map(|c| Hir::literal(c.to_string().into_bytes()))
+                .collect(),
+            Err(_) => literal.0.iter().map(|&b| Hir::literal([b])).collect(),
+        },
+        _ => vec![hir.clone()],
+    };
+
+    match hir.kind() {
+        HirKind::Empty => vec![],
+        HirKind::Concat(subs) => subs.iter().flat_map(split).collect(),
+        _ => split(hir),
+    }
+}
+
+/// Whether `atom` can be part of a factored out prefix. Prefixes that can
+/// match in more than one way can not be, since `p(?:x|y)` tries `y`
+/// after the first way of `p` while `px|py` tries every way with `x`
+/// first.
+fn is_single_way(atom: &Hir) -> bool {
+    matches!(
+        atom.kind(),
+        HirKind::Literal(_) | HirKind::Class(_) | HirKind::Look(_)
+    )
+}
+
+/// Length of the prefix or suffix of `atoms` shared with `common`
+fn shared(common: &[Hir], atoms: &[Hir], side: Side) -> usize {
+    match side {
+        Side::Prefix => common
+            .iter()
+            .zip(atoms)
+            .take_while(|(a, b)| a == b && is_single_way(a))
+            .count(),
+        Side::Suffix => common
+            .iter()
+            .rev()
+            .zip(atoms.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count(),
+    }
+}
+
+/// Runs of adjacent alternatives sharing a prefix or suffix as a single
+/// alternative, with the rest of each in an alternation
+fn factor(subs: Vec<Hir>, side: Side) -> Vec<Hir> {
+    let mut factored = Vec::with_capacity(subs.len());
+    let mut run: Vec<Vec<Hir>> = vec![];
+    let mut common = 0;
+
+    let mut flush = |run: &mut Vec<Vec<Hir>>, common: usize| {
+        match run.len() {
+            0 => (),
+            1 => factored.push(Hir::concat(run.pop().unwrap())),
+            _ => {
+                let mut rests = vec![];
+                let mut shared = vec![];
+
+                for mut atoms in run.drain(..) {
+                    let rest = match side {
+                        Side::Prefix => {
+                            let rest = atoms.split_off(common);
+                            shared = atoms;
+                            rest
+                        }
+                        Side::Suffix => {
+                            shared = atoms.split_off(atoms.len() - common);
+                            atoms
+                        }
+                    };
+
+                    rests.push(Hir::concat(rest));
+                }
+
+                let rest = alternation(rests);
+
+                factored.push(concat(match side {
+                    Side::Prefix => shared.into_iter().chain([rest]).collect(),
+                    Side::Suffix => [rest].into_iter().chain(shared).collect(),
+                }));
+            }
+        }
+    };
+
+    for sub in subs {
+        let atoms = atoms(&sub);
+        let len = match run.first() {
+            Some(first) if run.len() == 1 => shared(first, &atoms, side),
+            Some(first) => shared(first, &atoms, side).min(common),
+            None => 0,
+        };
+
+        if len == 0 {
+            flush(&mut run, common);
+        }
+
+        common = len;
+        run.push(atoms);
+    }
+
+    flush(&mut run, common);
+
+    factored
+}
+
+#[cfg(test)]
+mod tests {
+    use regex_syntax::Parser;
+
+    use super::simplify;
+
+    fn assert_simplifies(pattern: &str, expected: &str) {
+        let hir = Parser::new().parse(pattern).unwrap();
+        let expected = Parser::new().parse(expected).unwrap();
+
+        assert_eq!(simplify(&hir), simplify(&expected), "{pattern}");
+    }
+
+    #[test]
+    fn factored_alternations() {
+        assert_simplifies(r"a\.com|a\.net", r"a\.(?:com|net)");
+        assert_simplifies(r"x\.com|y\.com", r"[xy]\.com");
+        assert_simplifies(r"ab|ac|d|eb", r"a[bc]|d|eb");
+        assert_simplifies(r"foo|foobar", r"foo(?:|bar)");
+    }
+
+    #[test]
+    fn ambiguous_prefixes_stay() {
+        assert_simplifies(r"a+b|a+c", r"a+b|a+c");
+    }
+
+    #[test]
+    fn merged_classes_and_repetitions() {
+        assert_simplifies(r"[a-c]|[x-z]|b", r"[a-cx-z]");
+        assert_simplifies(r"x*x*", r"x*");
+        assert_simplifies(r"xx*", r"x+");
+        assert_simplifies(r"x{2,3}?x*?", r"x{2,}?");
+        assert_simplifies(r"x*x*?", r"x*x*?");
+    }
+
+    #[test]
+    fn groups_and_duplicates() {
+        assert_simplifies(r"((a))b", r"ab");
+        assert_simplifies(r"(?:ab|cd|ab)e", r"(?:ab|cd)e");
+    }
+}
@@ -1,12 +1,47 @@
+use std::fmt::Display;
+
 use regex::Error as RegexError;
 use regex_syntax::Error as RegexSyntaxError;
 
 #[derive(Debug)]
 pub enum CompileError {
     UnexpectedToken(usize, usize),
-    RegexSyntaxError(RegexSyntaxError),
+    RegexSyntaxError(Box<RegexSyntaxError>),
     RegexError(RegexError),
+    /// Syntax that parses but can not be compiled, such as classes
+    /// matching invalid UTF-8
+    Unsupported(String),
+    /// An option given after the pattern is unknown, has an invalid
+    /// value, or conflicts with another option
+    InvalidOption(String),
+    /// More than one pattern of a file is given the same name
+    DuplicateName(String),
+    /// Patterns of a set that failed to compile, by their index
+    Patterns(Vec<(usize, CompileError)>),
     TODO,
 }
 
+impl Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompileError::UnexpectedToken(start, end) => {
+                write!(f, "unexpected token at {start}..{end}")
+            }
+            CompileError::RegexSyntaxError(err) => write!(f, "{err}"),
+            CompileError::RegexError(err) => write!(f, "{err}"),
+            CompileError::Unsupported(message)
+            | CompileError::InvalidOption(message) => write!(f, "{message}"),
+            CompileError::DuplicateName(name) => {
+                write!(f, "`{name}` is defined more than once")
+            }
+            CompileError::Patterns(errors) => {
+                errors.iter().try_for_each(|(pattern, err)| {
+                    writeln!(f, "pattern {pattern}: {err}")
+                })
+            }
+            CompileError::TODO => write!(f, "unexpected input"),
+        }
+    }
+}
+
 pub type Result<T> = core::result::Result<T, CompileError>;
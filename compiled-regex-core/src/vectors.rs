@@ -0,0 +1,84 @@
+use crate::options::Options;
+use crate::types::Result;
+use crate::{compile_program, interpreter, parse_hir};
+
+/// Inputs a pattern is expected to match or not, given to
+/// `parse_regex!` as `matches = [...]` and `rejects = [...]`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TestVectors {
+    /// Inputs the pattern has to match somewhere
+    pub matches: Vec<String>,
+    /// Inputs the pattern must not match anywhere
+    pub rejects: Vec<String>,
+}
+
+impl TestVectors {
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty() && self.rejects.is_empty()
+    }
+
+    /// A message for every expectation that the type `name` of `pattern`
+    /// compiled with `options` does not meet.
+    ///
+    /// The inputs are matched by running the program the code is
+    /// generated from, so a wrong expectation is reported while the
+    /// macro expands instead of when the tests run.
+    pub fn failures(
+        &self,
+        name: &str,
+        pattern: &str,
+        options: &Options,
+    ) -> Result<Vec<String>> {
+        options.validate()?;
+
+        let program = compile_program(&parse_hir(pattern)?, options)?;
+
+        let matches = self
+            .matches
+            .iter()
+            .filter(|input| !interpreter::is_match(&program, input))
+            .map(|input| format!("`{name}` should match {input:?}"));
+        let rejects = self
+            .rejects
+            .iter()
+            .filter(|input| interpreter::is_match(&program, input))
+            .map(|input| format!("`{name}` should not match {input:?}"));
+
+        Ok(matches.chain(rejects).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TestVectors;
+    use crate::options::Options;
+
+    fn failures(pattern: &str, matches: &[&str], rejects: &[&str]) -> Vec<String> {
+        let vectors = TestVectors {
+            matches: matches.iter().map(|s| s.to_string()).collect(),
+            rejects: rejects.iter().map(|s| s.to_string()).collect(),
+        };
+
+        vectors.failures("R", pattern, &Options::new()).unwrap()
+    }
+
+    #[test]
+    fn met() {
+        let ipv4 = r"^(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)$";
+
+        assert!(failures(ipv4, &["10.0.0.1", "255.255.255.255"], &["256.1.1.1", "1.2.3"]).is_empty());
+        assert!(failures(r"\bfoo\b", &["a foo", "foo"], &["food", "afoo"]).is_empty());
+        assert!(failures("(?m)^b$", &["a\nb\nc"], &["ab"]).is_empty());
+        assert!(failures("x[0-9a-f]{4,}y", &["x12abcy"], &["x12y", "x12345"]).is_empty());
+        assert!(failures("foo|foobar|ba[rz]", &["bar", "xbaz", "foob"], &["fo", "bax"]).is_empty());
+        assert!(failures("(?:a*)*b", &["aaab"], &["aaaa"]).is_empty());
+    }
+
+    #[test]
+    fn unmet() {
+        assert_eq!(
+            failures("^a+$", &["aa", "ab"], &["a", "b"]),
+            ["`R` should match \"ab\"", "`R` should not match \"a\""]
+        );
+    }
+}
@@ -1,58 +1,130 @@
 #![allow(dead_code)]
 use compiled_regex_core::types::CompileError;
 use proc_macro::TokenStream;
-use proc_macro::{self, TokenTree};
+use proc_macro::{self, Delimiter, TokenTree};
 
-use sha2::{Sha256, Digest};
-use hex::encode;
+use compiled_regex_core::cache::{pattern_key, Cache};
+use compiled_regex_core::codegen::{
+    lexer_type, newtype_pattern, newtype_type, pattern_file_types,
+    regex_set_type, regex_type, test_vectors_fn, validate_type,
+    FieldPattern, Generics, Naming, TokenPatterns,
+};
+use compiled_regex_core::options::Options;
+use compiled_regex_core::pattern_file::{location, parse_pattern_file};
+use compiled_regex_core::vectors::TestVectors;
 
-use compiled_regex_core::{parse_regex as parse_regex_program, CHAR_GET_FUNC};
+use litrs::{IntegerLit, StringLit};
 
-use litrs::StringLit;
+/// The text of an option value, which is either an identifier such as
+/// `recursive`, an integer or a string literal
+fn option_value(token: TokenTree) -> Result<String, CompileError> {
+    match token {
+        TokenTree::Ident(x) => Ok(x.to_string()),
+        TokenTree::Literal(x) => {
+            if let Ok(s) = StringLit::try_from(&x) {
+                Ok(s.value().to_string())
+            } else if let Ok(i) = IntegerLit::try_from(&x) {
+                i.value::<usize>().map(|i| i.to_string()).ok_or_else(|| {
+                    CompileError::InvalidOption(format!("`{x}` is out of range"))
+                })
+            } else {
+                Err(CompileError::InvalidOption(format!("unexpected value `{x}`")))
+            }
+        }
+        x => Err(CompileError::InvalidOption(format!("unexpected value `{x}`"))),
+    }
+}
 
-fn parse_regex_string(
-    export_name: &str,
-    regex: &str,
-) -> Result<String, CompileError> {
-    let implementation = parse_regex_program(regex)?;
-    let body = implementation.to_string();
+/// The values of a `["...", ...]` list of string literals
+fn string_list(token: Option<TokenTree>) -> Result<Vec<String>, CompileError> {
+    let group = match token {
+        Some(TokenTree::Group(x)) if x.delimiter() == Delimiter::Bracket => x,
+        // TODO: Specifiy that a list of string literals is needed
+        _ => return Err(CompileError::TODO),
+    };
 
-    // keeping hash name to improve upon algorithm later
-    let mut hasher = Sha256::new();
+    let mut strings = vec![];
 
-    hasher.update(regex);
-    hasher.update(export_name);
-    hasher.update(&implementation.name);
-    hasher.update(&body);
+    for token in group.stream() {
+        match token {
+            TokenTree::Literal(x) => match StringLit::try_from(x) {
+                Ok(s) => strings.push(s.value().to_string()),
+                // TODO: Specify illegal literal type usage
+                Err(_) => return Err(CompileError::TODO),
+            },
+            TokenTree::Punct(x) if x.as_char() == ',' => (),
+            // TODO: Specifiy that str literals are needed
+            _ => return Err(CompileError::TODO),
+        }
+    }
 
-    let result = hasher.finalize();
-    let mut struct_name = encode(result);
-    struct_name.insert(0, 'S');
+    Ok(strings)
+}
 
-    let code = format!("
-struct __{struct_name}();
-#[allow(unused_variables)]
-#[allow(nonstandard_style)]
-impl __{struct_name} {{
-    {0}
-    {1}
+/// Options following the pattern as `, key = value` pairs, and the
+/// test vectors given as `, matches = [...]` and `, rejects = [...]`
+fn parse_options(
+    mut iter: impl Iterator<Item = TokenTree>,
+) -> Result<(Options, TestVectors), CompileError> {
+    let mut options = Options::new();
+    let mut vectors = TestVectors::default();
 
-    #[allow(dead_code)]
-    fn is_match(input: &str) -> bool {{
-        Self::{2}(input, &mut 0)
-    }}
-}}
-type {export_name} = __{struct_name};",
-    CHAR_GET_FUNC.replace("\n", "\n    "),
-    body.replace("\n", "\n    "),
-    implementation.name);
+    loop {
+        match iter.next() {
+            Some(TokenTree::Punct(x)) if x.as_char() == ',' => (),
+            None => return Ok((options, vectors)),
+            Some(x) => {
+                return Err(CompileError::InvalidOption(format!(
+                    "expected `,` before `{x}`"
+                )))
+            }
+        }
+
+        // Allow a trailing comma
+        let key = match iter.next() {
+            Some(TokenTree::Ident(x)) => x.to_string(),
+            None => return Ok((options, vectors)),
+            Some(x) => {
+                return Err(CompileError::InvalidOption(format!(
+                    "expected an option name, found `{x}`"
+                )))
+            }
+        };
 
-    Ok(code)
+        if !matches!(iter.next(), Some(TokenTree::Punct(x)) if x.as_char() == '=') {
+            return Err(CompileError::InvalidOption(format!(
+                "expected `=` after `{key}`"
+            )));
+        }
+
+        match key.as_str() {
+            "matches" => {
+                vectors.matches.extend(string_list(iter.next())?);
+                continue;
+            }
+            "rejects" => {
+                vectors.rejects.extend(string_list(iter.next())?);
+                continue;
+            }
+            _ => (),
+        }
+
+        let value = match iter.next() {
+            Some(x) => option_value(x)?,
+            None => {
+                return Err(CompileError::InvalidOption(format!(
+                    "expected a value for `{key}`"
+                )))
+            }
+        };
+
+        options.set(&key, &value)?;
+    }
 }
 
 fn parse_token_stream(
     tokens: TokenStream,
-) -> Result<(String, String), CompileError> {
+) -> Result<(String, String, Options, TestVectors), CompileError> {
     if tokens.is_empty() {
         // TODO: Should return error describing empty token set
         return Err(CompileError::TODO);
@@ -65,7 +137,7 @@ fn parse_token_stream(
         Some(TokenTree::Ident(x)) => x.to_string(),
         Some(TokenTree::Literal(x)) => {
             if let Ok(s) = StringLit::try_from(x) {
-                s.to_string()
+                s.value().to_string()
             } else {
                 // TODO: Specify illegal literal type usage
                 return Err(CompileError::TODO);
@@ -88,8 +160,9 @@ fn parse_token_stream(
     // Get the regex string literal
     let regex = match iter.next() {
         Some(TokenTree::Literal(x)) => {
+            // The value has escapes resolved and string marks stripped
             if let Ok(s) = StringLit::try_from(x) {
-                s.to_string()
+                s.value().to_string()
             } else {
                 // TODO: Specify illegal literal type usage
                 return Err(CompileError::TODO);
@@ -99,26 +172,873 @@ fn parse_token_stream(
         _ => return Err(CompileError::TODO),
     };
 
-    // Strip surrounding string marks from RegEx literal
-    let regex = regex.as_str()
-        [regex.find('"').unwrap() + 1..regex.rfind('"').unwrap()]
-        .to_string();
+    let (options, vectors) = parse_options(iter)?;
+
+    Ok((name, regex, options, vectors))
+}
+
+/// `vis newtype Name<'a>` before the `=` of `parse_regex!`
+struct Newtype {
+    vis: String,
+    /// The lifetime of a newtype borrowing its string
+    lifetime: Option<String>,
+}
+
+/// The newtype the tokens start with, if any, and the tokens of a
+/// `parse_regex!` without it
+fn parse_newtype(
+    tokens: TokenStream,
+) -> Result<(Option<Newtype>, TokenStream), CompileError> {
+    let mut iter = tokens.clone().into_iter().peekable();
+    let vis = parse_visibility(&mut iter);
+
+    // `newtype` can name a pattern too, but is not followed by a name then
+    match (iter.next(), iter.peek()) {
+        (Some(TokenTree::Ident(x)), Some(TokenTree::Ident(_))) if x.to_string() == "newtype" => (),
+        _ if vis.is_empty() => return Ok((None, tokens)),
+        _ => return Err(input_error("expected `newtype` after the visibility")),
+    }
+
+    let name = iter.next();
+    let lifetime = match parse_generic_params(&mut iter).as_slice() {
+        [] => None,
+        [param] => Some(param.iter().cloned().collect::<TokenStream>().to_string()),
+        _ => return Err(input_error("newtypes only take the lifetime of the string")),
+    };
+
+    let rest = name.into_iter().chain(iter).collect();
+
+    Ok((Some(Newtype { vis, lifetime }), rest))
+}
+
+/// Where the patterns of a set come from
+enum SetSource {
+    /// `["pattern", ...]`
+    Literals(Vec<String>),
+    /// `file "path"`, relative to the manifest of the crate using the
+    /// macro
+    File(String),
+}
+
+/// `Name = ["pattern", ...]` or `Name = file "path"` followed by
+/// options, like `parse_token_stream`
+fn parse_set_token_stream(
+    tokens: TokenStream,
+) -> Result<(String, SetSource, Options), CompileError> {
+    let mut iter = tokens.into_iter();
+
+    let name = match iter.next() {
+        Some(TokenTree::Ident(x)) => x.to_string(),
+        // TODO: Specifiy that identity is needed
+        _ => return Err(CompileError::TODO),
+    };
+
+    if !matches!(iter.next(), Some(TokenTree::Punct(_))) {
+        // TODO: Specify that some punctuation is needed
+        return Err(CompileError::TODO);
+    }
+
+    let source = match iter.next() {
+        Some(x @ TokenTree::Group(_)) => SetSource::Literals(string_list(Some(x))?),
+        Some(TokenTree::Ident(x)) if x.to_string() == "file" => {
+            match iter.next() {
+                Some(TokenTree::Literal(x)) => match StringLit::try_from(x) {
+                    Ok(s) => SetSource::File(s.value().to_string()),
+                    // TODO: Specify illegal literal type usage
+                    Err(_) => return Err(CompileError::TODO),
+                },
+                // TODO: Specifiy that a path literal is needed
+                _ => return Err(CompileError::TODO),
+            }
+        }
+        // TODO: Specifiy that a list of patterns is needed
+        _ => return Err(CompileError::TODO),
+    };
+
+    let (options, vectors) = parse_options(iter)?;
+
+    if !vectors.is_empty() {
+        return Err(CompileError::InvalidOption(String::from(
+            "sets do not take test vectors",
+        )));
+    }
+
+    Ok((name, source, options))
+}
+
+/// A variant of the enum given to `lexer!`
+struct Variant {
+    name: String,
+    /// Attributes other than those of the lexer, such as doc comments
+    attributes: Vec<String>,
+    patterns: Vec<String>,
+    is_error: bool,
+}
+
+/// The enum given to `lexer!`
+struct TokenEnum {
+    attributes: Vec<String>,
+    vis: String,
+    name: String,
+    variants: Vec<Variant>,
+}
+
+impl TokenEnum {
+    /// The enum without the attributes of the lexer
+    fn declaration(&self) -> String {
+        let variants = self
+            .variants
+            .iter()
+            .map(|variant| format!("{}\n{},", variant.attributes.concat(), variant.name))
+            .collect::<String>();
+
+        format!(
+            "{}\n{}enum {} {{\n{variants}\n}}",
+            self.attributes.concat(),
+            self.vis,
+            self.name,
+        )
+    }
+}
+
+/// Error in the item given to a macro
+fn input_error(message: &str) -> CompileError {
+    CompileError::InvalidOption(message.to_string())
+}
+
+/// Attributes up to the next token that is not one, as the `[...]`
+/// groups following `#`
+fn parse_attributes(
+    iter: &mut std::iter::Peekable<impl Iterator<Item = TokenTree>>,
+) -> Vec<proc_macro::Group> {
+    let mut attributes = vec![];
+
+    while matches!(iter.peek(), Some(TokenTree::Punct(x)) if x.as_char() == '#') {
+        iter.next();
+
+        match iter.next() {
+            Some(TokenTree::Group(x)) if x.delimiter() == Delimiter::Bracket => {
+                attributes.push(x)
+            }
+            _ => break,
+        }
+    }
+
+    attributes
+}
+
+/// `pub`, `pub(...)` followed by a space, or nothing
+fn parse_visibility(
+    iter: &mut std::iter::Peekable<impl Iterator<Item = TokenTree>>,
+) -> String {
+    let mut vis = String::new();
+
+    if matches!(iter.peek(), Some(TokenTree::Ident(x)) if x.to_string() == "pub") {
+        vis.push_str("pub");
+        iter.next();
+
+        if let Some(TokenTree::Group(x)) = iter.peek() {
+            if x.delimiter() == Delimiter::Parenthesis {
+                vis.push_str(&x.to_string());
+                iter.next();
+            }
+        }
+
+        vis.push(' ');
+    }
+
+    vis
+}
+
+/// `#[regex("...")] Variant` and `#[error] Variant`, separated by commas
+fn parse_variant(
+    iter: &mut std::iter::Peekable<impl Iterator<Item = TokenTree>>,
+) -> Result<Option<Variant>, CompileError> {
+    let mut variant = Variant {
+        name: String::new(),
+        attributes: vec![],
+        patterns: vec![],
+        is_error: false,
+    };
+
+    for attribute in parse_attributes(iter) {
+        let mut tokens = attribute.stream().into_iter();
+
+        match tokens.next() {
+            Some(TokenTree::Ident(x)) if x.to_string() == "regex" => {
+                let pattern = match tokens.next() {
+                    Some(TokenTree::Group(x)) if x.delimiter() == Delimiter::Parenthesis => {
+                        x.stream().into_iter().next()
+                    }
+                    _ => None,
+                };
+
+                match pattern.map(StringLit::try_from) {
+                    Some(Ok(s)) => variant.patterns.push(s.value().to_string()),
+                    _ => return Err(input_error("expected `#[regex(\"pattern\")]`")),
+                }
+            }
+            Some(TokenTree::Ident(x)) if x.to_string() == "error" => {
+                variant.is_error = true;
+            }
+            _ => variant.attributes.push(format!("#{attribute}")),
+        }
+    }
+
+    variant.name = match iter.next() {
+        Some(TokenTree::Ident(x)) => x.to_string(),
+        None if variant.attributes.is_empty() && variant.patterns.is_empty() => {
+            return Ok(None)
+        }
+        _ => return Err(input_error("expected the name of a variant")),
+    };
+
+    match iter.next() {
+        Some(TokenTree::Punct(x)) if x.as_char() == ',' => (),
+        None => (),
+        Some(_) => {
+            return Err(input_error(&format!(
+                "`{}` has to be a variant without fields",
+                variant.name
+            )))
+        }
+    }
+
+    Ok(Some(variant))
+}
+
+/// `#[attributes] vis enum Name { variants }`
+fn parse_lexer_token_stream(tokens: TokenStream) -> Result<TokenEnum, CompileError> {
+    let mut iter = tokens.into_iter().peekable();
+
+    let attributes = parse_attributes(&mut iter)
+        .into_iter()
+        .map(|attribute| format!("#{attribute}"))
+        .collect();
+
+    let vis = parse_visibility(&mut iter);
+
+    if !matches!(iter.next(), Some(TokenTree::Ident(x)) if x.to_string() == "enum") {
+        return Err(input_error("expected an enum"));
+    }
+
+    let name = match iter.next() {
+        Some(TokenTree::Ident(x)) => x.to_string(),
+        _ => return Err(input_error("expected the name of the enum")),
+    };
+
+    let body = match iter.next() {
+        Some(TokenTree::Group(x)) if x.delimiter() == Delimiter::Brace => x,
+        _ => return Err(input_error("expected the variants of the enum")),
+    };
+
+    let mut body = body.stream().into_iter().peekable();
+    let mut variants = vec![];
+
+    while let Some(variant) = parse_variant(&mut body)? {
+        variants.push(variant);
+    }
+
+    Ok(TokenEnum { attributes, vis, name, variants })
+}
+
+/// The struct a derive is given, as the parts it needs
+struct DeriveInput {
+    attributes: Vec<proc_macro::Group>,
+    name: String,
+    /// Generic parameters without defaults
+    params: String,
+    /// Names of the generic parameters
+    args: String,
+    where_clause: String,
+    /// The fields in braces or parentheses, if any
+    fields: Option<proc_macro::Group>,
+}
+
+impl DeriveInput {
+    fn generics(&self) -> Generics<'_> {
+        Generics {
+            params: &self.params,
+            args: &self.args,
+            where_clause: &self.where_clause,
+        }
+    }
+
+    /// The arguments of the attribute `name`, the `...` of `#[name(...)]`
+    fn attribute(&self, name: &str) -> Option<TokenStream> {
+        self.attributes.iter().find_map(|attribute| {
+            let mut tokens = attribute.stream().into_iter();
+
+            match (tokens.next(), tokens.next()) {
+                (Some(TokenTree::Ident(x)), Some(TokenTree::Group(args)))
+                    if x.to_string() == name
+                        && args.delimiter() == Delimiter::Parenthesis =>
+                {
+                    Some(args.stream())
+                }
+                _ => None,
+            }
+        })
+    }
+}
+
+fn is_punct(token: &TokenTree, c: char) -> bool {
+    matches!(token, TokenTree::Punct(x) if x.as_char() == c)
+}
+
+/// Parameters of `<...>`, split at the commas between them
+fn parse_generic_params(
+    iter: &mut std::iter::Peekable<impl Iterator<Item = TokenTree>>,
+) -> Vec<Vec<TokenTree>> {
+    let mut params = vec![];
+
+    if !iter.peek().is_some_and(|token| is_punct(token, '<')) {
+        return params;
+    }
+
+    iter.next();
+
+    let mut param = vec![];
+    let mut depth = 0;
+    let mut previous: Option<TokenTree> = None;
+
+    for token in iter.by_ref() {
+        // The `>` of `->` does not close a bracket
+        let is_arrow = previous.as_ref().is_some_and(|x| is_punct(x, '-'));
+
+        if is_punct(&token, '<') {
+            depth += 1;
+        } else if is_punct(&token, '>') && !is_arrow {
+            if depth == 0 {
+                break;
+            }
+
+            depth -= 1;
+        } else if is_punct(&token, ',') && depth == 0 {
+            params.push(std::mem::take(&mut param));
+            previous = Some(token);
+            continue;
+        }
+
+        previous = Some(token.clone());
+        param.push(token);
+    }
+
+    if !param.is_empty() {
+        params.push(param);
+    }
+
+    params
+}
+
+/// Index of the `=` of the default of a generic parameter, or its length
+/// without one. An `=` inside angle brackets binds an associated type,
+/// as in `I: Iterator<Item = u8>`.
+fn default_start(param: &[TokenTree]) -> usize {
+    let mut depth = 0;
+    let mut previous: Option<&TokenTree> = None;
+
+    for (i, token) in param.iter().enumerate() {
+        // The `>` of `->` does not close a bracket
+        let is_arrow = previous.is_some_and(|x| is_punct(x, '-'));
+
+        if is_punct(token, '<') {
+            depth += 1;
+        } else if is_punct(token, '>') && !is_arrow {
+            depth -= 1;
+        } else if is_punct(token, '=') && depth == 0 {
+            return i;
+        }
+
+        previous = Some(token);
+    }
+
+    param.len()
+}
+
+/// `#[attributes] vis struct Name<params> ...`, of which the body is
+/// skipped
+fn parse_derive_input(tokens: TokenStream) -> Result<DeriveInput, CompileError> {
+    let mut iter = tokens.into_iter().peekable();
+
+    let attributes = parse_attributes(&mut iter);
+    parse_visibility(&mut iter);
+
+    if !matches!(iter.next(), Some(TokenTree::Ident(x)) if x.to_string() == "struct") {
+        return Err(input_error("expected a struct"));
+    }
+
+    let name = match iter.next() {
+        Some(TokenTree::Ident(x)) => x.to_string(),
+        _ => return Err(input_error("expected the name of the struct")),
+    };
+
+    let mut params = vec![];
+    let mut args = vec![];
+
+    for param in parse_generic_params(&mut iter) {
+        // Defaults are only allowed where the type is declared
+        let param = &param[..default_start(&param)];
+
+        let arg = match param {
+            [lifetime @ TokenTree::Punct(_), TokenTree::Ident(x), ..] if is_punct(lifetime, '\'') => {
+                format!("'{x}")
+            }
+            [TokenTree::Ident(x), TokenTree::Ident(name), ..] if x.to_string() == "const" => {
+                name.to_string()
+            }
+            [TokenTree::Ident(x), ..] => x.to_string(),
+            _ => return Err(input_error("unexpected generic parameter")),
+        };
+
+        params.push(param.iter().cloned().collect::<TokenStream>().to_string());
+        args.push(arg);
+    }
+
+    // The where clause is either before the fields in braces, or after
+    // the fields in parentheses
+    let mut where_clause = vec![];
+    let mut in_where = false;
+    let mut fields = None;
+
+    for token in iter {
+        match &token {
+            TokenTree::Ident(x) if x.to_string() == "where" => in_where = true,
+            TokenTree::Group(x) if x.delimiter() == Delimiter::Brace => {
+                fields = Some(x.clone());
+                break;
+            }
+            TokenTree::Group(x) if x.delimiter() == Delimiter::Parenthesis && !in_where => {
+                fields = Some(x.clone());
+                continue;
+            }
+            TokenTree::Punct(x) if x.as_char() == ';' => break,
+            _ => (),
+        }
 
-    Ok((name, regex))
+        if in_where {
+            where_clause.push(token);
+        }
+    }
+
+    Ok(DeriveInput {
+        attributes,
+        name,
+        params: params.join(", "),
+        args: args.join(", "),
+        where_clause: where_clause.into_iter().collect::<TokenStream>().to_string(),
+        fields,
+    })
+}
+
+/// The fields of a struct with their attributes, named by their index
+/// in a tuple struct
+fn parse_fields(fields: &proc_macro::Group) -> Vec<(String, Vec<proc_macro::Group>)> {
+    let is_tuple = fields.delimiter() == Delimiter::Parenthesis;
+    let mut iter = fields.stream().into_iter().peekable();
+    let mut parsed = vec![];
+
+    while iter.peek().is_some() {
+        let attributes = parse_attributes(&mut iter);
+        parse_visibility(&mut iter);
+
+        let name = match is_tuple {
+            true => parsed.len().to_string(),
+            false => iter.next().map(|name| name.to_string()).unwrap_or_default(),
+        };
+
+        // Skip the type, whose commas are inside angle brackets
+        let mut depth = 0;
+        let mut previous: Option<TokenTree> = None;
+
+        for token in iter.by_ref() {
+            let is_arrow = previous.as_ref().is_some_and(|x| is_punct(x, '-'));
+
+            if is_punct(&token, '<') {
+                depth += 1;
+            } else if is_punct(&token, '>') && !is_arrow {
+                depth -= 1;
+            } else if is_punct(&token, ',') && depth == 0 {
+                break;
+            }
+
+            previous = Some(token);
+        }
+
+        parsed.push((name, attributes));
+    }
+
+    parsed
+}
+
+/// The code `generate` returns, or the code it returned when a macro was
+/// last expanded with the same `key` and the same parsed `patterns`,
+/// skipping compilation entirely
+fn cached<'a, E>(
+    key: &[&str],
+    patterns: impl IntoIterator<Item = &'a str>,
+    generate: impl FnOnce() -> Result<String, E>,
+) -> Result<String, E> {
+    let Some(cache) = Cache::from_env() else {
+        return generate();
+    };
+
+    let parsed = patterns.into_iter().map(pattern_key).collect::<Vec<_>>();
+    let key = [key, &parsed.iter().map(String::as_str).collect::<Vec<_>>()].concat();
+
+    cache.get_or_insert_with(&key, generate)
+}
+
+/// `compile_error!` for every message
+fn compile_errors(messages: impl Iterator<Item = String>) -> String {
+    messages
+        .map(|message| format!("compile_error!({message:?});"))
+        .collect()
+}
+
+/// A set of the patterns in the file at `path` named `name`, and a type
+/// for every named line of the file
+fn parse_pattern_file_string(
+    name: &str,
+    path: &str,
+    options: &Options,
+) -> String {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let full_path = std::path::Path::new(&manifest_dir).join(path);
+
+    let text = match std::fs::read_to_string(&full_path) {
+        Ok(text) => text,
+        Err(err) => {
+            return compile_errors(std::iter::once(format!(
+                "{path}: could not read pattern file: {err}"
+            )))
+        }
+    };
+
+    let lines = parse_pattern_file(&text);
+
+    // The text is part of the key, so an edited file is compiled again
+    let key = ["file", name, &text, &format!("{options:?}")];
+    let patterns = lines.iter().map(|line| line.pattern.as_str());
+    let code = cached(&key, patterns, || {
+        pattern_file_types(name, &lines, options, Naming::Hashed)
+    });
+
+    match code {
+        // Including the file makes cargo rebuild when it changes
+        Ok(code) => format!(
+            "const _: &[u8] = include_bytes!({:?});\n{code}",
+            full_path.display().to_string()
+        ),
+        Err(errors) => compile_errors(errors.into_iter().map(|(line, err)| {
+            format!("{}: {err}", location(path, line))
+        })),
+    }
 }
 
 #[proc_macro]
 pub fn parse_regex(tokens: TokenStream) -> TokenStream {
     // Parse the tokens into a name and a RegEx literal
     // TODO: CompileError report
-    let (name, regex) = parse_token_stream(tokens).unwrap();
+    let (newtype, tokens) = parse_newtype(tokens).unwrap();
+    let (name, regex, options, vectors) = parse_token_stream(tokens).unwrap();
     // format!(r###"println!("{{}}", r##"{:?}"##)"###, (name, regex)).parse().unwrap()
 
-    // Parse the RegEx into actual code
-    let code = parse_regex_string(&name, &regex).unwrap();
+    // Parse the RegEx into actual code, unless it is cached
+    let (code, matched) = match &newtype {
+        Some(newtype) => {
+            let lifetime = newtype.lifetime.as_deref();
+            let key = [
+                "newtype",
+                &name,
+                &newtype.vis,
+                lifetime.unwrap_or_default(),
+                &regex,
+                &format!("{options:?}"),
+            ];
+            let code = cached(&key, [regex.as_str()], || {
+                newtype_type(&name, &newtype.vis, lifetime, &regex, &options)
+            });
+
+            (code, newtype_pattern(&regex))
+        }
+        None => {
+            let key = ["regex", &name, &regex, &format!("{options:?}")];
+            let code = cached(&key, [regex.as_str()], || {
+                regex_type(&name, &regex, &options, Naming::Hashed)
+            });
+
+            (code, Ok(regex))
+        }
+    };
+    let mut code = code.unwrap();
+    let matched = matched.unwrap();
+
+    // The test vectors are checked on every expansion, as they are not
+    // part of the key
+    if !vectors.is_empty() {
+        let failures = vectors.failures(&name, &matched, &options).unwrap();
+
+        code += &compile_errors(failures.into_iter());
+        code += &test_vectors_fn(&name, &matched, &vectors);
+    }
 
     // Parse the code into Rust tokens
-    return code.parse().unwrap();
+    code.parse().unwrap()
+}
+
+#[proc_macro]
+pub fn parse_regex_set(tokens: TokenStream) -> TokenStream {
+    // Parse the tokens into a name and the source of the RegEx literals
+    // TODO: CompileError report
+    let (name, source, options) = parse_set_token_stream(tokens).unwrap();
+
+    // Parse the RegEx set into actual code, reporting the patterns
+    // that failed to compile
+    let code = match source {
+        SetSource::Literals(patterns) => {
+            let patterns = patterns.iter().map(String::as_str).collect::<Vec<_>>();
+            let options_key = format!("{options:?}");
+            let key = [&["set", &name, &options_key], &patterns[..]].concat();
+            let code = cached(&key, patterns.iter().copied(), || {
+                regex_set_type(&name, &patterns, &options, Naming::Hashed)
+            });
+
+            match code {
+                Ok(code) => code,
+                Err(CompileError::Patterns(errors)) => {
+                    compile_errors(errors.into_iter().map(|(i, err)| {
+                        format!("pattern {i} of `{name}`: {err}")
+                    }))
+                }
+                Err(err) => compile_errors(std::iter::once(err.to_string())),
+            }
+        }
+        SetSource::File(path) => parse_pattern_file_string(&name, &path, &options),
+    };
+
+    // Parse the code into Rust tokens
+    code.parse().unwrap()
+}
+
+/// An enum of tokens and a `lexer` function splitting an input into
+/// them, like
+///
+/// ```ignore
+/// lexer! {
+///     enum Token {
+///         #[regex("[a-zA-Z_][a-zA-Z0-9_]*")]
+///         Ident,
+///         #[regex("[0-9]+")]
+///         Int,
+///         #[error]
+///         Error,
+///     }
+/// }
+/// ```
+#[proc_macro]
+pub fn lexer(tokens: TokenStream) -> TokenStream {
+    // TODO: CompileError report
+    let token_enum = parse_lexer_token_stream(tokens).unwrap();
+
+    let mut errors = token_enum.variants.iter().filter(|variant| variant.is_error);
+    let error = match (errors.next(), errors.next()) {
+        (Some(error), None) if error.patterns.is_empty() => Some(&error.name),
+        _ => None,
+    };
+
+    let Some(error) = error else {
+        return compile_errors(std::iter::once(format!(
+            "`{}` needs exactly one `#[error]` variant without patterns",
+            token_enum.name
+        )))
+        .parse()
+        .unwrap();
+    };
+
+    let tokens = token_enum
+        .variants
+        .iter()
+        .map(|variant| TokenPatterns {
+            variant: &variant.name,
+            patterns: variant.patterns.iter().map(String::as_str).collect(),
+        })
+        .collect::<Vec<_>>();
+
+    // The variants are part of the key, as the code names them
+    let variants = tokens
+        .iter()
+        .map(|token| format!("{} {:?}", token.variant, token.patterns))
+        .collect::<Vec<_>>();
+    let mut key = vec!["lexer", &token_enum.name, &token_enum.vis, error];
+    key.extend(variants.iter().map(String::as_str));
+
+    let patterns = tokens.iter().flat_map(|token| token.patterns.iter().copied());
+    let code = cached(&key, patterns, || {
+        lexer_type(&token_enum.name, &token_enum.vis, &tokens, error)
+    });
+
+    // Errors name the variant of the pattern rather than its index
+    let variant_of = |pattern: usize| {
+        let mut seen = 0;
+
+        tokens.iter().find_map(|token| {
+            seen += token.patterns.len();
+            (pattern < seen).then_some(token.variant)
+        })
+    };
+
+    let code = match code {
+        Ok(code) => format!("{}\n{code}", token_enum.declaration()),
+        Err(CompileError::Patterns(errors)) => {
+            compile_errors(errors.into_iter().map(|(i, err)| {
+                format!("pattern of `{}::{}`: {err}", token_enum.name, variant_of(i).unwrap_or_default())
+            }))
+        }
+        Err(err) => compile_errors(std::iter::once(err.to_string())),
+    };
+
+    code.parse().unwrap()
+}
+
+/// `is_match` and `find` of the pattern in the `#[regex("...")]`
+/// attribute, which takes the same options as `parse_regex!`, as
+/// methods of the struct
+#[proc_macro_derive(Regex, attributes(regex))]
+pub fn derive_regex(tokens: TokenStream) -> TokenStream {
+    // TODO: CompileError report
+    let input = parse_derive_input(tokens).unwrap();
+
+    let Some(arguments) = input.attribute("regex") else {
+        return compile_errors(std::iter::once(format!(
+            "`{}` needs a `#[regex(\"pattern\")]` attribute",
+            input.name
+        )))
+        .parse()
+        .unwrap();
+    };
+
+    let mut iter = arguments.into_iter();
+    let regex = match iter.next().map(StringLit::try_from) {
+        Some(Ok(s)) => s.value().to_string(),
+        // TODO: Specifiy that str literal is needed
+        _ => return compile_errors(std::iter::once(String::from(
+            "expected `#[regex(\"pattern\")]`"
+        )))
+        .parse()
+        .unwrap(),
+    };
+    let (options, vectors) = parse_options(iter).unwrap();
+
+    let key = [
+        "derive",
+        &input.name,
+        &input.params,
+        &input.where_clause,
+        &regex,
+        &format!("{options:?}"),
+    ];
+    let code = cached(&key, [regex.as_str()], || {
+        regex_type(&input.name, &regex, &options, Naming::Derived(input.generics()))
+    });
+
+    let mut code = match code {
+        Ok(code) => code,
+        // The vectors of a pattern that does not compile can not be
+        // checked either
+        Err(err) => {
+            return compile_errors(std::iter::once(err.to_string()))
+                .parse()
+                .unwrap()
+        }
+    };
+
+    if !vectors.is_empty() {
+        let failures = vectors.failures(&input.name, &regex, &options).unwrap();
+
+        code += &compile_errors(failures.into_iter());
+
+        // The test can only name a type without parameters
+        if input.args.is_empty() {
+            code += &test_vectors_fn(&input.name, &regex, &vectors);
+        }
+    }
+
+    code.parse().unwrap()
+}
+
+/// `validate`, checking that the string of every field with a
+/// `#[pattern("...")]` attribute matches the pattern. The attribute takes
+/// the same options as `parse_regex!`, and a field can have several.
+#[proc_macro_derive(ValidatePatterns, attributes(pattern))]
+pub fn derive_validate_patterns(tokens: TokenStream) -> TokenStream {
+    // TODO: CompileError report
+    let input = parse_derive_input(tokens).unwrap();
+    let fields = input.fields.as_ref().map(parse_fields).unwrap_or_default();
+
+    let mut patterns = vec![];
+
+    for (field, attributes) in &fields {
+        for attribute in attributes {
+            let mut tokens = attribute.stream().into_iter();
+
+            match tokens.next() {
+                Some(TokenTree::Ident(x)) if x.to_string() == "pattern" => (),
+                _ => continue,
+            }
+
+            let arguments = match tokens.next() {
+                Some(TokenTree::Group(x)) if x.delimiter() == Delimiter::Parenthesis => x,
+                _ => return compile_errors(std::iter::once(format!(
+                    "expected `#[pattern(\"pattern\")]` on `{field}`"
+                )))
+                .parse()
+                .unwrap(),
+            };
+
+            let mut iter = arguments.stream().into_iter();
+            let pattern = match iter.next().map(StringLit::try_from) {
+                Some(Ok(s)) => s.value().to_string(),
+                _ => return compile_errors(std::iter::once(format!(
+                    "expected `#[pattern(\"pattern\")]` on `{field}`"
+                )))
+                .parse()
+                .unwrap(),
+            };
+            let (options, vectors) = parse_options(iter).unwrap();
+
+            if !vectors.is_empty() {
+                return compile_errors(std::iter::once(format!(
+                    "the pattern of `{field}` does not take test vectors"
+                )))
+                .parse()
+                .unwrap();
+            }
+
+            patterns.push((field, pattern, options));
+        }
+    }
+
+    let field_patterns = patterns
+        .iter()
+        .map(|(field, pattern, options)| FieldPattern {
+            field,
+            pattern,
+            options,
+        })
+        .collect::<Vec<_>>();
+
+    let code = match validate_type(&input.name, input.generics(), &field_patterns) {
+        Ok(code) => code,
+        Err(CompileError::Patterns(errors)) => {
+            compile_errors(errors.into_iter().map(|(i, err)| {
+                format!("pattern of `{}`: {err}", field_patterns[i].field)
+            }))
+        }
+        Err(err) => compile_errors(std::iter::once(err.to_string())),
+    };
+
+    code.parse().unwrap()
 }
 
 #[proc_macro]
@@ -127,13 +1047,13 @@ pub fn __parse_regex_generative_output(
 ) -> TokenStream {
     // Parse the tokens into a name and a RegEx literal
     // TODO: CompileError report
-    let (name, regex) = parse_token_stream(tokens).unwrap();
+    let (name, regex, options, _) = parse_token_stream(tokens).unwrap();
 
     // Parse the RegEx into actual code
-    let code = parse_regex_string(&name, &regex).unwrap();
+    let code = regex_type(&name, &regex, &options, Naming::Hashed).unwrap();
 
     // Parse the code into Rust tokens
-    return format!(r###"println!("{{}}", r##"{}"##)"###, code)
+    format!(r###"println!("{{}}", r##"{}"##)"###, code)
         .parse()
-        .unwrap();
+        .unwrap()
 }
@@ -0,0 +1,9 @@
+use compiled_regex_core::build::Builder;
+
+fn main() {
+    Builder::new()
+        .regex("Email", r"^[^@\s]+@[^@\s]+\.[a-z]{2,}$")
+        .set("Extensions", [r"\.exe$", r"\.zip$", r"\.tar\.gz$"])
+        .write("regexes.rs")
+        .unwrap();
+}
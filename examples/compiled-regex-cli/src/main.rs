@@ -7,10 +7,16 @@ use regex::Regex;
 
 regex!(Reg = "a{1,3}b");
 
+// Types written by build.rs
+include!(concat!(env!("OUT_DIR"), "/regexes.rs"));
+
 fn main() {
     // parse_regex_output!(MyRegex = "a{1,3}b");
     let r = Regex::new("a{1,3}b").unwrap();
 
     println!("{:?}", Reg::is_match("aaaab"));
     println!("{:?}", r.is_match("aaaab"));
+
+    println!("{:?}", Email::is_match("someone@example.com"));
+    println!("{:?}", Extensions::first_match("archive.tar.gz"));
 }
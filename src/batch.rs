@@ -0,0 +1,104 @@
+//! The result of `is_match_many`, which is only generated when the
+//! `alloc` feature of this crate is enabled.
+
+#[cfg(feature = "alloc")]
+use alloc::vec;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Which of many inputs a pattern matched, a bit per input by its
+/// index, as returned by the `is_match_many` of a generated type.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MatchBits {
+    words: Vec<u64>,
+    len: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl MatchBits {
+    /// No matches for `len` inputs
+    #[doc(hidden)]
+    pub fn new(len: usize) -> Self {
+        MatchBits {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn insert(&mut self, input: usize) {
+        self.words[input / 64] |= 1 << (input % 64);
+    }
+
+    /// Whether the input at index `input` matched
+    ///
+    /// # Panics
+    ///
+    /// When `input` is not smaller than [`MatchBits::len`]
+    #[inline]
+    pub fn get(&self, input: usize) -> bool {
+        assert!(input < self.len, "input {input} out of {} inputs", self.len);
+
+        self.words[input / 64] & (1 << (input % 64)) != 0
+    }
+
+    /// Number of inputs, matched or not
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of inputs that matched
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Whether each input matched, in the order of the inputs
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(|input| self.get(input))
+    }
+
+    /// Indices of the inputs that matched, in ascending order
+    pub fn matched(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+
+            core::iter::from_fn(move || {
+                let bit = word.trailing_zeros() as usize;
+
+                (word != 0).then(|| {
+                    word &= word - 1;
+                    i * 64 + bit
+                })
+            })
+        })
+    }
+
+    /// The bits, the input at index `i` being bit `i % 64` of word
+    /// `i / 64`, with the bits past the last input unset
+    pub fn as_words(&self) -> &[u64] {
+        &self.words
+    }
+}
+
+/// Items of a generated type that allocate
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __with_alloc {
+    ($($items:tt)*) => {
+        $($items)*
+    };
+}
+
+#[cfg(not(feature = "alloc"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __with_alloc {
+    ($($items:tt)*) => {};
+}
@@ -0,0 +1,12 @@
+/// Whether `regex::Regex` finds `pattern` in `input`, or `None` when the
+/// `regex` feature is disabled and tests of test vectors can not compare
+/// the generated code to it
+#[cfg(feature = "regex")]
+pub fn regex_is_match(pattern: &str, input: &str) -> Option<bool> {
+    Some(regex::Regex::new(pattern).ok()?.is_match(input))
+}
+
+#[cfg(not(feature = "regex"))]
+pub fn regex_is_match(_pattern: &str, _input: &str) -> Option<bool> {
+    None
+}
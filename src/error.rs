@@ -0,0 +1,81 @@
+use core::fmt;
+
+/// Error of the `try_is_match` and `try_find` of types generated with a
+/// `stack_limit`, when a match needed more backtracking frames than the
+/// limit allows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StackExhausted;
+
+impl fmt::Display for StackExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "backtracking stack limit exceeded")
+    }
+}
+
+impl core::error::Error for StackExhausted {}
+
+/// Error of converting a string that does not match the pattern of a
+/// newtype generated with `parse_regex!(newtype ...)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidFormat {
+    name: &'static str,
+    pattern: &'static str,
+}
+
+impl InvalidFormat {
+    #[doc(hidden)]
+    pub fn new(name: &'static str, pattern: &'static str) -> Self {
+        InvalidFormat { name, pattern }
+    }
+
+    /// Name of the newtype
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Pattern the whole string has to match
+    pub fn pattern(&self) -> &'static str {
+        self.pattern
+    }
+}
+
+impl fmt::Display for InvalidFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "not a valid `{}`, which has to match `{}`", self.name, self.pattern)
+    }
+}
+
+impl core::error::Error for InvalidFormat {}
+
+/// A field whose value does not match one of its patterns, as returned
+/// by the `validate` of a struct deriving `ValidatePatterns`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldError {
+    field: &'static str,
+    pattern: &'static str,
+}
+
+impl FieldError {
+    #[doc(hidden)]
+    pub fn new(field: &'static str, pattern: &'static str) -> Self {
+        FieldError { field, pattern }
+    }
+
+    /// Name of the field, or its index in a tuple struct
+    pub fn field(&self) -> &'static str {
+        self.field
+    }
+
+    /// Pattern the value does not match
+    pub fn pattern(&self) -> &'static str {
+        self.pattern
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` does not match `{}`", self.field, self.pattern)
+    }
+}
+
+impl core::error::Error for FieldError {}
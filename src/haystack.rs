@@ -0,0 +1,341 @@
+use core::iter::Copied;
+use core::ops::Range;
+use core::slice;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::SparseSet;
+
+/// Text that is read one byte or character at a time, as the
+/// `is_match_in` and `find_in` of a type generated with `chunked = true`
+/// do, so text kept in pieces does not have to be copied into a single
+/// `str` first.
+///
+/// The bytes read have to be UTF-8, characters may be split between
+/// pieces. Offsets of matches are counted from the first byte read.
+pub trait Haystack {
+    /// The next byte, moving past it
+    fn next_byte(&mut self) -> Option<u8>;
+
+    /// The next character, moving past its bytes. Decodes the bytes of
+    /// `next_byte` unless overridden.
+    fn next_char(&mut self) -> Option<char> {
+        decode(self)
+    }
+}
+
+/// The character of the UTF-8 bytes `haystack` reads next
+fn decode<H: Haystack + ?Sized>(haystack: &mut H) -> Option<char> {
+    let first = haystack.next_byte()?;
+    let width = match first {
+        0x00..=0x7F => return Some(char::from(first)),
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    };
+    let mut c = u32::from(first) & (0x7F >> width);
+
+    for _ in 1..width {
+        c = (c << 6) | (u32::from(haystack.next_byte()?) & 0x3F);
+    }
+
+    Some(char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER))
+}
+
+/// The text of `str` pieces one after the other, such as the chunks of a
+/// rope or a `&[&str]`.
+pub struct Segments<'a, I> {
+    segments: I,
+    segment: &'a str,
+    /// Offset of the next byte in `segment`
+    at: usize,
+}
+
+impl<'a, I: Iterator<Item = &'a str>> Segments<'a, I> {
+    pub fn new(segments: impl IntoIterator<IntoIter = I>) -> Self {
+        Segments {
+            segments: segments.into_iter(),
+            segment: "",
+            at: 0,
+        }
+    }
+
+    /// The segment being read, empty at the end of all segments
+    fn segment(&mut self) -> &'a str {
+        while self.at == self.segment.len() {
+            match self.segments.next() {
+                Some(segment) => {
+                    self.segment = segment;
+                    self.at = 0;
+                }
+                None => break,
+            }
+        }
+
+        self.segment
+    }
+}
+
+impl<'a, I: Iterator<Item = &'a str>> Haystack for Segments<'a, I> {
+    fn next_byte(&mut self) -> Option<u8> {
+        let byte = *self.segment().as_bytes().get(self.at)?;
+
+        self.at += 1;
+        Some(byte)
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        // The rest of a character some of whose bytes were read is not a
+        // `str`
+        let Some(rest) = self.segment().get(self.at..) else {
+            return decode(self);
+        };
+        let c = rest.chars().next()?;
+
+        self.at += c.len_utf8();
+        Some(c)
+    }
+}
+
+impl<'a, 's> From<&'s [&'a str]> for Segments<'a, Copied<slice::Iter<'s, &'a str>>> {
+    fn from(segments: &'s [&'a str]) -> Self {
+        Segments::new(segments.iter().copied())
+    }
+}
+
+/// The states of a pattern as threads of a Pike VM, generated for
+/// `stream = true`, `chunked = true` and `reverse = true`
+#[doc(hidden)]
+pub struct Automaton {
+    pub states: usize,
+    pub start: usize,
+    pub is_anchored_start: bool,
+    pub follow: fn(&str, usize, usize, &mut SparseSet, &mut Vec<usize>),
+    pub step: fn(usize, char) -> Option<usize>,
+    pub accepts: fn(usize) -> Option<usize>,
+}
+
+impl Automaton {
+    /// Leftmost-first match in `haystack`
+    pub fn find<H: Haystack>(&self, mut haystack: H) -> Option<Range<usize>> {
+        self.find_chars(None, core::iter::from_fn(|| haystack.next_char()))
+    }
+
+    /// Leftmost-first match in `chars`, which follow `prev`
+    fn find_chars(
+        &self,
+        mut prev: Option<char>,
+        mut chars: impl Iterator<Item = char>,
+    ) -> Option<Range<usize>> {
+        let mut threads = Threads::new(self.states);
+        let mut candidate = None;
+        let mut at = 0;
+
+        loop {
+            let c = chars.next();
+            let may_start = candidate.is_none() && !(self.is_anchored_start && at > 0);
+
+            if let Some(start) = threads.step(self, at, prev, c, may_start, true) {
+                candidate = Some(start..at);
+            }
+
+            let Some(c) = c else {
+                return candidate;
+            };
+
+            if threads.is_empty() && (candidate.is_some() || self.is_anchored_start) {
+                return candidate;
+            }
+
+            at += c.len_utf8();
+            prev = Some(c);
+        }
+    }
+
+    /// End of the longest match at the start of `chars`, which follow
+    /// `prev`
+    fn longest(
+        &self,
+        mut prev: Option<char>,
+        mut chars: impl Iterator<Item = char>,
+    ) -> Option<usize> {
+        let mut threads = Threads::new(self.states);
+        let mut longest = None;
+        let mut at = 0;
+
+        loop {
+            let c = chars.next();
+
+            // Threads of lower priority may still match a longer text
+            if threads.step(self, at, prev, c, at == 0, false).is_some() {
+                longest = Some(at);
+            }
+
+            match c {
+                Some(c) if !threads.is_empty() => {
+                    at += c.len_utf8();
+                    prev = Some(c);
+                }
+                _ => return longest,
+            }
+        }
+    }
+
+    /// The match of `input` that ends last, and of the matches ending
+    /// there the one starting first. `self` is the automaton of the
+    /// reversed pattern and `suffix` the text every match ends with.
+    ///
+    /// The end is found with the suffix, searched for from the end of
+    /// `input` with `memchr`, or otherwise by the leftmost-first match
+    /// of the reversed pattern in the reversed input. The start is then
+    /// found by reading backwards from the end.
+    pub fn rfind(&self, input: &str, suffix: Option<&str>) -> Option<Range<usize>> {
+        let Some(suffix) = suffix else {
+            let end = input.len() - self.find_chars(None, input.chars().rev())?.start;
+
+            return self.find_ending_at(input, end);
+        };
+
+        let mut limit = input.len();
+
+        while let Some(at) = memchr::memmem::rfind(&input.as_bytes()[..limit], suffix.as_bytes()) {
+            let end = at + suffix.len();
+
+            if let Some(found) = self.find_ending_at(input, end) {
+                return Some(found);
+            }
+
+            // The next occurrence has to end before this one
+            limit = end - 1;
+        }
+
+        None
+    }
+
+    /// The match of a pattern that can only match at the end of `input`,
+    /// which is the one starting first. `self` is the automaton of the
+    /// reversed pattern and `suffix` the text every match ends with.
+    pub fn find_at_end(&self, input: &str, suffix: Option<&str>) -> Option<Range<usize>> {
+        if suffix.is_some_and(|suffix| !input.ends_with(suffix)) {
+            return None;
+        }
+
+        self.find_ending_at(input, input.len())
+    }
+
+    /// The match ending at `end` that starts first
+    fn find_ending_at(&self, input: &str, end: usize) -> Option<Range<usize>> {
+        let after = input[end..].chars().next();
+        let len = self.longest(after, input[..end].chars().rev())?;
+
+        Some(end - len..end)
+    }
+}
+
+/// The threads of a search with an `Automaton`, which consumes a
+/// character at a time
+pub(crate) struct Threads {
+    current: SparseSet,
+    stack: Vec<usize>,
+    /// Where the thread at every state started
+    starts: Vec<usize>,
+    /// States reached by consuming the previous character, with the
+    /// start of their threads, which are followed once the next
+    /// character is known
+    pending: Vec<(usize, usize)>,
+    next: Vec<(usize, usize)>,
+}
+
+impl Threads {
+    pub(crate) fn new(states: usize) -> Self {
+        Threads {
+            current: SparseSet::new(states),
+            stack: Vec::new(),
+            starts: vec![0; states],
+            pending: Vec::new(),
+            next: Vec::new(),
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Whether no thread is left to run
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Start of the earliest thread
+    pub(crate) fn earliest(&self) -> Option<usize> {
+        self.pending.iter().map(|&(_, start)| start).min()
+    }
+
+    /// Run the threads at `at`, between `prev` and `c`, starting a new
+    /// one there if `may_start`. The start of the thread of highest
+    /// priority that matched at `at`, whose lower priority threads are
+    /// dropped if `leftmost_first`.
+    pub(crate) fn step(
+        &mut self,
+        automaton: &Automaton,
+        at: usize,
+        prev: Option<char>,
+        c: Option<char>,
+        may_start: bool,
+        leftmost_first: bool,
+    ) -> Option<usize> {
+        // Assertions only look at the characters around a position
+        let mut window = [0; 8];
+        let before = prev.map_or(0, |prev| prev.encode_utf8(&mut window).len());
+        let after = c.map_or(0, |c| c.encode_utf8(&mut window[before..]).len());
+        let window = core::str::from_utf8(&window[..before + after]).unwrap();
+
+        self.current.clear();
+
+        for i in 0..self.pending.len() {
+            let (pc, start) = self.pending[i];
+
+            self.follow(automaton, window, before, pc, start);
+        }
+
+        // New threads have a lower priority than the running ones
+        if may_start {
+            self.follow(automaton, window, before, automaton.start, at);
+        }
+
+        let mut matched = None;
+        self.next.clear();
+
+        for &pc in self.current.as_slice() {
+            // Every later thread has a lower priority
+            if (automaton.accepts)(pc).is_some() {
+                matched = matched.or(Some(self.starts[pc]));
+
+                if leftmost_first {
+                    break;
+                }
+            }
+
+            if let Some(goto) = c.and_then(|c| (automaton.step)(pc, c)) {
+                self.next.push((goto, self.starts[pc]));
+            }
+        }
+
+        core::mem::swap(&mut self.pending, &mut self.next);
+
+        matched
+    }
+
+    /// Add the states following `pc` to the current ones, as part of the
+    /// thread that started at `start`
+    fn follow(&mut self, automaton: &Automaton, window: &str, at: usize, pc: usize, start: usize) {
+        let len = self.current.as_slice().len();
+
+        (automaton.follow)(window, at, pc, &mut self.current, &mut self.stack);
+
+        for &pc in &self.current.as_slice()[len..] {
+            self.starts[pc] = start;
+        }
+    }
+}
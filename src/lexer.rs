@@ -0,0 +1,62 @@
+use core::ops::Range;
+
+use crate::SetCache;
+
+/// Range of the bytes of a token in the input
+pub type Span = Range<usize>;
+
+/// The tokens of an input and their spans, as returned by the `lexer`
+/// function of an enum declared with `lexer!`.
+///
+/// Every character is part of a token, characters that no pattern
+/// matches becoming error tokens of one character each.
+pub struct Lexer<'a, T> {
+    input: &'a str,
+    at: usize,
+    cache: SetCache,
+    token: fn(Option<usize>) -> T,
+    longest: fn(&str, usize, &mut SetCache) -> Option<(usize, usize)>,
+}
+
+impl<'a, T> Lexer<'a, T> {
+    /// Lexer of `input` with `states` states, whose `longest` match at a
+    /// position is that of the pattern `token` makes a variant of
+    #[doc(hidden)]
+    pub fn new(
+        input: &'a str,
+        states: usize,
+        token: fn(Option<usize>) -> T,
+        longest: fn(&str, usize, &mut SetCache) -> Option<(usize, usize)>,
+    ) -> Self {
+        Lexer {
+            input,
+            at: 0,
+            cache: SetCache::new(states),
+            token,
+            longest,
+        }
+    }
+
+    /// The part of the input that has not been tokenized yet
+    pub fn remainder(&self) -> &'a str {
+        &self.input[self.at..]
+    }
+}
+
+impl<'a, T> Iterator for Lexer<'a, T> {
+    type Item = (T, Span);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.at;
+        let c = self.input[start..].chars().next()?;
+
+        let (pattern, end) = match (self.longest)(self.input, start, &mut self.cache) {
+            Some((pattern, end)) => (Some(pattern), end),
+            None => (None, start + c.len_utf8()),
+        };
+
+        self.at = end;
+
+        Some(((self.token)(pattern), start..end))
+    }
+}
@@ -1,4 +1,117 @@
+//! Types of the matchers generated from patterns at compile time.
+//!
+//! The crate is `no_std`, and the generated code only needs `core` with
+//! `engine = recursive`, `engine = structured` or a `stack_limit`. The
+//! `alloc` feature, enabled by default, is needed by everything that
+//! allocates: the default backtracking stack, the loops of
+//! `engine = structured` repeating more than a sequence of characters,
+//! like `(?:ab|c)+`, the `bounded` engine, sets, lexers,
+//! `chunked = true`, `is_match_many`, newtypes owning a `String` and
+//! `ValidatePatterns`.
+//! The `std` feature, also enabled by default, is needed by
+//! `stream = true`, which reads from an `std::io::Read`, and by the
+//! `options` and `types` of the proc macro, which are re-exported from
+//! `compiled-regex-core`. Without it the crate only depends on `core`
+//! and `memchr` at run time.
+//!
+//! The tests generated for the `matches = [...]` and `rejects = [...]`
+//! of a pattern compare the generated code to `regex::Regex` when the
+//! `regex` feature is enabled, which a crate only needs for its tests:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! compiled-regex = { version = "0.1", features = ["regex"] }
+//! ```
+#![no_std]
 #![allow(dead_code)]
-pub use compiled_regex_core::types;
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+mod batch;
+mod cross_check;
+mod error;
+#[cfg(feature = "alloc")]
+mod haystack;
+#[cfg(feature = "alloc")]
+mod lexer;
+mod matches;
+mod newtype;
+#[cfg(feature = "alloc")]
+mod set;
+#[cfg(feature = "std")]
+mod stream;
+mod validate;
+#[cfg(feature = "alloc")]
+mod visited;
+
+/// The allocated types the generated code uses, which are not in the
+/// prelude of a `no_std` crate
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub mod __alloc {
+    pub use alloc::string::String;
+    pub use alloc::vec::Vec;
+}
+
+/// Fails the build without the `alloc` feature, for generated code that
+/// only allocates for some patterns
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __requires_alloc {
+    ($what:literal) => {};
+}
+
+#[cfg(not(feature = "alloc"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __requires_alloc {
+    ($what:literal) => {
+        ::core::compile_error!(::core::concat!($what, " needs the `alloc` feature of `compiled_regex`"));
+    };
+}
+
+/// The thread locals the generated code uses with the `std` feature
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod __std {
+    pub use std::thread_local;
+}
+
+#[cfg(feature = "alloc")]
+pub use batch::MatchBits;
+#[cfg(feature = "std")]
+pub use compiled_regex_core::{options, types};
 pub use compiled_regex_macro::__parse_regex_generative_output as parse_regex_output;
-pub use compiled_regex_macro::parse_regex;
+pub use compiled_regex_macro::{
+    lexer, parse_regex, parse_regex_set, Regex, ValidatePatterns,
+};
+#[doc(hidden)]
+pub use cross_check::regex_is_match;
+pub use error::{FieldError, InvalidFormat, StackExhausted};
+#[cfg(feature = "alloc")]
+pub use haystack::{Haystack, Segments};
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub use haystack::Automaton;
+#[cfg(feature = "alloc")]
+pub use lexer::{Lexer, Span};
+pub use matches::Match;
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub use newtype::serde;
+#[cfg(feature = "alloc")]
+pub use set::{SetCache, SetMatches};
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub use set::{SetMode, SparseSet};
+#[cfg(feature = "std")]
+pub use stream::{StreamFind, StreamMatch};
+#[doc(hidden)]
+pub use validate::PatternField;
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub use visited::Visited;
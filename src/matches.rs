@@ -0,0 +1,74 @@
+use core::ops::Range;
+
+/// A single match of a pattern in a haystack, as returned by the `find`
+/// of a generated type.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Match<'h> {
+    haystack: &'h str,
+    start: usize,
+    end: usize,
+}
+
+impl<'h> Match<'h> {
+    /// Used by the generated code, `start..end` has to lie on `char`
+    /// boundaries of `haystack`
+    #[doc(hidden)]
+    #[inline]
+    pub const fn new(haystack: &'h str, start: usize, end: usize) -> Self {
+        Match {
+            haystack,
+            start,
+            end,
+        }
+    }
+
+    /// Byte offset of the start of the match in the haystack
+    #[inline]
+    pub const fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Byte offset of the end of the match in the haystack
+    #[inline]
+    pub const fn end(&self) -> usize {
+        self.end
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Length of the match in bytes
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// The matched part of the haystack
+    #[inline]
+    pub fn as_str(&self) -> &'h str {
+        &self.haystack[self.range()]
+    }
+}
+
+impl core::fmt::Debug for Match<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Match")
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .field("string", &self.as_str())
+            .finish()
+    }
+}
+
+impl<'h> From<Match<'h>> for &'h str {
+    fn from(m: Match<'h>) -> &'h str {
+        m.as_str()
+    }
+}
@@ -0,0 +1,62 @@
+//! Implementations of `Serialize` and `Deserialize` for the newtypes
+//! generated with `parse_regex!(newtype ...)`, which are only expanded
+//! when the `serde` feature of this crate is enabled.
+
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub use serde;
+
+/// A deserialized string is validated like any other
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __newtype_serde {
+    ($name:ident) => {
+        impl $crate::serde::Serialize for $name {
+            fn serialize<S: $crate::serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.0)
+            }
+        }
+
+        impl<'de> $crate::serde::Deserialize<'de> for $name {
+            fn deserialize<D: $crate::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                let input = <$crate::__alloc::String as $crate::serde::Deserialize>::deserialize(deserializer)?;
+
+                Self::try_from(input).map_err(<D::Error as $crate::serde::de::Error>::custom)
+            }
+        }
+    };
+    ($name:ident<$lifetime:lifetime>) => {
+        impl<$lifetime> $crate::serde::Serialize for $name<$lifetime> {
+            fn serialize<S: $crate::serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.0)
+            }
+        }
+
+        // Only strings without escapes can be borrowed from the input
+        impl<'de: $lifetime, $lifetime> $crate::serde::Deserialize<'de> for $name<$lifetime> {
+            fn deserialize<D: $crate::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                let input = <&'de str as $crate::serde::Deserialize>::deserialize(deserializer)?;
+
+                Self::try_from(input).map_err(<D::Error as $crate::serde::de::Error>::custom)
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "serde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __newtype_serde {
+    ($($tokens:tt)*) => {};
+}
@@ -0,0 +1,194 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The patterns of a set that matched somewhere in a haystack, as
+/// returned by the `matches` of a type generated with
+/// `parse_regex_set!`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SetMatches {
+    matched: Vec<bool>,
+    count: usize,
+}
+
+impl SetMatches {
+    /// No matches for a set of `len` patterns
+    #[doc(hidden)]
+    pub fn new(len: usize) -> Self {
+        SetMatches {
+            matched: vec![false; len],
+            count: 0,
+        }
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn insert(&mut self, pattern: usize) {
+        if !self.matched[pattern] {
+            self.matched[pattern] = true;
+            self.count += 1;
+        }
+    }
+
+    /// Whether any pattern matched
+    pub fn matched_any(&self) -> bool {
+        self.count > 0
+    }
+
+    /// Whether every pattern matched
+    pub fn matched_all(&self) -> bool {
+        self.count == self.matched.len()
+    }
+
+    /// Whether the pattern at index `pattern` matched
+    ///
+    /// # Panics
+    ///
+    /// When `pattern` is not smaller than [`SetMatches::len`]
+    pub fn matched(&self, pattern: usize) -> bool {
+        self.matched[pattern]
+    }
+
+    /// Number of patterns in the set, matched or not
+    pub fn len(&self) -> usize {
+        self.matched.len()
+    }
+
+    /// Whether the set has no patterns
+    pub fn is_empty(&self) -> bool {
+        self.matched.is_empty()
+    }
+
+    /// Indices of the patterns that matched, in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.matched
+            .iter()
+            .enumerate()
+            .filter_map(|(pattern, &matched)| matched.then_some(pattern))
+    }
+}
+
+/// What the search of a set stops at
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetMode {
+    /// The first match found, for `is_match`
+    Earliest,
+    /// The match `regex::Regex::find` would report for the alternation
+    /// of all patterns
+    LeftmostFirst,
+    /// Every pattern that matches
+    All,
+}
+
+/// Set of states with insertion order, constant time inserts and
+/// clearing
+#[doc(hidden)]
+pub struct SparseSet {
+    dense: Vec<usize>,
+    sparse: Vec<usize>,
+}
+
+impl SparseSet {
+    /// Empty set of values smaller than `capacity`
+    pub fn new(capacity: usize) -> Self {
+        SparseSet {
+            dense: Vec::with_capacity(capacity),
+            sparse: vec![0; capacity],
+        }
+    }
+
+    /// Add `value`, `false` if it already was in the set
+    #[inline]
+    pub fn insert(&mut self, value: usize) -> bool {
+        let index = self.sparse[value];
+
+        if index < self.dense.len() && self.dense[index] == value {
+            return false;
+        }
+
+        self.sparse[value] = self.dense.len();
+        self.dense.push(value);
+        true
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.dense.clear();
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    /// The values in the order they were inserted
+    #[inline]
+    pub fn as_slice(&self) -> &[usize] {
+        &self.dense
+    }
+}
+
+/// Memory used by the search of a set, which can be reused between
+/// searches, as created by the `create_cache` of a type generated with
+/// `parse_regex_set!` and taken by its `is_match_with`, `matches_with`
+/// and `first_match_with`. A cache can be shared by sets, it grows to
+/// fit the largest.
+///
+/// The methods without a cache keep one per thread with the `std`
+/// feature, and create one for every search without it.
+pub struct SetCache {
+    #[doc(hidden)]
+    pub current: SparseSet,
+    #[doc(hidden)]
+    pub next: SparseSet,
+    #[doc(hidden)]
+    pub stack: Vec<usize>,
+}
+
+impl SetCache {
+    /// Cache for a set with `states` states
+    #[doc(hidden)]
+    pub fn new(states: usize) -> Self {
+        SetCache {
+            current: SparseSet::new(states),
+            next: SparseSet::new(states),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Make room for a set with `states` states, for a cache created by
+    /// another set
+    #[doc(hidden)]
+    #[inline]
+    pub fn ensure(&mut self, states: usize) {
+        if self.current.sparse.len() < states {
+            self.current = SparseSet::new(states);
+            self.next = SparseSet::new(states);
+        }
+    }
+}
+
+/// Run `$search` with the cache of a set of `$states` states, which is
+/// kept for the next search of the thread
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __with_set_cache {
+    ($states:expr, $search:expr) => {{
+        $crate::__std::thread_local! {
+            static CACHE: ::core::cell::RefCell<$crate::SetCache> =
+                ::core::cell::RefCell::new($crate::SetCache::new($states));
+        }
+
+        CACHE.with(|cache| ($search)(&mut *cache.borrow_mut()))
+    }};
+}
+
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __with_set_cache {
+    ($states:expr, $search:expr) => {
+        ($search)(&mut $crate::SetCache::new($states))
+    };
+}
@@ -0,0 +1,277 @@
+use core::ops::Range;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use std::io::{self, Read};
+
+use crate::haystack::{Automaton, Threads};
+
+/// Bytes read from the reader at once
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// A match found by `stream_find`, which owns its text since the text
+/// it was read from is gone.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StreamMatch {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+impl StreamMatch {
+    /// Byte offset of the start of the match from the start of the
+    /// stream
+    #[inline]
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Byte offset of the end of the match from the start of the stream
+    #[inline]
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Length of the match in bytes
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// The matched text
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+impl From<StreamMatch> for String {
+    fn from(m: StreamMatch) -> String {
+        m.text
+    }
+}
+
+/// Every match of a pattern in the text of a reader, as returned by the
+/// `stream_find` of a type generated with `stream = true`.
+///
+/// Matches are the ones `find` would report one after the other on the
+/// whole text, but the text is read in chunks and every character is
+/// looked at once by the threads of a Pike VM. Only the text from the
+/// start of the earliest thread is kept, which is the text a match could
+/// still need.
+///
+/// Text that is not UTF-8 is an error of kind
+/// [`io::ErrorKind::InvalidData`], after which the iterator ends, as it
+/// does after any error of the reader.
+pub struct StreamFind<R> {
+    reader: R,
+    automaton: Automaton,
+    /// Text from `base` on in the first `len` bytes, the part before it
+    /// was dropped
+    buffer: Vec<u8>,
+    base: usize,
+    len: usize,
+    is_eof: bool,
+    is_done: bool,
+    /// Offset of the next character, and the character before it
+    at: usize,
+    prev: Option<char>,
+    /// End of the last match, a match can not be empty right after it
+    last_end: Option<usize>,
+    threads: Threads,
+}
+
+/// The best match found so far, and the character before its end
+#[derive(Clone, Copy)]
+struct Candidate {
+    start: usize,
+    end: usize,
+    prev: Option<char>,
+}
+
+impl<R: Read> StreamFind<R> {
+    #[doc(hidden)]
+    pub fn new(reader: R, automaton: Automaton) -> Self {
+        let threads = Threads::new(automaton.states);
+
+        StreamFind {
+            reader,
+            automaton,
+            buffer: Vec::new(),
+            base: 0,
+            len: 0,
+            is_eof: false,
+            is_done: false,
+            at: 0,
+            prev: None,
+            last_end: None,
+            threads,
+        }
+    }
+
+    /// The character at `at`, reading more text when the buffer ends
+    /// before it. Text before `keep` is no longer needed.
+    fn char_at(&mut self, at: usize, keep: usize) -> io::Result<Option<char>> {
+        loop {
+            let bytes = &self.buffer[at - self.base..self.len];
+
+            if let Some(&first) = bytes.first() {
+                let width = utf8_width(first).ok_or_else(invalid_utf8)?;
+
+                if let Some(bytes) = bytes.get(..width) {
+                    let c = core::str::from_utf8(bytes).map_err(|_| invalid_utf8())?;
+
+                    return Ok(c.chars().next());
+                }
+            }
+
+            if self.is_eof {
+                return match bytes.is_empty() {
+                    true => Ok(None),
+                    false => Err(invalid_utf8()),
+                };
+            }
+
+            self.fill(keep)?;
+        }
+    }
+
+    /// Drop the text before `keep` and read the next chunk
+    fn fill(&mut self, keep: usize) -> io::Result<()> {
+        self.buffer.copy_within(keep - self.base..self.len, 0);
+        self.len -= keep - self.base;
+        self.base = keep;
+
+        // Readers giving out little at a time are read into the space
+        // left by the last chunk
+        if self.buffer.len() - self.len < CHUNK_SIZE / 2 {
+            self.buffer.resize(self.len + CHUNK_SIZE, 0);
+        }
+
+        let read = loop {
+            match self.reader.read(&mut self.buffer[self.len..]) {
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                result => break result?,
+            }
+        };
+
+        self.len += read;
+        self.is_eof = read == 0;
+
+        Ok(())
+    }
+
+    /// The leftmost-first match from `self.at` on
+    fn search(&mut self) -> io::Result<Option<Candidate>> {
+        let is_anchored_start = self.automaton.is_anchored_start;
+        let mut candidate: Option<Candidate> = None;
+
+        self.threads.clear();
+
+        loop {
+            // Text from the start of the earliest thread is kept for the
+            // text of the match
+            let keep = candidate
+                .map(|candidate| candidate.start)
+                .into_iter()
+                .chain(self.threads.earliest())
+                .fold(self.at, usize::min);
+            let c = self.char_at(self.at, keep)?;
+            // New threads are only started until the first match
+            let may_start = candidate.is_none() && !(is_anchored_start && self.at > 0);
+
+            if let Some(start) = self.threads.step(&self.automaton, self.at, self.prev, c, may_start, true) {
+                candidate = Some(Candidate {
+                    start,
+                    end: self.at,
+                    prev: self.prev,
+                });
+            }
+
+            let Some(c) = c else {
+                return Ok(candidate);
+            };
+
+            if self.threads.is_empty() && (candidate.is_some() || is_anchored_start) {
+                return Ok(candidate);
+            }
+
+            self.at += c.len_utf8();
+            self.prev = Some(c);
+        }
+    }
+
+    fn next_match(&mut self) -> io::Result<Option<StreamMatch>> {
+        loop {
+            let Some(candidate) = self.search()? else {
+                return Ok(None);
+            };
+
+            self.at = candidate.end;
+            self.prev = candidate.prev;
+
+            // An empty match right after the last one is skipped, as
+            // `regex::Regex::find_iter` does
+            if candidate.start == candidate.end && self.last_end == Some(candidate.end) {
+                let Some(c) = self.char_at(self.at, self.at)? else {
+                    return Ok(None);
+                };
+
+                self.at += c.len_utf8();
+                self.prev = Some(c);
+                continue;
+            }
+
+            self.last_end = Some(candidate.end);
+
+            let text = &self.buffer[candidate.start - self.base..candidate.end - self.base];
+
+            return Ok(Some(StreamMatch {
+                start: candidate.start,
+                end: candidate.end,
+                // The characters were decoded when they were matched
+                text: String::from(core::str::from_utf8(text).unwrap()),
+            }));
+        }
+    }
+}
+
+impl<R: Read> Iterator for StreamFind<R> {
+    type Item = io::Result<StreamMatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+
+        let result = self.next_match().transpose();
+        self.is_done = !matches!(result, Some(Ok(_)));
+
+        result
+    }
+}
+
+/// Bytes of the UTF-8 character starting with `first`
+fn utf8_width(first: u8) -> Option<usize> {
+    match first {
+        0x00..=0x7F => Some(1),
+        0xC2..=0xDF => Some(2),
+        0xE0..=0xEF => Some(3),
+        0xF0..=0xF4 => Some(4),
+        _ => None,
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8")
+}
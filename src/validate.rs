@@ -0,0 +1,49 @@
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, boxed::Box, string::String};
+
+/// The string of a field with a `#[pattern("...")]` attribute, or
+/// `None` when there is no value to check
+#[doc(hidden)]
+pub trait PatternField {
+    fn pattern_input(&self) -> Option<&str>;
+}
+
+impl PatternField for str {
+    fn pattern_input(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PatternField for String {
+    fn pattern_input(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PatternField for Box<str> {
+    fn pattern_input(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PatternField for Cow<'_, str> {
+    fn pattern_input(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl<T: PatternField + ?Sized> PatternField for &T {
+    fn pattern_input(&self) -> Option<&str> {
+        (**self).pattern_input()
+    }
+}
+
+/// Optional fields are valid when they are `None`
+impl<T: PatternField> PatternField for Option<T> {
+    fn pattern_input(&self) -> Option<&str> {
+        self.as_ref()?.pattern_input()
+    }
+}
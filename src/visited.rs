@@ -0,0 +1,68 @@
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The (instruction, position) pairs already explored by a type
+/// generated with `engine = bounded`.
+///
+/// A bitset with a bit per pair while it fits in the capacity, and an
+/// ordered set of only the explored pairs for longer inputs, which keeps
+/// the memory used proportional to the pairs explored at the cost of
+/// speed.
+#[doc(hidden)]
+pub enum Visited {
+    Dense { bits: Vec<u64>, stride: usize },
+    Sparse(BTreeSet<(usize, usize)>),
+}
+
+impl Visited {
+    /// Empty set for a program of `insts` instructions matched against
+    /// an input of `len` bytes, using at most `capacity` bytes for the
+    /// bitset
+    pub fn new(insts: usize, len: usize, capacity: usize) -> Self {
+        let stride = len + 1;
+
+        match insts.checked_mul(stride) {
+            Some(pairs) if pairs.div_ceil(8) <= capacity => Visited::Dense {
+                bits: vec![0; pairs.div_ceil(64)],
+                stride,
+            },
+            _ => Visited::Sparse(BTreeSet::new()),
+        }
+    }
+
+    /// Empty the set to be used like `Visited::new` with the same
+    /// arguments, reusing the memory of the bitset when it is large
+    /// enough
+    pub fn reset(&mut self, insts: usize, len: usize, capacity: usize) {
+        let stride = len + 1;
+        let pairs = insts.checked_mul(stride).filter(|pairs| pairs.div_ceil(8) <= capacity);
+
+        match (self, pairs) {
+            (Visited::Dense { bits, stride: old }, Some(pairs)) => {
+                bits.clear();
+                bits.resize(pairs.div_ceil(64), 0);
+                *old = stride;
+            }
+            (Visited::Sparse(pairs), None) => pairs.clear(),
+            (visited, _) => *visited = Visited::new(insts, len, capacity),
+        }
+    }
+
+    /// Mark the pair as visited, `false` if it already was
+    #[inline]
+    pub fn insert(&mut self, inst: usize, at: usize) -> bool {
+        match self {
+            Visited::Dense { bits, stride } => {
+                let pair = inst * *stride + at;
+                let word = &mut bits[pair / 64];
+                let bit = 1 << (pair % 64);
+                let is_new = *word & bit == 0;
+
+                *word |= bit;
+                is_new
+            }
+            Visited::Sparse(pairs) => pairs.insert((inst, at)),
+        }
+    }
+}
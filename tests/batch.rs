@@ -0,0 +1,65 @@
+use compiled_regex::parse_regex;
+
+// Rules of EasyList, which are matched against many short URL parts
+const RULES: &str = include_str!("../data/easylist_url_regex.txt");
+
+parse_regex!(Banner = r"-ad-\d+x\d+[-.]");
+parse_regex!(Prefixed = r"^/ads?[-_/]", engine = bounded);
+parse_regex!(Exact = r"^[a-z0-9_]{4,8}=$", engine = structured);
+parse_regex!(Words = r"\bpop(?:up|under)s?\b", engine = recursive);
+parse_regex!(Empty = "x*");
+
+fn lines() -> Vec<&'static str> {
+    RULES.lines().collect()
+}
+
+macro_rules! assert_same {
+    ($name:ident, $lines:expr) => {
+        let lines = $lines;
+        let expected = lines.iter().map(|line| $name::is_match(line)).collect::<Vec<_>>();
+        let matched = $name::is_match_many(&lines);
+
+        assert_eq!(matched.iter().collect::<Vec<_>>(), expected, "{}", stringify!($name));
+        assert_eq!(
+            $name::filter(lines.iter().copied()).collect::<Vec<_>>(),
+            lines.iter().copied().filter(|line| $name::is_match(line)).collect::<Vec<_>>(),
+        );
+        assert_eq!(matched.count(), expected.iter().filter(|&&m| m).count());
+    };
+}
+
+#[test]
+fn same_as_is_match() {
+    assert_same!(Banner, lines());
+    assert_same!(Prefixed, lines());
+    assert_same!(Exact, lines());
+    assert_same!(Words, lines());
+    assert_same!(Empty, lines());
+    assert_same!(Banner, Vec::<&str>::new());
+}
+
+#[test]
+fn match_bits() {
+    let lines = ["-ad-300x250-", "ad", "", "/x-ad-1x1.", "-ad-x"];
+    let matched = Banner::is_match_many(&lines);
+
+    assert_eq!(matched.len(), 5);
+    assert!(matched.get(0) && !matched.get(1) && matched.get(3));
+    assert_eq!(matched.matched().collect::<Vec<_>>(), [0, 3]);
+    assert_eq!(matched.as_words(), [0b1001]);
+
+    let many = vec!["/ads/"; 130];
+    let matched = Prefixed::is_match_many(&many);
+
+    assert_eq!(matched.count(), 130);
+    assert_eq!(matched.as_words(), [u64::MAX, u64::MAX, 0b11]);
+    assert_eq!(matched.matched().last(), Some(129));
+}
+
+#[test]
+fn filter_lines() {
+    let text = "-ad-300x250-\n/ads/\npopup\n-ad-728x90.\n";
+
+    assert_eq!(Banner::filter(text.lines()).collect::<Vec<_>>(), ["-ad-300x250-", "-ad-728x90."]);
+    assert_eq!(Words::filter(text.lines()).count(), 1);
+}
@@ -0,0 +1,60 @@
+use compiled_regex::parse_regex;
+use regex::Regex;
+
+// Not UTF-8, the Latin-1 characters get replaced
+const HTML: &[u8] = include_bytes!("../data/cp.html");
+
+fn span(m: Option<compiled_regex::Match>) -> Option<(usize, usize)> {
+    m.map(|m| (m.start(), m.end()))
+}
+
+// Exponential for a plain backtracker, every `a` can be matched by
+// either branch
+parse_regex!(Rambiguous = "(a|a)*b", engine = bounded);
+parse_regex!(Roverlapping = "(?:a|aa)*c", engine = bounded);
+parse_regex!(Rsparse = "(a|a)*b", engine = bounded, visited_capacity = 8);
+
+#[test]
+fn pathological() {
+    let input = "a".repeat(5_000);
+
+    assert!(!Rambiguous::is_match(&input));
+    assert!(!Roverlapping::is_match(&input));
+    assert!(!Rsparse::is_match(&input));
+
+    let input = input + "b";
+    assert_eq!(span(Rambiguous::find(&input)), Some((0, input.len())));
+    assert_eq!(span(Rsparse::find(&input)), Some((0, input.len())));
+}
+
+parse_regex!(Rtag = "<([a-z]+)[^>]*?>", engine = bounded);
+parse_regex!(Rcounted = "[a-z]{2,4}?[0-9]{2}", engine = bounded);
+parse_regex!(Rwords = r"\b\w+\b\s+\b\w+ing\b", engine = bounded);
+
+#[test]
+fn same_as_regex() {
+    let html = String::from_utf8_lossy(HTML);
+    let cases = [
+        ("<([a-z]+)[^>]*?>", Rtag::find as fn(&str) -> Option<compiled_regex::Match>),
+        ("[a-z]{2,4}?[0-9]{2}", Rcounted::find),
+        (r"\b\w+\b\s+\b\w+ing\b", Rwords::find),
+    ];
+
+    for (pattern, find) in cases {
+        let r = Regex::new(pattern).unwrap();
+        let mut at = 0;
+
+        while let Some(m) = find(&html[at..]) {
+            let expected = r.find(&html[at..]).unwrap();
+
+            assert_eq!((m.start(), m.end()), (expected.start(), expected.end()));
+            at += m.end().max(m.start() + 1);
+
+            while !html.is_char_boundary(at) {
+                at += 1;
+            }
+        }
+
+        assert!(r.find(&html[at..]).is_none());
+    }
+}
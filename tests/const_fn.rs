@@ -0,0 +1,44 @@
+use compiled_regex::parse_regex;
+use regex::Regex;
+
+parse_regex!(Semver = r"^(0|[1-9]\d*)\.(0|[1-9]\d*)\.(0|[1-9]\d*)(?:-[0-9A-Za-z.-]+)?$", engine = recursive, const_fn = true);
+parse_regex!(Key = r"\b[a-z_]+\b", engine = recursive, const_fn = true);
+parse_regex!(Greek = r"\p{Greek}{2,3}", engine = recursive, const_fn = true);
+
+const _: () = assert!(Semver::is_match(env!("CARGO_PKG_VERSION")));
+const _: () = assert!(!Semver::is_match("1.02.3"));
+
+const KEY: Option<(usize, usize)> = match Key::find("  log_level = 3") {
+    Some(m) => Some((m.start(), m.end())),
+    None => None,
+};
+
+#[test]
+fn evaluated_at_compile_time() {
+    assert_eq!(KEY, Some((2, 11)));
+}
+
+#[test]
+fn same_as_regex() {
+    let cases = [
+        (Semver::find as fn(&str) -> Option<compiled_regex::Match>, r"^(0|[1-9]\d*)\.(0|[1-9]\d*)\.(0|[1-9]\d*)(?:-[0-9A-Za-z.-]+)?$"),
+        (Key::find, r"\b[a-z_]+\b"),
+        (Greek::find, r"\p{Greek}{2,3}"),
+    ];
+    let inputs = [
+        "", "0.1.0", "1.2.3-beta.1", "01.2.3", "1.2", "1.2.٣", "é log_level",
+        "äbc", "x αβγδ", "λ", "ab_c d", "日本 key",
+    ];
+
+    for (find, pattern) in cases {
+        let regex = Regex::new(pattern).unwrap();
+
+        for input in inputs {
+            assert_eq!(
+                find(input).map(|m| m.range()),
+                regex.find(input).map(|m| m.range()),
+                "{pattern}: {input:?}"
+            );
+        }
+    }
+}
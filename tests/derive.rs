@@ -0,0 +1,55 @@
+use std::fmt::Display;
+use std::marker::PhantomData;
+
+use compiled_regex::Regex;
+
+/// Addresses of the form `user@example.com`
+#[derive(Regex, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[regex(r"^[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}$")]
+pub struct Email;
+
+#[derive(Regex)]
+#[regex("[0-9]+", engine = recursive, matches = ["a1"], rejects = ["abc"])]
+struct Digits;
+
+/// Generic types get the methods for every instantiation
+#[derive(Regex, Debug)]
+#[regex(r"\bfn\b")]
+struct Keyword<'a, T: Display = u8, const N: usize = 3>(PhantomData<&'a T>)
+where
+    T: Clone;
+
+#[derive(Regex)]
+#[regex("x+y")]
+struct Named<T> {
+    _marker: PhantomData<T>,
+}
+
+#[derive(Regex)]
+#[regex("[0-9]+")]
+struct Bytes<I: Iterator<Item = u8>, F: Fn(u8) -> Option<u8> = fn(u8) -> Option<u8>>(PhantomData<(I, F)>);
+
+/// A trait the generated types can implement, since they are ours
+trait Validate {
+    fn is_valid(input: &str) -> bool;
+}
+
+impl Validate for Email {
+    fn is_valid(input: &str) -> bool {
+        Email::is_match(input)
+    }
+}
+
+#[test]
+fn methods() {
+    assert!(Email::is_valid("someone@example.com"));
+    assert!(!Email::is_valid("someone@example"));
+    assert_eq!(format!("{:?}", Email), "Email");
+
+    assert_eq!(Digits::find("ab123c").map(|m| m.range()), Some(2..5));
+
+    assert!(Keyword::<'static, String, 5>::is_match("pub fn main"));
+    assert!(!Keyword::<u8>::is_match("fnord"));
+    assert!(Named::<()>::is_match("axxy"));
+    assert!(Bytes::<std::vec::IntoIter<u8>>::is_match("x42"));
+}
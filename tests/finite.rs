@@ -0,0 +1,37 @@
+use compiled_regex::parse_regex;
+use regex::Regex;
+
+parse_regex!(Method = "^(GET|POST|PUT|DELETE|PATCH)$");
+parse_regex!(Mime = r"^(?:text/(?:plain|html|css)|image/(?:png|jpe?g|gif)|application/json)$");
+parse_regex!(Caseless = "(?i)^(?:get|post)$");
+parse_regex!(Accented = "^(?:caf[eé]|na[iï]ve)$");
+
+#[test]
+fn same_as_regex() {
+    let cases = [
+        (Method::find as fn(&str) -> Option<compiled_regex::Match>, "^(GET|POST|PUT|DELETE|PATCH)$"),
+        (Mime::find, r"^(?:text/(?:plain|html|css)|image/(?:png|jpe?g|gif)|application/json)$"),
+        (Caseless::find, "(?i)^(?:get|post)$"),
+        (Accented::find, "^(?:caf[eé]|na[iï]ve)$"),
+    ];
+    let inputs = [
+        "", "GET", "get", "GeT", "POST", "POSTS", " PUT", "DELETE", "PATCH",
+        "text/html", "text/htm", "image/jpg", "image/jpeg", "image/jpegg",
+        "application/json", "café", "cafe", "caf\u{e9}\n", "naïve", "naive",
+    ];
+
+    for (find, pattern) in cases {
+        let regex = Regex::new(pattern).unwrap();
+
+        for input in inputs {
+            assert_eq!(
+                find(input).map(|m| m.range()),
+                regex.find(input).map(|m| m.range()),
+                "{pattern}: {input:?}"
+            );
+        }
+    }
+
+    assert!(Method::is_match("PUT"));
+    assert!(!Method::is_match("PUT\n"));
+}
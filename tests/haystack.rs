@@ -0,0 +1,110 @@
+use compiled_regex::{parse_regex, Haystack, Segments};
+use regex::Regex;
+
+parse_regex!(Word = r"\b[A-Z][a-z]+\b", chunked = true);
+parse_regex!(Greek = r"αβ+|γ", chunked = true);
+parse_regex!(Line = r"(?m)^fn \w+\(.*\)$", chunked = true);
+parse_regex!(Anchored = "^(?:ab)+c", chunked = true, engine = structured);
+parse_regex!(Empty = "x*", chunked = true);
+
+const PATTERNS: [&str; 5] = [
+    r"\b[A-Z][a-z]+\b",
+    r"αβ+|γ",
+    r"(?m)^fn \w+\(.*\)$",
+    "^(?:ab)+c",
+    "x*",
+];
+
+const TEXTS: [&str; 6] = [
+    "the Quick brown Fox",
+    "δ αββ γ",
+    "let x = 1;\nfn main()\n",
+    "ababc",
+    "abac",
+    "",
+];
+
+/// Bytes of chunks given out one after the other, which can split
+/// characters unlike the segments of a rope
+struct Chunks<'a> {
+    chunks: &'a [&'a [u8]],
+    /// Offset of the next byte in the first chunk
+    at: usize,
+}
+
+impl Haystack for Chunks<'_> {
+    fn next_byte(&mut self) -> Option<u8> {
+        loop {
+            let (chunk, rest) = self.chunks.split_first()?;
+
+            if let Some(&byte) = chunk.get(self.at) {
+                self.at += 1;
+                return Some(byte);
+            }
+
+            self.chunks = rest;
+            self.at = 0;
+        }
+    }
+}
+
+fn find_in(pattern: usize, segments: &[&str]) -> Option<(usize, usize)> {
+    let found = match pattern {
+        0 => Word::find_in(Segments::from(segments)),
+        1 => Greek::find_in(Segments::from(segments)),
+        2 => Line::find_in(Segments::from(segments)),
+        3 => Anchored::find_in(Segments::from(segments)),
+        _ => Empty::find_in(Segments::from(segments)),
+    };
+
+    found.map(|range| (range.start, range.end))
+}
+
+#[test]
+fn same_as_regex() {
+    for (i, pattern) in PATTERNS.iter().enumerate() {
+        let regex = Regex::new(pattern).unwrap();
+
+        for text in TEXTS {
+            let expected = regex.find(text).map(|m| (m.start(), m.end()));
+
+            // Every split into two segments at a character boundary, and
+            // empty segments around them
+            for split in (0..=text.len()).filter(|&at| text.is_char_boundary(at)) {
+                let (a, b) = text.split_at(split);
+
+                assert_eq!(find_in(i, &[a, b]), expected, "{pattern} in {a:?} {b:?}");
+                assert_eq!(find_in(i, &["", a, "", b, ""]), expected);
+            }
+
+            // A segment per character
+            let chars = text
+                .char_indices()
+                .map(|(at, c)| &text[at..at + c.len_utf8()])
+                .collect::<Vec<_>>();
+
+            assert_eq!(find_in(i, &chars), expected, "{pattern} in {text:?}");
+        }
+    }
+}
+
+#[test]
+fn rope_chunks() {
+    let rope = ["impl Foo {\n", "    fn new", "() -> Self\n", "}\n", "fn foo(x: u8)\n"];
+    let text = rope.concat();
+    let found = Line::find_in(Segments::new(rope.iter().copied()));
+
+    assert_eq!(found, Some(34..47));
+    assert_eq!(&text[34..47], "fn foo(x: u8)");
+    assert!(!Word::is_match_in(Segments::from(&["lower ", "case"][..])));
+}
+
+#[test]
+fn split_characters() {
+    // `β` and `γ` are split between chunks
+    let text = "δ αββ γ".as_bytes();
+    let chunks = [&text[..5], &text[5..7], &text[7..10], &text[10..]];
+
+    assert_eq!(Greek::find_in(Chunks { chunks: &chunks, at: 0 }), Some(3..9));
+    assert!(Word::is_match_in(Chunks { chunks: &[b"a ", b"B", b"cd"], at: 0 }));
+}
@@ -0,0 +1,120 @@
+use compiled_regex::lexer;
+
+lexer! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum C {
+        #[regex("if|else|while|for|return|int|char|static")]
+        Keyword,
+        #[regex("[a-zA-Z_][a-zA-Z0-9_]*")]
+        Ident,
+        #[regex("[0-9]+")]
+        #[regex("0[xX][0-9a-fA-F]+")]
+        Int,
+        #[regex(r#""(?:[^"\\\n]|\\.)*""#)]
+        #[regex(r"'(?:[^'\\\n]|\\.)'")]
+        Literal,
+        #[regex(r"/\*(?s:.)*?\*/")]
+        Comment,
+        #[regex(r"#[a-z]+")]
+        Directive,
+        #[regex(r"[-+*/%=<>!&|^~?:;,.(){}\[\]]|->|\+\+|--|[-+*/%=<>!&|^]=|&&|\|\||<<|>>")]
+        Punct,
+        #[regex(r"\s+")]
+        Space,
+        /// Characters no other token starts with
+        #[error]
+        Error,
+    }
+}
+
+lexer! {
+    #[derive(Debug, PartialEq)]
+    pub(crate) enum Lisp {
+        #[regex(r"\(")]
+        Open,
+        #[regex(r"\)")]
+        Close,
+        #[regex(r"'")]
+        Quote,
+        #[regex(r"\$[a-z0-9]+")]
+        Variable,
+        #[regex(r"[^\s()';]+")]
+        Symbol,
+        #[regex(r";[^\n]*")]
+        Comment,
+        #[regex(r"\s+")]
+        Space,
+        #[error]
+        Error,
+    }
+}
+
+#[test]
+fn longest_then_first() {
+    let tokens = C::lexer("int integer = 0x1f+x++; @")
+        .filter(|(token, _)| *token != C::Space)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        tokens,
+        [
+            // Both match `int`, the keyword is declared first
+            (C::Keyword, 0..3),
+            (C::Ident, 4..11),
+            (C::Punct, 12..13),
+            // `0` alone is shorter
+            (C::Int, 14..18),
+            (C::Punct, 18..19),
+            (C::Ident, 19..20),
+            (C::Punct, 20..22),
+            (C::Punct, 22..23),
+            (C::Error, 24..25),
+        ]
+    );
+}
+
+#[test]
+fn errors() {
+    let tokens = C::lexer("a€b").collect::<Vec<_>>();
+
+    assert_eq!(tokens, [(C::Ident, 0..1), (C::Error, 1..4), (C::Ident, 4..5)]);
+
+    let mut lexer = C::lexer("x y");
+
+    assert_eq!(lexer.next(), Some((C::Ident, 0..1)));
+    assert_eq!(lexer.remainder(), " y");
+}
+
+#[test]
+fn c_source() {
+    let input = include_str!("../data/fields.c");
+    let mut end = 0;
+    let mut counts = [0; 9];
+
+    for (token, span) in C::lexer(input) {
+        // Every byte belongs to exactly one token
+        assert_eq!(span.start, end);
+        end = span.end;
+        counts[token as usize] += 1;
+    }
+
+    assert_eq!(end, input.len());
+    assert_eq!(counts[C::Error as usize], 0);
+    assert!(counts[C::Comment as usize] > 0);
+    assert!(counts[C::Keyword as usize] > counts[C::Directive as usize]);
+}
+
+#[test]
+fn lisp_grammar() {
+    let input = include_str!("../data/grammar.lsp");
+    let tokens = Lisp::lexer(input).collect::<Vec<_>>();
+
+    assert_eq!(tokens.last().unwrap().1.end, input.len());
+    assert!(!tokens.iter().any(|(token, _)| *token == Lisp::Error));
+
+    let opened = tokens.iter().filter(|(token, _)| *token == Lisp::Open).count();
+    let closed = tokens.iter().filter(|(token, _)| *token == Lisp::Close).count();
+
+    assert_eq!(opened, closed);
+    assert!(tokens.iter().any(|(token, span)| *token == Lisp::Variable && &input[span.clone()] == "$subj"));
+}
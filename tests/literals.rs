@@ -0,0 +1,92 @@
+use compiled_regex::{parse_regex, parse_regex_set};
+use regex::{Regex, RegexSet};
+
+const DOMAINS: [&str; 8] = [
+    "tripadvisor.co",
+    "tripadvisor.co.uk",
+    "tripadvisor.com",
+    "tripadvisor.com.au",
+    "tripadvisor.de",
+    "trip",
+    "tripadvisor.co",
+    "advisor",
+];
+
+// The shorter literals are found first, and have to be given up for
+// the longer ones when the rest of the pattern does not match
+const PATTERN: &str = r"(?:tripadvisor\.co|tripadvisor\.co\.uk|tripadvisor\.com|tripadvisor\.com\.au|tripadvisor\.de|trip|advisor)/\w+";
+
+parse_regex!(Backtrack = r"(?:tripadvisor\.co|tripadvisor\.co\.uk|tripadvisor\.com|tripadvisor\.com\.au|tripadvisor\.de|trip|advisor)/\w+");
+parse_regex!(Bounded = r"(?:tripadvisor\.co|tripadvisor\.co\.uk|tripadvisor\.com|tripadvisor\.com\.au|tripadvisor\.de|trip|advisor)/\w+", engine = bounded);
+parse_regex!(Recursive = r"(?:tripadvisor\.co|tripadvisor\.co\.uk|tripadvisor\.com|tripadvisor\.com\.au|tripadvisor\.de|trip|advisor)/\w+", engine = recursive);
+parse_regex!(Limited = r"(?:tripadvisor\.co|tripadvisor\.co\.uk|tripadvisor\.com|tripadvisor\.com\.au|tripadvisor\.de|trip|advisor)/\w+", stack_limit = 8);
+parse_regex!(Structured = r"(?:tripadvisor\.co|tripadvisor\.co\.uk|tripadvisor\.com|tripadvisor\.com\.au|tripadvisor\.de|trip|advisor)/\w+", engine = structured);
+
+parse_regex_set!(
+    Domains = [
+        "tripadvisor.co",
+        "tripadvisor.co.uk",
+        "tripadvisor.com",
+        "tripadvisor.com.au",
+        "tripadvisor.de",
+        "trip",
+        "tripadvisor.co",
+        "advisor",
+    ]
+);
+
+const INPUTS: [&str; 12] = [
+    "",
+    "trip",
+    "trip/x",
+    "tripadvisor.co/x",
+    "tripadvisor.co.uk/x",
+    "tripadvisor.co.u/x",
+    "tripadvisor.com.au/x",
+    "tripadvisor.com.a/x",
+    "www.tripadvisor.de/hotels",
+    "tripadvisor.fr/x",
+    "see tripadvisor.com.au and advisor/1",
+    "tripadvisor.co\u{e9}/x",
+];
+
+#[test]
+fn same_as_regex() {
+    let regex = Regex::new(PATTERN).unwrap();
+    let matchers = [
+        Backtrack::find as fn(&str) -> Option<compiled_regex::Match>,
+        Bounded::find,
+        Recursive::find,
+        Limited::find,
+        Structured::find,
+    ];
+
+    for input in INPUTS {
+        let expected = regex.find(input).map(|m| m.range());
+
+        for (i, find) in matchers.iter().enumerate() {
+            assert_eq!(find(input).map(|m| m.range()), expected, "{i}: {input:?}");
+        }
+    }
+}
+
+#[test]
+fn literal_set() {
+    let set = RegexSet::new(DOMAINS.map(regex::escape)).unwrap();
+    let alternation = DOMAINS.map(|domain| format!("({})", regex::escape(domain))).join("|");
+    let alternation = Regex::new(&alternation).unwrap();
+
+    for input in INPUTS {
+        assert_eq!(
+            Domains::matches(input).iter().collect::<Vec<_>>(),
+            set.matches(input).into_iter().collect::<Vec<_>>(),
+            "{input:?}"
+        );
+        assert_eq!(Domains::is_match(input), set.is_match(input), "{input:?}");
+
+        let first = alternation.captures(input).and_then(|captures| {
+            (1..captures.len()).find(|&group| captures.get(group).is_some())
+        });
+        assert_eq!(Domains::first_match(input), first.map(|group| group - 1), "{input:?}");
+    }
+}
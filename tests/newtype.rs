@@ -0,0 +1,58 @@
+use std::collections::HashSet;
+
+use compiled_regex::{parse_regex, InvalidFormat};
+
+parse_regex!(pub newtype Email = r"[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}");
+parse_regex!(
+    newtype Ident<'a> = "[a-zA-Z_][a-zA-Z0-9_]*",
+    matches = ["snake_case", "_"],
+    rejects = ["1st", "a-b", ""],
+);
+// Alternatives have to match the whole string as well
+parse_regex!(newtype Level = "debug|info|warn", engine = recursive);
+// The anchors can not end up in the comment
+parse_regex!(newtype Letters = "(?x)[a-z]+ # letters");
+
+#[test]
+fn owned() {
+    let email: Email = "someone@example.com".parse().unwrap();
+
+    assert_eq!(email.as_str(), "someone@example.com");
+    assert_eq!(email.to_string(), "someone@example.com");
+    assert_eq!(email.len(), 19);
+    assert!(email.ends_with(".com"));
+    assert_eq!(String::from(email.clone()), "someone@example.com");
+    assert_eq!(Email::try_from(String::from("a@b.cd")).unwrap().into_inner(), "a@b.cd");
+
+    // Matches inside a longer string are not enough
+    let err = Email::try_from("mail someone@example.com").unwrap_err();
+
+    assert_eq!(err.name(), "Email");
+    assert_eq!(err, InvalidFormat::new("Email", r"[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}"));
+    assert!(err.to_string().contains("Email"));
+
+    let set = HashSet::from([email]);
+
+    assert!(set.contains(&Email::try_from("someone@example.com").unwrap()));
+}
+
+#[test]
+fn borrowed() {
+    let input = String::from("some_name");
+    let ident = Ident::try_from(input.as_str()).unwrap();
+    let inner: &str = ident.into_inner();
+
+    assert_eq!(inner, "some_name");
+    assert_eq!(ident.as_ref(), "some_name");
+    assert!(Ident::try_from("some name").is_err());
+}
+
+#[test]
+fn whole() {
+    assert!(Level::is_match("info"));
+    assert!(!Level::is_match("information"));
+    assert!(!Level::is_match("a warn"));
+
+    assert!(Letters::try_from(String::from("abc")).is_ok());
+    assert!(Letters::try_from(String::from("abc1")).is_err());
+}
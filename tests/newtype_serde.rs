@@ -0,0 +1,24 @@
+#![cfg(feature = "serde")]
+
+use compiled_regex::parse_regex;
+
+parse_regex!(newtype Hex = "[0-9a-f]+");
+parse_regex!(newtype Word<'a> = "[a-z]+");
+
+#[test]
+fn deserialize() {
+    let hex: Hex = serde_json::from_str(r#""c0ffee""#).unwrap();
+
+    assert_eq!(hex.as_str(), "c0ffee");
+    assert_eq!(serde_json::to_string(&hex).unwrap(), r#""c0ffee""#);
+
+    let err = serde_json::from_str::<Hex>(r#""coffee""#).unwrap_err();
+
+    assert!(err.to_string().contains("Hex"));
+
+    let input = String::from(r#"["one", "two"]"#);
+    let words: Vec<Word> = serde_json::from_str(&input).unwrap();
+
+    assert_eq!(words[1].as_str(), "two");
+    assert!(serde_json::from_str::<Vec<Word>>(r#"["one", "2"]"#).is_err());
+}
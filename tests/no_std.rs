@@ -0,0 +1,136 @@
+// The generated code only names what is in the prelude of a `no_std`
+// crate, or what `compiled_regex` exports
+#[no_implicit_prelude]
+mod firmware {
+    use ::core::prelude::rust_2021::*;
+
+    use ::compiled_regex::{lexer, parse_regex, parse_regex_set, Regex, ValidatePatterns};
+
+    // Responses of AT commands, which only need `core`
+    parse_regex!(Ok = r"^\r\nOK\r\n$", engine = recursive);
+    parse_regex!(Signal = r"\+CSQ: (\d{1,2}),(\d)", engine = structured);
+    parse_regex!(Registered = r"\+CREG: [0-2],[15]", stack_limit = 16);
+    parse_regex!(Version = r"^v\d+\.\d+$", engine = recursive, const_fn = true);
+    parse_regex!(Boundary = r"\bERROR\b", engine = recursive);
+    parse_regex!(Words = r"(?:foo|bar|baz)+");
+
+    // Allocating with the `alloc` feature
+    parse_regex!(Default = r"\+CME ERROR: \d+");
+    parse_regex!(Bounded = "(a|ab)*c", engine = bounded);
+    parse_regex!(newtype Imei = r"\d{15}");
+    parse_regex!(newtype Apn<'a> = r"[a-z.]+");
+    parse_regex_set!(Responses = [r"^OK", r"ERROR", r"\+C[A-Z]+:"]);
+    parse_regex_set!(Literals = ["OK", "ERROR"]);
+
+    lexer! {
+        #[derive(Debug, PartialEq)]
+        pub enum Token {
+            #[regex("[A-Z]+")]
+            Word,
+            #[regex("[0-9]+")]
+            Number,
+            #[error]
+            Error,
+        }
+    }
+
+    #[derive(Regex)]
+    #[regex(r"^AT\+[A-Z]+")]
+    pub struct Command;
+
+    #[derive(ValidatePatterns)]
+    pub struct Config<'a> {
+        #[pattern("^[a-z.]+$")]
+        pub apn: &'a str,
+    }
+
+    pub fn check() {
+        assert!(Ok::is_match("\r\nOK\r\n"));
+        assert!(Signal::is_match("+CSQ: 21,0"));
+        assert_eq!(Registered::try_is_match("+CREG: 0,1"), Result::Ok(true));
+        assert!(Version::is_match("v1.2"));
+        assert!(Boundary::is_match("+CME ERROR"));
+        assert!(Words::is_match("bazfoo"));
+        assert!(Default::is_match("+CME ERROR: 10"));
+        assert!(Bounded::is_match("ababc"));
+        assert!(Imei::is_match("490154203237518"));
+        assert!(Apn::try_from("internet").is_ok());
+        assert!(Responses::is_match("+CSQ: 1"));
+        assert!(Literals::is_match("ERROR"));
+        assert!(Token::lexer("AT 5").count() == 3);
+        assert!(Command::is_match("AT+CSQ"));
+        assert!(Config { apn: "x" }.validate().is_ok());
+    }
+}
+
+#[test]
+fn core_prelude() {
+    firmware::check();
+}
+
+#[test]
+fn runtime_dependencies() {
+    // The proc macro runs on the host, everything else has to build for
+    // targets without `std`, such as `thumbv7em-none-eabihf`
+    let output = std::process::Command::new(env!("CARGO"))
+        .args(["tree", "--offline", "--no-default-features", "--features", "alloc"])
+        .args(["--edges", "normal,no-proc-macro", "--prefix", "none", "--package", "compiled-regex"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let dependencies = String::from_utf8(output.stdout).unwrap();
+    let names = dependencies
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .collect::<Vec<_>>();
+
+    assert_eq!(names, ["compiled-regex", "memchr"], "{dependencies}");
+}
+
+#[test]
+fn core_only() {
+    // Built on its own, as the tests enable `std` through the
+    // dev-dependencies
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("core-fixture");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"core-fixture\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+             [dependencies]\ncompiled-regex = {{ path = {:?}, default-features = false }}\n\n[workspace]\n",
+            env!("CARGO_MANIFEST_DIR")
+        ),
+    )
+    .unwrap();
+    std::fs::copy(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.lock"), dir.join("Cargo.lock")).unwrap();
+
+    let check = |code: &str| {
+        std::fs::write(dir.join("src/lib.rs"), format!("#![no_std]\n{code}")).unwrap();
+
+        std::process::Command::new(env!("CARGO"))
+            .args(["check", "--offline", "--quiet"])
+            .current_dir(&dir)
+            .env("CARGO_TARGET_DIR", dir.with_file_name("core-target"))
+            .output()
+            .unwrap()
+    };
+
+    let output = check(
+        r#"use compiled_regex::parse_regex;
+parse_regex!(Signal = r"\+CSQ: (\d{1,2}),(\d)", engine = structured);
+parse_regex!(Lines = r"(?:OK|ERROR)\r\n(?:ab)+c*", engine = structured);
+parse_regex!(Ok = r"^\r\nOK\r\n$", engine = recursive);
+"#,
+    );
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    // Loops over more than characters keep a list of the ends of their
+    // body
+    let output = check(r#"compiled_regex::parse_regex!(S = "(?:ab|c)+d", engine = structured);"#);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("needs the `alloc` feature"));
+}
@@ -0,0 +1,126 @@
+use compiled_regex::parse_regex;
+use regex::Regex;
+
+const RULES: &str = include_str!("../data/easylist_url_regex.txt");
+
+parse_regex!(Gif = r"[a-z0-9]+\.gif", reverse = true);
+parse_regex!(Script = r"/ads?[-_]?\w*\.js", reverse = true, engine = bounded);
+parse_regex!(Overlapping = "a|ab|b+", reverse = true);
+parse_regex!(Greek = r"αβ+|γ", reverse = true, engine = recursive);
+parse_regex!(Empty = "x*", reverse = true);
+parse_regex!(Word = r"\bad\b", reverse = true);
+
+parse_regex!(GifEnd = r"[a-z0-9]+\.gif$", reverse = true);
+parse_regex!(RepeatEnd = r"(?:ab|b)+\|?$", reverse = true, engine = structured);
+parse_regex!(WordEnd = r"\b[a-z]+=$", reverse = true);
+
+parse_regex!(LineBreak = r"(?m)^\n", reverse = true);
+parse_regex!(Line = r"(?m)^[a-z]*$", reverse = true);
+parse_regex!(LineEnd = r"(?m)b$", reverse = true, engine = structured);
+
+const TEXTS: [&str; 7] = [
+    "/img/a1.gif?/ads.js&b.gif",
+    "x.gif.gif/ad_banner.js",
+    "babba ab a",
+    "αββ γ αβ",
+    "xxaxx",
+    "",
+    ".gif",
+];
+
+/// The match ending last and starting first, found by trying every
+/// span of `text`
+fn brute_force(pattern: &str, text: &str) -> Option<(usize, usize)> {
+    let whole = Regex::new(&format!("^(?:{pattern})$")).unwrap();
+    let boundaries = (0..=text.len())
+        .filter(|&at| text.is_char_boundary(at))
+        .collect::<Vec<_>>();
+
+    boundaries.iter().rev().find_map(|&end| {
+        boundaries
+            .iter()
+            .take_while(|&&start| start <= end)
+            .find(|&&start| whole.is_match(&text[start..end]))
+            .map(|&start| (start, end))
+    })
+}
+
+macro_rules! assert_rfind {
+    ($name:ident, $pattern:expr) => {
+        for text in TEXTS {
+            assert_eq!(
+                $name::rfind(text).map(|m| (m.start(), m.end())),
+                brute_force($pattern, text),
+                "{} in {text:?}",
+                $pattern
+            );
+        }
+    };
+}
+
+#[test]
+fn last_match() {
+    assert_rfind!(Gif, r"[a-z0-9]+\.gif");
+    assert_rfind!(Script, r"/ads?[-_]?\w*\.js");
+    assert_rfind!(Overlapping, "a|ab|b+");
+    assert_rfind!(Greek, r"αβ+|γ");
+    assert_rfind!(Empty, "x*");
+
+    assert_eq!(Gif::rfind("a.gif b.gif c").unwrap().as_str(), "b.gif");
+    assert_eq!(Overlapping::rfind("abbb").unwrap().range(), 1..4);
+    // Word boundaries look at the text after the end too
+    assert_eq!(Word::rfind("bad ad add").unwrap().range(), 4..6);
+    assert_eq!(Word::rfind("bad adx"), None);
+}
+
+#[test]
+fn anchored_at_end() {
+    let patterns = [r"[a-z0-9]+\.gif$", r"(?:ab|b)+\|?$", r"\b[a-z]+=$"];
+    let regexes = patterns.map(|pattern| Regex::new(pattern).unwrap());
+
+    for line in RULES.lines().chain(TEXTS).chain(["abab|", "b|", "cabb", "x.gif", "é="]) {
+        let found = [GifEnd::find(line), RepeatEnd::find(line), WordEnd::find(line)];
+
+        for (found, regex) in found.iter().zip(&regexes) {
+            let expected = regex.find(line).map(|m| m.range());
+
+            assert_eq!(found.map(|m| m.range()), expected, "{regex} in {line:?}");
+        }
+    }
+
+    // The end anchor holds only at the end after reversing
+    assert_eq!(GifEnd::rfind("a.gif b.gif c"), None);
+    assert_eq!(GifEnd::rfind("a.gif b.gif").unwrap().range(), 6..11);
+}
+
+/// The match ending last and starting first among the matches of
+/// `regex` at every position of `text`, which sees the text around them
+fn last_of_all(regex: &Regex, text: &str) -> Option<(usize, usize)> {
+    (0..=text.len())
+        .filter(|&at| text.is_char_boundary(at))
+        .filter_map(|at| regex.find_at(text, at))
+        .map(|m| (m.start(), m.end()))
+        .max_by_key(|&(start, end)| (end, std::cmp::Reverse(start)))
+}
+
+#[test]
+fn multiline() {
+    let texts = ["ab\ncd", "ab\n\ncd\n", "\r\n", "a\r\nb", "x1\nb", "\n", "b\r", ""];
+    let cases = [
+        (LineBreak::rfind as fn(&str) -> Option<compiled_regex::Match>, r"(?m)^\n"),
+        (Line::rfind, r"(?m)^[a-z]*$"),
+        (LineEnd::rfind, r"(?m)b$"),
+    ];
+
+    for (rfind, pattern) in cases {
+        let regex = Regex::new(pattern).unwrap();
+
+        for text in texts {
+            assert_eq!(
+                rfind(text).map(|m| (m.start(), m.end())),
+                last_of_all(&regex, text),
+                "{pattern} in {text:?}"
+            );
+        }
+    }
+}
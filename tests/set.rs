@@ -0,0 +1,107 @@
+use compiled_regex::parse_regex_set;
+use regex::{Regex, RegexSet};
+
+// Not UTF-8, the Latin-1 characters get replaced
+const HTML: &[u8] = include_bytes!("../data/cp.html");
+
+const PATTERNS: [&str; 6] = [
+    r"[a-z]+ing\b",
+    r"<h[1-6]>",
+    r"\d{4}",
+    "^<",
+    r"href=.*?\.html",
+    "zzz",
+];
+
+parse_regex_set!(
+    Rset = [
+        r"[a-z]+ing\b",
+        r"<h[1-6]>",
+        r"\d{4}",
+        "^<",
+        r"href=.*?\.html",
+        "zzz",
+    ]
+);
+
+/// Pattern of the leftmost-first match of the alternation of all
+/// patterns, found through a group per pattern
+fn first_match(r: &Regex, input: &str) -> Option<usize> {
+    let captures = r.captures(input)?;
+
+    (1..captures.len()).find(|&group| captures.get(group).is_some()).map(|group| group - 1)
+}
+
+#[test]
+fn same_as_regex() {
+    let set = RegexSet::new(PATTERNS).unwrap();
+    let alternation = PATTERNS.map(|pattern| format!("({pattern})")).join("|");
+    let alternation = Regex::new(&alternation).unwrap();
+    let html = String::from_utf8_lossy(HTML);
+
+    let inputs = html
+        .lines()
+        .chain(["", "<", "x<", "zzz and 1999", "thing <h2>", "sing zzz"]);
+
+    for input in inputs {
+        let matches = Rset::matches(input);
+
+        assert_eq!(
+            matches.iter().collect::<Vec<_>>(),
+            set.matches(input).into_iter().collect::<Vec<_>>(),
+            "{input:?}"
+        );
+        assert_eq!(Rset::is_match(input), set.is_match(input), "{input:?}");
+        assert_eq!(Rset::first_match(input), first_match(&alternation, input), "{input:?}");
+        assert_eq!(matches.matched_any(), set.is_match(input));
+    }
+
+    assert_eq!(Rset::len(), PATTERNS.len());
+    assert_eq!(Rset::patterns(), PATTERNS);
+}
+
+parse_regex_set!(Rpriority = ["abc", "b", "a"]);
+
+#[test]
+fn priority() {
+    // Earlier starts win, then earlier patterns
+    assert_eq!(Rpriority::first_match("abc"), Some(0));
+    assert_eq!(Rpriority::first_match("abd"), Some(2));
+    assert_eq!(Rpriority::first_match("xbc"), Some(1));
+    assert_eq!(Rpriority::first_match("xyz"), None);
+
+    let matches = Rpriority::matches("abd");
+    assert!(!matches.matched(0));
+    assert!(matches.matched(1));
+    assert!(matches.matched(2));
+    assert!(!matches.matched_all());
+}
+
+#[test]
+fn reused_cache() {
+    let html = String::from_utf8_lossy(HTML);
+    let mut cache = Rset::create_cache();
+
+    // One cache serves every input
+    for input in html.lines().chain(["zzz and 1999", "abd"]) {
+        assert_eq!(Rset::matches_with(input, &mut cache), Rset::matches(input), "{input:?}");
+        assert_eq!(Rset::is_match_with(input, &mut cache), Rset::is_match(input), "{input:?}");
+        assert_eq!(Rset::first_match_with(input, &mut cache), Rset::first_match(input), "{input:?}");
+    }
+
+    let mut cache = Rpriority::create_cache();
+    assert_eq!(Rpriority::first_match_with("abd", &mut cache), Some(2));
+    assert_eq!(Rpriority::first_match_with("xbc", &mut cache), Some(1));
+}
+
+#[test]
+fn cache_of_another_set() {
+    // The cache of a smaller set grows to fit a larger one
+    let mut cache = Rpriority::create_cache();
+
+    for input in ["zzz and 1999", "<h1>", "abd", ""] {
+        assert_eq!(Rset::matches_with(input, &mut cache), Rset::matches(input), "{input:?}");
+        assert_eq!(Rpriority::first_match_with(input, &mut cache), Rpriority::first_match(input), "{input:?}");
+        assert_eq!(Rset::first_match_with(input, &mut cache), Rset::first_match(input), "{input:?}");
+    }
+}
@@ -0,0 +1,45 @@
+use compiled_regex::parse_regex_set;
+use regex::{Regex, RegexSet};
+
+const RULES: &str = include_str!("../data/easylist_sample.txt");
+const EASYLIST: &str = include_str!("../data/easylist.txt");
+
+parse_regex_set!(Rads = file "data/easylist_sample.txt");
+
+/// Patterns of the rules, without names
+fn patterns() -> Vec<&'static str> {
+    RULES
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.split_once(" = ").map_or(line, |(_, pattern)| pattern))
+        .collect()
+}
+
+#[test]
+fn same_as_regex() {
+    let patterns = patterns();
+    let set = RegexSet::new(&patterns).unwrap();
+
+    assert_eq!(Rads::patterns(), patterns);
+
+    for input in EASYLIST.lines().take(1_000) {
+        assert_eq!(
+            Rads::matches(input).iter().collect::<Vec<_>>(),
+            set.matches(input).into_iter().collect::<Vec<_>>(),
+            "{input:?}"
+        );
+    }
+}
+
+#[test]
+fn named_lines() {
+    let size = Regex::new(r"-ad-\d+x\d+[-.]").unwrap();
+
+    for input in ["/img/x-ad-banner-1.png", "/-ad-300x250-", "-ad-300x.", "none"] {
+        assert_eq!(AdBanner::is_match(input), input.contains("-ad-banner-"));
+        assert_eq!(AdSize::is_match(input), size.is_match(input));
+    }
+
+    let last = Rads::len() - 1;
+    assert!(Rads::matches("/-ad-300x250-").matched(last));
+}
@@ -1,46 +1,148 @@
-use compiled_regex::parse_regex;
-use regex::Regex;
-
-parse_regex!(RaQ4a4 = "^a?a?a?a?aaaa");
-
-#[test]
-#[allow(non_snake_case)]
-fn aQ4a4() {
-    let r = Regex::new("^a?a?a?a?aaaa").unwrap();
-
-    assert_eq!(RaQ4a4::is_match(""), r.is_match(""));
-    assert_eq!(RaQ4a4::is_match("a"), r.is_match("a"));
-    assert_eq!(RaQ4a4::is_match("aa"), r.is_match("aa"));
-    assert_eq!(RaQ4a4::is_match("aaa"), r.is_match("aaa"));
-    assert_eq!(RaQ4a4::is_match("aaaa"), r.is_match("aaaa"));
-    assert_eq!(RaQ4a4::is_match("aaaaa"), r.is_match("aaaaa"));
-    assert_eq!(RaQ4a4::is_match("aaaaaa"), r.is_match("aaaaaa"));
-    assert_eq!(RaQ4a4::is_match("aaaaaaa"), r.is_match("aaaaaaa"));
-}
+/// The tests, with the options of the engine to run them on
+macro_rules! tests {
+    ($($options:tt)*) => {
+        use compiled_regex::parse_regex;
+        use regex::Regex;
 
-parse_regex!(Rbfg = "bfg[34]000");
+        parse_regex!(RaQ4a4 = "^a?a?a?a?aaaa" $($options)*);
 
-#[test]
-fn bfg() {
-    let r = Regex::new("bfg[34]000").unwrap();
+        #[test]
+        #[allow(non_snake_case)]
+        fn aQ4a4() {
+            let r = Regex::new("^a?a?a?a?aaaa").unwrap();
 
-    assert_eq!(Rbfg::is_match("bfg3000"), r.is_match("bfg3000"));
-    assert_eq!(Rbfg::is_match("bfg4000"), r.is_match("bfg4000"));
-    assert_eq!(Rbfg::is_match("bfg400"), r.is_match("bfg400"));
-    assert_eq!(Rbfg::is_match("bfg2000"), r.is_match("bfg2000"));
-    assert_eq!(Rbfg::is_match("bfg3"), r.is_match("bfg3"));
-    assert_eq!(Rbfg::is_match("bfg4"), r.is_match("bfg4"));
-    assert_eq!(Rbfg::is_match("bag3000"), r.is_match("bag3000"));
-}
+            assert_eq!(RaQ4a4::is_match(""), r.is_match(""));
+            assert_eq!(RaQ4a4::is_match("a"), r.is_match("a"));
+            assert_eq!(RaQ4a4::is_match("aa"), r.is_match("aa"));
+            assert_eq!(RaQ4a4::is_match("aaa"), r.is_match("aaa"));
+            assert_eq!(RaQ4a4::is_match("aaaa"), r.is_match("aaaa"));
+            assert_eq!(RaQ4a4::is_match("aaaaa"), r.is_match("aaaaa"));
+            assert_eq!(RaQ4a4::is_match("aaaaaa"), r.is_match("aaaaaa"));
+            assert_eq!(RaQ4a4::is_match("aaaaaaa"), r.is_match("aaaaaaa"));
+        }
+
+        parse_regex!(Rbfg = "bfg[34]000" $($options)*);
+
+        #[test]
+        fn bfg() {
+            let r = Regex::new("bfg[34]000").unwrap();
+
+            assert_eq!(Rbfg::is_match("bfg3000"), r.is_match("bfg3000"));
+            assert_eq!(Rbfg::is_match("bfg4000"), r.is_match("bfg4000"));
+            assert_eq!(Rbfg::is_match("bfg400"), r.is_match("bfg400"));
+            assert_eq!(Rbfg::is_match("bfg2000"), r.is_match("bfg2000"));
+            assert_eq!(Rbfg::is_match("bfg3"), r.is_match("bfg3"));
+            assert_eq!(Rbfg::is_match("bfg4"), r.is_match("bfg4"));
+            assert_eq!(Rbfg::is_match("bag3000"), r.is_match("bag3000"));
+        }
+
+        parse_regex!(Rkthlund = "kth|lund" $($options)*);
+
+        #[test]
+        fn kthlund() {
+            let r = Regex::new("kth|lund").unwrap();
+
+            assert_eq!(Rkthlund::is_match("kth"), r.is_match("kth"));
+            assert_eq!(Rkthlund::is_match("lund"), r.is_match("lund"));
+            assert_eq!(Rkthlund::is_match("ktha"), r.is_match("ktha"));
+            assert_eq!(Rkthlund::is_match("lunda"), r.is_match("lunda"));
+        }
+
+        parse_regex!(Rwords = "^\\w\\w\\w" $($options)*);
+
+        #[test]
+        fn words() {
+            let r = Regex::new("^\\w\\w\\w").unwrap();
+
+            assert_eq!(Rwords::is_match("abc"), r.is_match("abc"));
+            assert_eq!(Rwords::is_match("a_1"), r.is_match("a_1"));
+            assert_eq!(Rwords::is_match("ab"), r.is_match("ab"));
+            assert_eq!(Rwords::is_match("a-b"), r.is_match("a-b"));
+            assert_eq!(Rwords::is_match("éßø"), r.is_match("éßø"));
+            assert_eq!(Rwords::is_match("日本語"), r.is_match("日本語"));
+            assert_eq!(Rwords::is_match("日本 "), r.is_match("日本 "));
+            assert_eq!(Rwords::is_match("\u{2028}ab"), r.is_match("\u{2028}ab"));
+        }
+
+        parse_regex!(Rletters = "^\\p{L}[^\\w\\d_\\-\\.%][\\p{Greek}\\d]" $($options)*);
+
+        #[test]
+        fn letters() {
+            let r = Regex::new("^\\p{L}[^\\w\\d_\\-\\.%][\\p{Greek}\\d]").unwrap();
+
+            assert_eq!(Rletters::is_match("a/1"), r.is_match("a/1"));
+            assert_eq!(Rletters::is_match("ä λ"), r.is_match("ä λ"));
+            assert_eq!(Rletters::is_match("a%1"), r.is_match("a%1"));
+            assert_eq!(Rletters::is_match("1/1"), r.is_match("1/1"));
+            assert_eq!(Rletters::is_match("ж€a"), r.is_match("ж€a"));
+            assert_eq!(Rletters::is_match("ж€٣"), r.is_match("ж€٣"));
+        }
 
-parse_regex!(Rkthlund = "kth|lund");
+        parse_regex!(Rcounted = "^a{2,}b{1,3}?c" $($options)*);
 
-#[test]
-fn kthlund() {
-    let r = Regex::new("kth|lund").unwrap();
+        #[test]
+        fn counted() {
+            let r = Regex::new("^a{2,}b{1,3}?c").unwrap();
+
+            assert_eq!(Rcounted::is_match("abc"), r.is_match("abc"));
+            assert_eq!(Rcounted::is_match("aabc"), r.is_match("aabc"));
+            assert_eq!(Rcounted::is_match("aaaabbbc"), r.is_match("aaaabbbc"));
+            assert_eq!(Rcounted::is_match("aaaabbbbc"), r.is_match("aaaabbbbc"));
+            assert_eq!(Rcounted::is_match("aac"), r.is_match("aac"));
+        }
+
+        parse_regex!(Rhashes = "^[a-f0-9]{45,}/[a-f,0-9]{8,10}" $($options)*);
+
+        #[test]
+        fn hashes() {
+            let r = Regex::new("^[a-f0-9]{45,}/[a-f,0-9]{8,10}").unwrap();
+            let hash = "0123456789abcdef".repeat(3);
+
+            for input in [
+                format!("{}/01234567", &hash[..44]),
+                format!("{}/01234567", &hash[..45]),
+                format!("{}/0123456", &hash),
+                format!("{}/0,23,567,9", &hash),
+                format!("{}/", &hash),
+            ] {
+                assert_eq!(Rhashes::is_match(&input), r.is_match(&input), "{input}");
+            }
+        }
+
+        parse_regex!(Rthousand = "^(?:ab){1000}c" $($options)*);
+
+        #[test]
+        fn thousand() {
+            let r = Regex::new("^(?:ab){1000}c").unwrap();
+
+            for input in [
+                "ab".repeat(1000) + "c",
+                "ab".repeat(999) + "c",
+                "ab".repeat(1001) + "c",
+            ] {
+                assert_eq!(Rthousand::is_match(&input), r.is_match(&input));
+            }
+        }
+
+        parse_regex!(Rgiveback = "^ä{2,5}äb" $($options)*);
+
+        #[test]
+        fn giveback() {
+            let r = Regex::new("^ä{2,5}äb").unwrap();
+
+            for n in 0..8 {
+                let input = "ä".repeat(n) + "b";
+
+                assert_eq!(Rgiveback::is_match(&input), r.is_match(&input), "{input}");
+            }
+        }
+    };
+}
+
+mod backtrack {
+    tests!();
+}
 
-    assert_eq!(Rkthlund::is_match("kth"), r.is_match("kth"));
-    assert_eq!(Rkthlund::is_match("lund"), r.is_match("lund"));
-    assert_eq!(Rkthlund::is_match("ktha"), r.is_match("ktha"));
-    assert_eq!(Rkthlund::is_match("lunda"), r.is_match("lunda"));
+mod structured {
+    tests!(, engine = structured);
 }
@@ -0,0 +1,53 @@
+/// The tests, with the options of the engine to run them on
+macro_rules! tests {
+    ($($options:tt)*) => {
+        use compiled_regex::parse_regex;
+        use regex::Regex;
+
+        // Each pattern is redundant in a way the simplification removes, and
+        // tries its ways of matching in an order that has to be kept
+        parse_regex!(Domains = r"(?:a\.com|a\.net|a\.co|b\.com)m?/" $($options)*);
+        parse_regex!(Prefixes = r"foo|foobar|fob" $($options)*);
+        parse_regex!(Repeated = r"a*a*b|a+?a*?c" $($options)*);
+        parse_regex!(Groups = r"((a)|(b))((c))|(d)" $($options)*);
+        parse_regex!(Duplicates = r"(?:ab|a|ab)(?:bc|c)" $($options)*);
+        parse_regex!(Ambiguous = r"(?:a+b|a+c)|(?:x?y|x?z)" $($options)*);
+
+        #[test]
+        fn same_as_regex() {
+            let cases = [
+                (Domains::find as fn(&str) -> Option<compiled_regex::Match>, r"(?:a\.com|a\.net|a\.co|b\.com)m?/"),
+                (Prefixes::find, r"foo|foobar|fob"),
+                (Repeated::find, r"a*a*b|a+?a*?c"),
+                (Groups::find, r"((a)|(b))((c))|(d)"),
+                (Duplicates::find, r"(?:ab|a|ab)(?:bc|c)"),
+                (Ambiguous::find, r"(?:a+b|a+c)|(?:x?y|x?z)"),
+            ];
+            let inputs = [
+                "", "a.com/", "a.comm/", "a.co/", "a.com", "xa.net/", "b.com/",
+                "foo", "foobar", "fob", "fo", "aaab", "aaac", "ac", "b", "bc", "d",
+                "abc", "abbc", "ac", "aaz", "xz", "xy", "z",
+            ];
+
+            for (find, pattern) in cases {
+                let regex = Regex::new(pattern).unwrap();
+
+                for input in inputs {
+                    assert_eq!(
+                        find(input).map(|m| m.range()),
+                        regex.find(input).map(|m| m.range()),
+                        "{pattern}: {input:?}"
+                    );
+                }
+            }
+        }
+    };
+}
+
+mod backtrack {
+    tests!();
+}
+
+mod structured {
+    tests!(, engine = structured);
+}
@@ -0,0 +1,129 @@
+/// The tests, with the options of the engine to run them on
+macro_rules! tests {
+    ($($options:tt)*) => {
+        use compiled_regex::parse_regex;
+        use regex::Regex;
+
+        // Not UTF-8, the Latin-1 characters get replaced
+        const HTML: &[u8] = include_bytes!("../data/cp.html");
+
+        fn span(m: Option<compiled_regex::Match>) -> Option<(usize, usize)> {
+            m.map(|m| (m.start(), m.end()))
+        }
+
+        fn regex_span(r: &Regex, input: &str) -> Option<(usize, usize)> {
+            r.find(input).map(|m| (m.start(), m.end()))
+        }
+
+        parse_regex!(Rlazytag = "<.*?>" $($options)*);
+        parse_regex!(Rgreedytag = "<.*>" $($options)*);
+
+        #[test]
+        fn tags() {
+            let lazy = Regex::new("<.*?>").unwrap();
+            let greedy = Regex::new("<.*>").unwrap();
+
+            for input in ["<a><b>", "x <a> <b> y", "<a", "no tags", ""] {
+                assert_eq!(span(Rlazytag::find(input)), regex_span(&lazy, input));
+                assert_eq!(span(Rgreedytag::find(input)), regex_span(&greedy, input));
+            }
+        }
+
+        #[test]
+        fn html_tags() {
+            let lazy = Regex::new("<.*?>").unwrap();
+            let html = String::from_utf8_lossy(HTML);
+            let mut offset = 0;
+
+            // Walk the file match by match, comparing every span
+            while offset < html.len() {
+                let input = &html[offset..];
+                let expected = regex_span(&lazy, input);
+
+                assert_eq!(span(Rlazytag::find(input)), expected, "at {offset}");
+
+                match expected {
+                    Some((_, end)) if end > 0 => offset += end,
+                    _ => break,
+                }
+            }
+        }
+
+        parse_regex!(Rlazyplus = "a+?" $($options)*);
+        parse_regex!(Rswapped = "(?U)a+" $($options)*);
+        parse_regex!(Rswappedlazy = "(?U)a+?" $($options)*);
+        parse_regex!(Rlazycounted = "ba{2,5}?" $($options)*);
+        parse_regex!(Rlazyoptional = "ab??" $($options)*);
+        parse_regex!(Rlazyalternation = "(?:a|ab)(?:c|bcd)??" $($options)*);
+
+        #[test]
+        fn lazy() {
+            let cases = [
+                ("a+?", Rlazyplus::find as fn(&str) -> Option<compiled_regex::Match>),
+                ("(?U)a+", Rswapped::find),
+                ("(?U)a+?", Rswappedlazy::find),
+                ("ba{2,5}?", Rlazycounted::find),
+                ("ab??", Rlazyoptional::find),
+                ("(?:a|ab)(?:c|bcd)??", Rlazyalternation::find),
+            ];
+
+            for (pattern, find) in cases {
+                let r = Regex::new(pattern).unwrap();
+
+                for input in ["aaa", "xbaaaaaa", "ab", "abcd", "abc", "b"] {
+                    assert_eq!(
+                        span(find(input)),
+                        regex_span(&r, input),
+                        "{pattern} on {input}"
+                    );
+                }
+            }
+        }
+
+        parse_regex!(Rwordboundary = "\\bfoo\\b" $($options)*);
+        parse_regex!(Rmultiline = "(?m)^line\\d$" $($options)*);
+        parse_regex!(Rend = "\\d+$" $($options)*);
+
+        #[test]
+        fn assertions() {
+            let word = Regex::new("\\bfoo\\b").unwrap();
+            let multiline = Regex::new("(?m)^line\\d$").unwrap();
+            let end = Regex::new("\\d+$").unwrap();
+
+            for input in ["foo", "a foo b", "foobar", "éfoo", "x-foo-y", "line1", "a\nline2\nb", "line3x\nline4", "12 34", "12 34a"] {
+                assert_eq!(span(Rwordboundary::find(input)), regex_span(&word, input), "{input}");
+                assert_eq!(span(Rmultiline::find(input)), regex_span(&multiline, input), "{input}");
+                assert_eq!(span(Rend::find(input)), regex_span(&end, input), "{input}");
+            }
+        }
+
+        parse_regex!(Rcrlfstart = "(?Rm)^b" $($options)*);
+        parse_regex!(Rcrlfend = "(?Rm)a$" $($options)*);
+        parse_regex!(Rcrlfempty = "(?Rm)^$" $($options)*);
+
+        #[test]
+        fn crlf_lines() {
+            let cases = [
+                (Rcrlfstart::find as fn(&str) -> Option<compiled_regex::Match>, "(?Rm)^b"),
+                (Rcrlfend::find, "(?Rm)a$"),
+                (Rcrlfempty::find, "(?Rm)^$"),
+            ];
+
+            for (find, pattern) in cases {
+                let r = Regex::new(pattern).unwrap();
+
+                for input in ["a\rb", "a\nx", "a\r\nb", "xa\r\n", "\r\n", "\n\r", "a\r", "\rb", "ab", ""] {
+                    assert_eq!(span(find(input)), regex_span(&r, input), "{pattern} in {input:?}");
+                }
+            }
+        }
+    };
+}
+
+mod backtrack {
+    tests!();
+}
+
+mod structured {
+    tests!(, engine = structured);
+}
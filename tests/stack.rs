@@ -0,0 +1,113 @@
+use std::thread;
+
+use compiled_regex::{parse_regex, StackExhausted};
+use regex::Regex;
+
+// Small enough that a call per character of the input would overflow it
+const STACK_SIZE: usize = 128 * 1024;
+
+fn on_small_stack<F: FnOnce() + Send + 'static>(f: F) {
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+parse_regex!(Rlongest = "^(?:a|b)*c");
+parse_regex!(Rlazyrest = "x.*?y");
+parse_regex!(Rcounted = "^[ab]{2,}?c");
+
+#[test]
+fn long_inputs() {
+    on_small_stack(|| {
+        let input = "ab".repeat(500_000) + "c";
+        let r = Regex::new("^(?:a|b)*c").unwrap();
+
+        assert_eq!(Rlongest::is_match(&input), r.is_match(&input));
+        assert!(!Rlongest::is_match(&input[..input.len() - 1]));
+
+        let input = format!("x{}y", "-".repeat(1_000_000));
+        let m = Rlazyrest::find(&input).unwrap();
+        assert_eq!((m.start(), m.end()), (0, input.len()));
+
+        let input = "ab".repeat(100_000) + "c";
+        assert!(Rcounted::is_match(&input));
+        assert!(!Rcounted::is_match(&input[..input.len() - 1]));
+    });
+}
+
+parse_regex!(Rlimited = "^(?:a|b)*c", stack_limit = 64);
+
+#[test]
+fn stack_limit() {
+    assert_eq!(Rlimited::try_is_match("abbac"), Ok(true));
+    assert_eq!(Rlimited::try_is_match("abbad"), Ok(false));
+    assert!(Rlimited::is_match("abbac"));
+
+    let input = "ab".repeat(100) + "c";
+    assert_eq!(Rlimited::try_is_match(&input), Err(StackExhausted));
+    assert_eq!(Rlimited::try_find(&input), Err(StackExhausted));
+}
+
+#[test]
+#[should_panic(expected = "backtracking stack limit exceeded")]
+fn stack_limit_panics() {
+    Rlimited::is_match(&"ab".repeat(100));
+}
+
+parse_regex!(Rrecursive = "^(?:a|b)*c", engine = recursive);
+
+#[test]
+fn recursive_engine() {
+    let r = Regex::new("^(?:a|b)*c").unwrap();
+
+    for input in ["", "c", "abc", "abba", "xabc", "ababab c"] {
+        assert_eq!(Rrecursive::is_match(input), r.is_match(input));
+    }
+}
+
+parse_regex!(Rstarstar = "(a*)*b");
+parse_regex!(Roptional = "(?:a?)*b");
+parse_regex!(Ralternative = "(?:a|b?)*c");
+parse_regex!(Rplus = "(?:x?)+y");
+parse_regex!(Rlimitedstar = "(a*)*b", stack_limit = 64);
+parse_regex!(Rlimitedoptional = "(?:x?)*y", stack_limit = 64);
+parse_regex!(Rstructuredstar = "(a*)*b", engine = structured);
+parse_regex!(Rstructuredoptional = "(?:a?)*b", engine = structured);
+parse_regex!(Rstructuredalternative = "(?:a|b?)*c", engine = structured);
+parse_regex!(Rstructuredplus = "(?:x?)+y", engine = structured);
+
+#[test]
+fn empty_loop_bodies() {
+    let cases = [
+        (Rstarstar::find as fn(&str) -> Option<compiled_regex::Match>, "(a*)*b"),
+        (Roptional::find, "(?:a?)*b"),
+        (Ralternative::find, "(?:a|b?)*c"),
+        (Rplus::find, "(?:x?)+y"),
+        (Rstructuredstar::find, "(a*)*b"),
+        (Rstructuredoptional::find, "(?:a?)*b"),
+        (Rstructuredalternative::find, "(?:a|b?)*c"),
+        (Rstructuredplus::find, "(?:x?)+y"),
+    ];
+    let inputs = ["", "b", "ab", "aab", "aaa", "zzz", "c", "abbac", "xy", "y", "xxy", "xxz"];
+
+    for (find, pattern) in cases {
+        let r = Regex::new(pattern).unwrap();
+
+        for input in inputs {
+            assert_eq!(
+                find(input).map(|m| m.range()),
+                r.find(input).map(|m| m.range()),
+                "{pattern} in {input:?}"
+            );
+        }
+    }
+
+    assert_eq!(Rlimitedstar::try_find("b").map(|m| m.map(|m| m.range())), Ok(Some(0..1)));
+    assert_eq!(Rlimitedstar::try_find("aaab").map(|m| m.map(|m| m.range())), Ok(Some(0..4)));
+    assert_eq!(Rlimitedstar::try_is_match("zzz"), Ok(false));
+    assert_eq!(Rlimitedoptional::try_find("zzy").map(|m| m.map(|m| m.range())), Ok(Some(2..3)));
+    assert_eq!(Rlimitedoptional::try_is_match("zzz"), Ok(false));
+}
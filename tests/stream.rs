@@ -0,0 +1,112 @@
+#![cfg(feature = "std")]
+
+use std::io::{self, Read};
+
+use compiled_regex::parse_regex;
+use regex::Regex;
+
+const ALICE: &str = include_str!("../data/alice29.txt");
+
+parse_regex!(Word = r"\b[A-Z][a-z]+\b", stream = true);
+parse_regex!(Sentence = r"[A-Z][^.!?]*[.!?]", stream = true);
+parse_regex!(Line = r"(?m)^Alice.*$", stream = true);
+parse_regex!(Empty = "a*", stream = true);
+parse_regex!(Anchored = "^The|Project", stream = true);
+parse_regex!(Greek = r"αβ+|γ", stream = true, engine = recursive);
+parse_regex!(Quote = r#"'[^']*'|"[^"]*""#, stream = true);
+
+/// Reader giving out at most `size` bytes at a time, splitting the
+/// characters of the text
+struct Chunks<'a> {
+    text: &'a [u8],
+    size: usize,
+}
+
+impl Read for Chunks<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.size.min(buf.len()).min(self.text.len());
+
+        buf[..len].copy_from_slice(&self.text[..len]);
+        self.text = &self.text[len..];
+
+        Ok(len)
+    }
+}
+
+fn stream_ranges<I>(matches: I) -> Vec<(usize, usize, String)>
+where
+    I: Iterator<Item = io::Result<compiled_regex::StreamMatch>>,
+{
+    matches
+        .map(|m| {
+            let m = m.unwrap();
+
+            (m.start(), m.end(), String::from(m.as_str()))
+        })
+        .collect()
+}
+
+fn regex_ranges(pattern: &str, text: &str) -> Vec<(usize, usize, String)> {
+    Regex::new(pattern)
+        .unwrap()
+        .find_iter(text)
+        .map(|m| (m.start(), m.end(), String::from(m.as_str())))
+        .collect()
+}
+
+macro_rules! assert_same {
+    ($name:ident, $pattern:expr, $text:expr) => {
+        let expected = regex_ranges($pattern, $text);
+
+        for size in [1, 3, 4096] {
+            let reader = Chunks {
+                text: $text.as_bytes(),
+                size,
+            };
+
+            assert_eq!(
+                stream_ranges($name::stream_find(reader)),
+                expected,
+                "{} in chunks of {size}",
+                $pattern
+            );
+        }
+    };
+}
+
+#[test]
+fn same_as_regex() {
+    let greek = "αββ γ αγ ββ ααββββ";
+
+    assert_same!(Word, r"\b[A-Z][a-z]+\b", ALICE);
+    assert_same!(Sentence, r"[A-Z][^.!?]*[.!?]", ALICE);
+    assert_same!(Line, r"(?m)^Alice.*$", ALICE);
+    assert_same!(Quote, r#"'[^']*'|"[^"]*""#, ALICE);
+    assert_same!(Anchored, "^The|Project", ALICE);
+    assert_same!(Greek, r"αβ+|γ", greek);
+    assert_same!(Empty, "a*", "baaab aa");
+    assert_same!(Empty, "a*", "");
+    assert_same!(Empty, "a*", "ä");
+}
+
+#[test]
+fn offsets_span_the_whole_stream() {
+    let text = "x".repeat(100_000) + "\nAlice";
+    let matches = stream_ranges(Line::stream_find(text.as_bytes()));
+
+    assert_eq!(matches, [(100_001, 100_006, String::from("Alice"))]);
+}
+
+#[test]
+fn invalid_utf8() {
+    let mut matches = Word::stream_find(&b"Hello W\xFFrld Again"[..]);
+
+    assert_eq!(matches.next().unwrap().unwrap().as_str(), "Hello");
+    assert_eq!(matches.next().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert!(matches.next().is_none());
+
+    // A character cut off by the end of the stream
+    let mut matches = Greek::stream_find(&"αβ".as_bytes()[..3]);
+
+    assert_eq!(matches.next().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
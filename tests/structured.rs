@@ -0,0 +1,75 @@
+use std::thread;
+
+use compiled_regex::{parse_regex, Match};
+use regex::Regex;
+
+// The other tests run on the structured engine too, these are loops the
+// sections have to nest, or whose bodies are not characters
+parse_regex!(Rambiguous = "(a|a)*b", engine = structured);
+parse_regex!(Roverlapping = "(?:a|aa)*c", engine = structured);
+parse_regex!(Rlongest = "^(?:a|b)*c", engine = structured);
+parse_regex!(Rnested = "(?:a*b)*c", engine = structured);
+parse_regex!(Rnestedlazy = "(?:a*?b)+?c", engine = structured);
+parse_regex!(Rchoices = "(?:ab|a)+b", engine = structured);
+parse_regex!(Rgroupsplus = "(?:(?:ab)+c)*d", engine = structured);
+parse_regex!(Roptionalloop = "x(?:y(?:z|w)*)?v", engine = structured);
+parse_regex!(Rpairs = "(?:ab|cd)+", engine = structured);
+parse_regex!(Rempty = "(?:x?)*y", engine = structured);
+
+type Find = fn(&str) -> Option<Match>;
+
+const CASES: &[(Find, &str)] = &[
+    (Rambiguous::find, "(a|a)*b"),
+    (Roverlapping::find, "(?:a|aa)*c"),
+    (Rlongest::find, "^(?:a|b)*c"),
+    (Rnested::find, "(?:a*b)*c"),
+    (Rnestedlazy::find, "(?:a*?b)+?c"),
+    (Rchoices::find, "(?:ab|a)+b"),
+    (Rgroupsplus::find, "(?:(?:ab)+c)*d"),
+    (Roptionalloop::find, "x(?:y(?:z|w)*)?v"),
+    (Rpairs::find, "(?:ab|cd)+"),
+    (Rempty::find, "(?:x?)*y"),
+];
+
+#[test]
+fn same_as_regex() {
+    let inputs = [
+        "", "a", "aaaa", "aaab", "aaaac", "ac", "abbc", "ababc", "aabaabc",
+        "abababb", "ababcd", "ababcababcd", "xyzwzv", "xv", "xyv", "xyzq",
+        "zabcdab", "cdcx", "zzz", "xxy", "zxy", "y",
+    ];
+
+    for &(find, pattern) in CASES {
+        let regex = Regex::new(pattern).unwrap();
+
+        for input in inputs {
+            assert_eq!(
+                find(input).map(|m| m.range()),
+                regex.find(input).map(|m| m.range()),
+                "{pattern}: {input:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn native_loops() {
+    // Loops do not call a function per repetition, so long inputs fit
+    // on a small stack
+    thread::Builder::new()
+        .stack_size(128 * 1024)
+        .spawn(|| {
+            let input = "ab".repeat(500_000) + "c";
+
+            assert!(Rlongest::is_match(&input));
+            assert!(!Rlongest::is_match(&input[..input.len() - 1]));
+
+            let input = "ab".repeat(200_000);
+
+            assert_eq!(Rpairs::find(&input).unwrap().range(), 0..input.len());
+            assert!(!Rempty::is_match(&"z".repeat(200_000)));
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
@@ -2,7 +2,7 @@ use regex::Regex;
 
 use compiled_regex::parse_regex;
 
-const TEST_INPUTS_RAW: &'static str = include_str!("../data/keggle_urldata_urls.txt");
+const TEST_INPUTS_RAW: &str = include_str!("../data/keggle_urldata_urls.txt");
 
 fn get_lines() -> Vec<&'static str> {
     TEST_INPUTS_RAW.split("\n").collect()
@@ -0,0 +1,57 @@
+use std::borrow::Cow;
+
+use compiled_regex::{FieldError, ValidatePatterns};
+
+#[derive(ValidatePatterns, Debug)]
+struct Signup {
+    #[pattern("^[a-z0-9_]{3,16}$")]
+    username: String,
+    /// Checked with both patterns
+    #[pattern(r"^\S+@\S+$")]
+    #[pattern(r"\.(?:com|org)$", engine = recursive)]
+    email: String,
+    #[pattern("^[A-Z]{2}$")]
+    country: Option<String>,
+    age: u8,
+}
+
+#[derive(ValidatePatterns)]
+struct Borrowed<'a, T: Clone>(#[pattern("^v[0-9]+$")] &'a str, #[pattern("x")] Cow<'a, str>, T);
+
+#[test]
+fn valid() {
+    let signup = Signup {
+        username: String::from("some_one"),
+        email: String::from("someone@example.com"),
+        country: None,
+        age: 30,
+    };
+
+    assert_eq!(signup.validate(), Ok(()));
+    assert_eq!(signup.age, 30);
+    assert!(Borrowed("v2", Cow::from("xyz"), ()).validate().is_ok());
+}
+
+#[test]
+fn invalid() {
+    let signup = Signup {
+        username: String::from("Some One"),
+        email: String::from("someone@example.net"),
+        country: Some(String::from("Sweden")),
+        age: 30,
+    };
+
+    assert_eq!(
+        signup.validate(),
+        Err(vec![
+            FieldError::new("username", "^[a-z0-9_]{3,16}$"),
+            FieldError::new("email", r"\.(?:com|org)$"),
+            FieldError::new("country", "^[A-Z]{2}$"),
+        ])
+    );
+
+    let errors = Borrowed("v", Cow::from("y"), 1).validate().unwrap_err();
+
+    assert_eq!(errors.iter().map(FieldError::field).collect::<Vec<_>>(), ["0", "1"]);
+    assert_eq!(errors[0].to_string(), "`0` does not match `^v[0-9]+$`");
+}
@@ -0,0 +1,34 @@
+use compiled_regex::parse_regex;
+
+// The test vectors are checked while the macros expand, and again by the
+// tests they generate
+parse_regex!(
+    Ipv4 = r"^(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)$",
+    matches = ["10.0.0.1", "255.255.255.255", "0.0.0.0"],
+    rejects = ["256.1.1.1", "1.2.3", "1.2.3.4.5", ""],
+);
+parse_regex!(Word = r"\bfoo\b", matches = ["a foo b"], rejects = ["food"]);
+parse_regex!(
+    Hex = "0x[0-9a-f]{2,}",
+    engine = recursive,
+    matches = ["0xff", "=0x0a1"],
+    rejects = ["0x", "0xg1"],
+);
+parse_regex!(Method = "GET|POST|PUT", rejects = ["get"], matches = ["POST"]);
+// A loop whose body matches the empty string ends in the generated code
+// as it does in the program run while expanding
+parse_regex!(Nested = "(?:a*)*b", matches = ["aaab", "b"], rejects = ["aaaa", ""]);
+
+#[test]
+fn types() {
+    assert!(Ipv4::is_match("192.168.0.1"));
+    assert!(Hex::find("x = 0xbeef").is_some());
+}
+
+#[test]
+fn cross_check() {
+    // The tests of the test vectors compare to `regex::Regex`
+    assert_eq!(compiled_regex::regex_is_match("(?:a*)*b", "aab"), Some(true));
+    assert_eq!(compiled_regex::regex_is_match(r"\bfoo\b", "food"), Some(false));
+    assert_eq!(compiled_regex::regex_is_match("(", "("), None);
+}